@@ -1,13 +1,18 @@
 //! Deploys Anchor Protocol contracts to livenet/testnet using Odra CLI.
 
-use anchor_protocol::a_token::{AToken, ATokenInitArgs};
-use anchor_protocol::lending_market::{LendingMarket, LendingMarketHostRef, LendingMarketInitArgs};
-use anchor_protocol::market_registry::{MarketRegistry, MarketRegistryInitArgs};
+use anchor_protocol::a_token::{AToken, ATokenFactory, ATokenInitArgs};
+use anchor_protocol::lending_market::{
+    LendingMarket, LendingMarketFactory, LendingMarketHostRef, LendingMarketInitArgs,
+};
+use anchor_protocol::market_registry::{
+    MarketRegistry, MarketRegistryHostRef, MarketRegistryInitArgs,
+};
 use anchor_protocol::math::WAD_U128;
 use anchor_protocol::price_oracle::{PriceOracle, PriceOracleHostRef, PriceOracleInitArgs};
-use anchor_protocol::types::{RateModel, RiskParams};
+use anchor_protocol::types::{MarketConfig, PauseFlags, RateModel, RiskParams};
+use anchor_protocol::variable_debt_token::{VariableDebtToken, VariableDebtTokenInitArgs};
 use odra::casper_types::U256;
-use odra::host::{Deployer, HostEnv, HostRefLoader};
+use odra::host::{Deployer, HostEnv, HostRefLoader, InstallConfig, NoArgs};
 use odra::prelude::*;
 use odra::schema::casper_contract_schema::NamedCLType;
 use odra_cli::{
@@ -72,22 +77,37 @@ impl DeployScript for AnchorDeployScript {
             GAS,
         )?;
 
+        // `add-market` lists markets through `create_market`, which deploys
+        // from these factories.
+        env.set_gas(GAS);
+        let market_factory = LendingMarketFactory::load_or_deploy(env, NoArgs, container, GAS)?;
+        env.set_gas(GAS);
+        let a_token_factory = ATokenFactory::load_or_deploy(env, NoArgs, container, GAS)?;
+        if registry.get_market_templates().is_none() {
+            env.set_gas(GAS);
+            registry.set_market_templates(market_factory.address(), a_token_factory.address());
+        }
+
         if core_only {
             return Ok(());
         }
 
         env.set_gas(GAS);
-        let mut market = LendingMarket::load_or_deploy(
+        let mut market = LendingMarket::load_or_deploy_with_cfg(
             env,
+            None,
             LendingMarketInitArgs {
-                admin,
-                asset,
-                a_token: admin,
-                oracle: oracle.address(),
-                registry: registry.address(),
-                rate_model,
-                risk_params,
+                config: MarketConfig {
+                    admin,
+                    asset,
+                    a_token: admin,
+                    oracle: oracle.address(),
+                    registry: registry.address(),
+                    rate_model,
+                    risk_params,
+                },
             },
+            InstallConfig::upgradable::<LendingMarket>(),
             container,
             GAS,
         )?;
@@ -109,13 +129,22 @@ impl DeployScript for AnchorDeployScript {
             GAS,
         )?;
 
+        env.set_gas(GAS);
+        let debt_token = VariableDebtToken::load_or_deploy(
+            env,
+            debt_token_init_args(decimals, market.address()),
+            container,
+            GAS,
+        )?;
+
         env.set_gas(GAS);
         market.set_a_token(a_token.address());
-        market.set_registry(registry.address());
 
         env.set_caller(oracle_admin);
+        oracle.register_asset(asset);
         oracle.set_price(asset, asset_price);
         registry.register_market(asset, market.address(), a_token.address(), oracle.address());
+        registry.link_debt_token(asset, debt_token.address());
 
         Ok(())
     }
@@ -164,49 +193,30 @@ impl Scenario for AddMarketScenario {
         let mut registry = container.contract_ref::<MarketRegistry>(env)?;
         let mut oracle = container.contract_ref::<PriceOracle>(env)?;
 
-        env.set_gas(GAS);
-        let mut market = LendingMarket::deploy(
-            env,
-            LendingMarketInitArgs {
-                admin,
-                asset,
-                a_token: admin,
-                oracle: oracle.address(),
-                registry: registry.address(),
-                rate_model,
-                risk_params,
-            },
-        );
-
-        let name = env_var("ANCHOR_ATOKEN_NAME").unwrap_or_else(|| "Anchor Token".to_string());
-        let symbol = env_var("ANCHOR_ATOKEN_SYMBOL").unwrap_or_else(|| "aTKN".to_string());
-        let decimals = parse_u8("ANCHOR_ATOKEN_DECIMALS", 9);
-
-        env.set_gas(GAS);
-        let a_token = AToken::deploy(
-            env,
-            ATokenInitArgs {
-                name,
-                symbol,
-                decimals,
-                market: market.address(),
-            },
-        );
-
-        env.set_gas(GAS);
-        env.set_caller(admin);
-        market.set_a_token(a_token.address());
-        market.set_registry(registry.address());
-
+        env.set_caller(oracle_admin);
+        oracle.register_asset(asset);
         if let Some(price_value) = env_var("ANCHOR_ASSET_PRICE_WAD") {
             if let Ok(price) = U256::from_dec_str(&price_value) {
-                env.set_caller(oracle_admin);
                 oracle.set_price(asset, price);
             }
         }
 
+        let name = env_var("ANCHOR_ATOKEN_NAME").unwrap_or_else(|| "Anchor Token".to_string());
+        let symbol = env_var("ANCHOR_ATOKEN_SYMBOL").unwrap_or_else(|| "aTKN".to_string());
+
+        // Deploys the market and aToken from the registry's factories, wires
+        // them and registers both in one transaction.
         env.set_caller(admin);
-        registry.register_market(asset, market.address(), a_token.address(), oracle.address());
+        env.set_gas(GAS);
+        let addresses =
+            registry.create_market(asset, oracle.address(), rate_model, risk_params, name, symbol);
+
+        let decimals = AToken::load(env, addresses.a_token).decimals();
+        env.set_gas(GAS);
+        let debt_token =
+            VariableDebtToken::deploy(env, debt_token_init_args(decimals, addresses.market));
+        env.set_gas(GAS);
+        registry.link_debt_token(asset, debt_token.address());
 
         Ok(())
     }
@@ -380,8 +390,6 @@ impl Scenario for LiquidateScenario {
     fn args(&self) -> Vec<CommandArg> {
         vec![
             CommandArg::new("asset", "Asset address (hash-...)", NamedCLType::String),
-            CommandArg::new("deposit", "Borrower deposit (U256)", NamedCLType::String),
-            CommandArg::new("borrow", "Borrow amount (U256)", NamedCLType::String),
             CommandArg::new("repay", "Liquidator repay amount (U256)", NamedCLType::String),
         ]
     }
@@ -393,69 +401,18 @@ impl Scenario for LiquidateScenario {
         args: Args,
     ) -> Result<(), Error> {
         let asset = parse_asset_arg(&args);
-        let deposit = parse_u256_arg(&args, "deposit");
-        let borrow_amount = parse_u256_arg(&args, "borrow");
         let repay_amount = parse_u256_arg(&args, "repay");
 
-        let mut registry = container.contract_ref::<MarketRegistry>(env)?;
         let (mut market, mut oracle) = load_market_and_oracle(env, container, asset)?;
 
         env.set_gas(GAS);
         refresh_price_if_configured(env, &mut oracle, asset);
 
+        // Risk params only change through the registry timelock, so the position
+        // must already be underwater (e.g. after `update-risk` + `execute-queued`).
         let borrower = env.get_account(0);
         let liquidator = env.get_account(0);
 
-        env.set_caller(env.get_account(0));
-        env.set_gas(GAS);
-        let normal_collateral =
-            parse_u256("ANCHOR_COLLATERAL_FACTOR", U256::from(WAD_U128 * 75 / 100));
-        let normal_threshold =
-            parse_u256("ANCHOR_LIQ_THRESHOLD", U256::from(WAD_U128 * 80 / 100));
-        let normal_close = parse_u256("ANCHOR_CLOSE_FACTOR", U256::from(WAD_U128 / 2));
-        let normal_bonus = parse_u256("ANCHOR_LIQ_BONUS", U256::from(WAD_U128 * 5 / 100));
-        let normal_reserve = parse_u256("ANCHOR_RESERVE_FACTOR", U256::from(WAD_U128 / 10));
-        let normal_borrow_cap = parse_u256("ANCHOR_BORROW_CAP", U256::zero());
-        let normal_supply_cap = parse_u256("ANCHOR_SUPPLY_CAP", U256::zero());
-        registry.update_market_risk_params(
-            asset,
-            normal_collateral,
-            normal_threshold,
-            normal_close,
-            normal_bonus,
-            normal_reserve,
-            normal_borrow_cap,
-            normal_supply_cap,
-        );
-
-        env.set_caller(borrower);
-        env.set_gas(GAS);
-        market.deposit(deposit);
-        env.set_gas(GAS);
-        market.borrow(borrow_amount);
-
-        env.set_caller(env.get_account(0));
-        env.set_gas(GAS);
-        let scenario_collateral =
-            parse_u256("ANCHOR_LIQ_SCENARIO_COLLATERAL_FACTOR", U256::from(WAD_U128 / 10));
-        let scenario_threshold =
-            parse_u256("ANCHOR_LIQ_SCENARIO_THRESHOLD", U256::from(WAD_U128 / 10));
-        let scenario_close = parse_u256("ANCHOR_CLOSE_FACTOR", U256::from(WAD_U128 / 2));
-        let scenario_bonus = parse_u256("ANCHOR_LIQ_BONUS", U256::from(WAD_U128 * 5 / 100));
-        let scenario_reserve = parse_u256("ANCHOR_RESERVE_FACTOR", U256::from(WAD_U128 / 10));
-        let scenario_borrow_cap = parse_u256("ANCHOR_BORROW_CAP", U256::zero());
-        let scenario_supply_cap = parse_u256("ANCHOR_SUPPLY_CAP", U256::zero());
-        registry.update_market_risk_params(
-            asset,
-            scenario_collateral,
-            scenario_threshold,
-            scenario_close,
-            scenario_bonus,
-            scenario_reserve,
-            scenario_borrow_cap,
-            scenario_supply_cap,
-        );
-
         env.set_caller(liquidator);
         env.set_gas(GAS);
         market.liquidate(borrower, repay_amount);
@@ -465,7 +422,7 @@ impl Scenario for LiquidateScenario {
 
 impl ScenarioMetadata for LiquidateScenario {
     const NAME: &'static str = "liquidate";
-    const DESCRIPTION: &'static str = "Deposits, borrows, then liquidates";
+    const DESCRIPTION: &'static str = "Liquidates an underwater position";
 }
 
 pub struct PauseScenario;
@@ -479,6 +436,7 @@ impl Scenario for PauseScenario {
             CommandArg::new("withdraw", "Pause withdraw", NamedCLType::Bool),
             CommandArg::new("repay", "Pause repay", NamedCLType::Bool),
             CommandArg::new("liquidate", "Pause liquidation", NamedCLType::Bool),
+            CommandArg::new("reason", "Pause reason code", NamedCLType::U32),
         ]
     }
 
@@ -489,23 +447,19 @@ impl Scenario for PauseScenario {
         args: Args,
     ) -> Result<(), Error> {
         let asset = parse_asset_arg(&args);
-        let supply_paused = args.get_single::<bool>("supply")?;
-        let borrow_paused = args.get_single::<bool>("borrow")?;
-        let withdraw_paused = args.get_single::<bool>("withdraw")?;
-        let repay_paused = args.get_single::<bool>("repay")?;
-        let liquidation_paused = args.get_single::<bool>("liquidate")?;
+        let flags = PauseFlags {
+            supply_paused: args.get_single::<bool>("supply")?,
+            borrow_paused: args.get_single::<bool>("borrow")?,
+            withdraw_paused: args.get_single::<bool>("withdraw")?,
+            repay_paused: args.get_single::<bool>("repay")?,
+            liquidation_paused: args.get_single::<bool>("liquidate")?,
+        };
+        let reason_code = args.get_single::<u32>("reason")?;
 
         let mut registry = container.contract_ref::<MarketRegistry>(env)?;
         env.set_caller(env.get_account(0));
         env.set_gas(GAS);
-        registry.set_pause_flags(
-            asset,
-            supply_paused,
-            borrow_paused,
-            withdraw_paused,
-            repay_paused,
-            liquidation_paused,
-        );
+        registry.set_pause_flags(asset, flags, reason_code);
         Ok(())
     }
 }
@@ -553,17 +507,19 @@ impl Scenario for UpdateParamsScenario {
         let mut registry = container.contract_ref::<MarketRegistry>(env)?;
         env.set_caller(env.get_account(0));
         env.set_gas(GAS);
-        registry.update_market_rate_model(asset, base_rate, slope_rate);
+        registry.queue_market_rate_model(asset, base_rate, slope_rate);
         env.set_gas(GAS);
-        registry.update_market_risk_params(
+        registry.queue_market_risk_params(
             asset,
-            collateral,
-            liq_threshold,
-            close_factor,
-            liq_bonus,
-            reserve,
-            borrow_cap,
-            supply_cap,
+            RiskParams {
+                collateral_factor: collateral,
+                liquidation_threshold: liq_threshold,
+                close_factor,
+                liquidation_bonus: liq_bonus,
+                reserve_factor: reserve,
+                borrow_cap,
+                supply_cap,
+            },
         );
         Ok(())
     }
@@ -571,7 +527,7 @@ impl Scenario for UpdateParamsScenario {
 
 impl ScenarioMetadata for UpdateParamsScenario {
     const NAME: &'static str = "update-params";
-    const DESCRIPTION: &'static str = "Updates rate model and risk params";
+    const DESCRIPTION: &'static str = "Queues rate model and risk params updates";
 }
 
 pub struct UpdateRiskScenario;
@@ -608,15 +564,17 @@ impl Scenario for UpdateRiskScenario {
         let mut registry = container.contract_ref::<MarketRegistry>(env)?;
         env.set_caller(env.get_account(0));
         env.set_gas(GAS);
-        registry.update_market_risk_params(
+        registry.queue_market_risk_params(
             asset,
-            collateral,
-            liq_threshold,
-            close_factor,
-            liq_bonus,
-            reserve,
-            borrow_cap,
-            supply_cap,
+            RiskParams {
+                collateral_factor: collateral,
+                liquidation_threshold: liq_threshold,
+                close_factor,
+                liquidation_bonus: liq_bonus,
+                reserve_factor: reserve,
+                borrow_cap,
+                supply_cap,
+            },
         );
         Ok(())
     }
@@ -624,7 +582,7 @@ impl Scenario for UpdateRiskScenario {
 
 impl ScenarioMetadata for UpdateRiskScenario {
     const NAME: &'static str = "update-risk";
-    const DESCRIPTION: &'static str = "Updates risk params only";
+    const DESCRIPTION: &'static str = "Queues a risk params update";
 }
 
 pub struct UpdateRiskEnvScenario;
@@ -656,15 +614,17 @@ impl Scenario for UpdateRiskEnvScenario {
         let mut registry = container.contract_ref::<MarketRegistry>(env)?;
         env.set_caller(env.get_account(0));
         env.set_gas(GAS);
-        registry.update_market_risk_params(
+        registry.queue_market_risk_params(
             asset,
-            collateral,
-            liq_threshold,
-            close_factor,
-            liq_bonus,
-            reserve,
-            borrow_cap,
-            supply_cap,
+            RiskParams {
+                collateral_factor: collateral,
+                liquidation_threshold: liq_threshold,
+                close_factor,
+                liquidation_bonus: liq_bonus,
+                reserve_factor: reserve,
+                borrow_cap,
+                supply_cap,
+            },
         );
         Ok(())
     }
@@ -672,7 +632,186 @@ impl Scenario for UpdateRiskEnvScenario {
 
 impl ScenarioMetadata for UpdateRiskEnvScenario {
     const NAME: &'static str = "update-risk-env";
-    const DESCRIPTION: &'static str = "Updates risk params from env values";
+    const DESCRIPTION: &'static str = "Queues a risk params update from env values";
+}
+
+pub struct ExecuteQueuedScenario;
+
+impl Scenario for ExecuteQueuedScenario {
+    fn args(&self) -> Vec<CommandArg> {
+        vec![CommandArg::new("id", "Queued action id", NamedCLType::U64)]
+    }
+
+    fn run(
+        &self,
+        env: &HostEnv,
+        container: &DeployedContractsContainer,
+        args: Args,
+    ) -> Result<(), Error> {
+        let id = args.get_single::<u64>("id")?;
+        let mut registry = container.contract_ref::<MarketRegistry>(env)?;
+        env.set_caller(env.get_account(0));
+        env.set_gas(GAS);
+        registry.execute_queued_action(id);
+        Ok(())
+    }
+}
+
+impl ScenarioMetadata for ExecuteQueuedScenario {
+    const NAME: &'static str = "execute-queued";
+    const DESCRIPTION: &'static str = "Executes a timelocked registry action after its ETA";
+}
+
+pub struct CancelQueuedScenario;
+
+impl Scenario for CancelQueuedScenario {
+    fn args(&self) -> Vec<CommandArg> {
+        vec![CommandArg::new("id", "Queued action id", NamedCLType::U64)]
+    }
+
+    fn run(
+        &self,
+        env: &HostEnv,
+        container: &DeployedContractsContainer,
+        args: Args,
+    ) -> Result<(), Error> {
+        let id = args.get_single::<u64>("id")?;
+        let mut registry = container.contract_ref::<MarketRegistry>(env)?;
+        env.set_caller(env.get_account(0));
+        env.set_gas(GAS);
+        registry.cancel_queued_action(id);
+        Ok(())
+    }
+}
+
+impl ScenarioMetadata for CancelQueuedScenario {
+    const NAME: &'static str = "cancel-queued";
+    const DESCRIPTION: &'static str = "Cancels a timelocked registry action";
+}
+
+/// Installs the current `LendingMarket` code over an existing market and runs its migration.
+pub struct UpgradeMarketScenario;
+
+impl Scenario for UpgradeMarketScenario {
+    fn args(&self) -> Vec<CommandArg> {
+        vec![CommandArg::new("asset", "Asset address (hash-...)", NamedCLType::String)]
+    }
+
+    fn run(
+        &self,
+        env: &HostEnv,
+        container: &DeployedContractsContainer,
+        args: Args,
+    ) -> Result<(), Error> {
+        let asset = parse_asset_arg(&args);
+        let registry = container.contract_ref::<MarketRegistry>(env)?;
+        let market_address = registry.get_market_addresses(asset).market;
+
+        env.set_caller(env.get_account(0));
+        env.set_gas(GAS);
+        LendingMarket::try_upgrade(env, market_address, NoArgs)?;
+        Ok(())
+    }
+}
+
+impl ScenarioMetadata for UpgradeMarketScenario {
+    const NAME: &'static str = "upgrade-market";
+    const DESCRIPTION: &'static str = "Upgrades a market's contract code in place";
+}
+
+/// Proposes the multisig as pending admin on the registry, every market and their oracles.
+pub struct ProposeAdminScenario;
+
+impl Scenario for ProposeAdminScenario {
+    fn args(&self) -> Vec<CommandArg> {
+        vec![CommandArg::new(
+            "multisig",
+            "Multisig account (account-hash-...)",
+            NamedCLType::String,
+        )]
+    }
+
+    fn run(
+        &self,
+        env: &HostEnv,
+        container: &DeployedContractsContainer,
+        args: Args,
+    ) -> Result<(), Error> {
+        let multisig_str = args.get_single::<String>("multisig")?;
+        let multisig = Address::from_str(&multisig_str).unwrap();
+        let (mut registry, markets, oracles) = load_admin_contracts(env, container)?;
+
+        env.set_caller(env.get_account(0));
+        env.set_gas(GAS);
+        registry.propose_admin(multisig);
+        for mut market in markets {
+            env.set_gas(GAS);
+            market.propose_admin(multisig);
+        }
+        for mut oracle in oracles {
+            env.set_gas(GAS);
+            oracle.propose_admin(multisig);
+        }
+        Ok(())
+    }
+}
+
+impl ScenarioMetadata for ProposeAdminScenario {
+    const NAME: &'static str = "propose-admin";
+    const DESCRIPTION: &'static str = "Proposes a multisig as admin of every contract";
+}
+
+/// Accepts a pending handover as the multisig and moves the operational roles to it.
+///
+/// The multisig signs as account `ANCHOR_MULTISIG_ACCOUNT_INDEX` (default 1).
+pub struct AcceptAdminScenario;
+
+impl Scenario for AcceptAdminScenario {
+    fn args(&self) -> Vec<CommandArg> {
+        vec![]
+    }
+
+    fn run(
+        &self,
+        env: &HostEnv,
+        container: &DeployedContractsContainer,
+        _args: Args,
+    ) -> Result<(), Error> {
+        let multisig_index = parse_u64("ANCHOR_MULTISIG_ACCOUNT_INDEX", 1);
+        let multisig = env.get_account(multisig_index as usize);
+        let (mut registry, markets, oracles) = load_admin_contracts(env, container)?;
+        let previous_admin = registry.get_admin();
+
+        env.set_caller(multisig);
+        env.set_gas(GAS);
+        registry.accept_admin();
+        env.set_gas(GAS);
+        registry.grant_protocol_admin(multisig);
+        env.set_gas(GAS);
+        registry.revoke_protocol_admin(previous_admin);
+        for mut market in markets {
+            env.set_gas(GAS);
+            market.accept_admin();
+            env.set_gas(GAS);
+            market.grant_market_admin(multisig);
+            env.set_gas(GAS);
+            market.revoke_market_admin(previous_admin);
+        }
+        for mut oracle in oracles {
+            env.set_gas(GAS);
+            oracle.accept_admin();
+            env.set_gas(GAS);
+            oracle.grant_oracle_admin(multisig);
+            env.set_gas(GAS);
+            oracle.revoke_oracle_admin(previous_admin);
+        }
+        Ok(())
+    }
+}
+
+impl ScenarioMetadata for AcceptAdminScenario {
+    const NAME: &'static str = "accept-admin";
+    const DESCRIPTION: &'static str = "Accepts admin as the multisig and moves operational roles";
 }
 
 fn parse_address(name: &str, fallback: Address) -> Address {
@@ -726,6 +865,34 @@ fn load_market_and_oracle(
     ))
 }
 
+type AdminContracts = (
+    MarketRegistryHostRef,
+    Vec<LendingMarketHostRef>,
+    Vec<PriceOracleHostRef>,
+);
+
+/// Registry plus every registered market and its (deduplicated) oracle.
+fn load_admin_contracts(
+    env: &HostEnv,
+    container: &DeployedContractsContainer,
+) -> Result<AdminContracts, Error> {
+    let registry = container.contract_ref::<MarketRegistry>(env)?;
+    let mut markets = Vec::new();
+    let mut oracle_addresses: Vec<Address> = Vec::new();
+    for index in 0..registry.get_market_count() {
+        let addrs = registry.get_market_addresses(registry.get_market_by_index(index));
+        markets.push(LendingMarket::load(env, addrs.market));
+        if !oracle_addresses.contains(&addrs.oracle) {
+            oracle_addresses.push(addrs.oracle);
+        }
+    }
+    let oracles = oracle_addresses
+        .into_iter()
+        .map(|address| PriceOracle::load(env, address))
+        .collect();
+    Ok((registry, markets, oracles))
+}
+
 fn refresh_price_if_configured(env: &HostEnv, oracle: &mut PriceOracleHostRef, asset: Address) {
     if let Some(price_value) = env_var("ANCHOR_ASSET_PRICE_WAD") {
         if let Ok(price) = U256::from_dec_str(&price_value) {
@@ -736,6 +903,16 @@ fn refresh_price_if_configured(env: &HostEnv, oracle: &mut PriceOracleHostRef, a
     }
 }
 
+fn debt_token_init_args(decimals: u8, market: Address) -> VariableDebtTokenInitArgs {
+    VariableDebtTokenInitArgs {
+        name: env_var("ANCHOR_DEBT_TOKEN_NAME")
+            .unwrap_or_else(|| "Anchor Variable Debt".to_string()),
+        symbol: env_var("ANCHOR_DEBT_TOKEN_SYMBOL").unwrap_or_else(|| "vdTKN".to_string()),
+        decimals,
+        market,
+    }
+}
+
 fn env_var(name: &str) -> Option<String> {
     env::var(name).ok()
 }
@@ -748,6 +925,9 @@ pub fn main() {
         .contract::<PriceOracle>()
         .contract::<LendingMarket>()
         .contract::<AToken>()
+        .contract::<VariableDebtToken>()
+        .contract::<LendingMarketFactory>()
+        .contract::<ATokenFactory>()
         .scenario(AddMarketScenario)
         .scenario(SupplyScenario)
         .scenario(BorrowScenario)
@@ -758,6 +938,11 @@ pub fn main() {
         .scenario(UpdateParamsScenario)
         .scenario(UpdateRiskScenario)
         .scenario(UpdateRiskEnvScenario)
+        .scenario(ExecuteQueuedScenario)
+        .scenario(CancelQueuedScenario)
+        .scenario(UpgradeMarketScenario)
+        .scenario(ProposeAdminScenario)
+        .scenario(AcceptAdminScenario)
         .build()
         .run();
 }
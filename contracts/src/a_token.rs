@@ -1,17 +1,58 @@
-use odra::casper_types::U256;
+use odra::casper_types::bytesrepr::{Bytes, ToBytes};
+use odra::casper_types::{PublicKey, U256};
 use odra::prelude::*;
+use odra::ContractRef;
+use odra_modules::cep18::errors::Error as Cep18Error;
+use odra_modules::cep18::events::{
+    DecreaseAllowance, IncreaseAllowance, SetAllowance, TransferFrom,
+};
 use odra_modules::cep18_token::Cep18;
 
 use crate::errors::Error;
+use crate::lending_market::LendingMarketContractRef;
+use crate::rewards_controller::RewardsControllerContractRef;
+use crate::types::{Checkpoint, Timestamp};
 
-#[odra::module(errors = Error)]
+/// Prefix of every permit message, so a permit signature cannot be replayed
+/// as any other signed payload.
+const PERMIT_DOMAIN: &[u8] = b"anchor-atoken-permit-v1";
+
+/// Market-minted deposit receipt; the balance is the holder's collateral.
+///
+/// Invariants (MVP):
+/// - Only the market mints and burns.
+/// - Transfers ask the market first, so a borrower cannot move collateral away
+///   from an open position and recipients must pass the market's access mode.
+/// - Allowances live here rather than in `Cep18`, so `permit` can set them;
+///   the entrypoints and events match CEP-18.
+/// - A permit is bound to this contract, the owner's nonce and a deadline;
+///   each nonce is usable once.
+/// - Balance changes are reported to the market's rewards controller, if
+///   set, before they happen.
+/// - Every mint, burn and transfer checkpoints the touched balances (and the
+///   supply on mint/burn), so past values can be read by block time.
+#[odra::module(
+    events = [SetAllowance, IncreaseAllowance, DecreaseAllowance, TransferFrom],
+    errors = Error,
+    factory = on
+)]
 pub struct AToken {
     token: SubModule<Cep18>,
     market: Var<Address>,
+    allowances: Mapping<(Address, Address), U256>,
+    permit_nonces: Mapping<Address, u64>,
+    balance_checkpoint_count: Mapping<Address, u32>,
+    balance_checkpoints: Mapping<(Address, u32), Checkpoint>,
+    supply_checkpoint_count: Var<u32>,
+    supply_checkpoints: Mapping<u32, Checkpoint>,
+    rewards_controller: Var<Address>,
 }
 
-#[odra::module]
+// `factory = on` copies each entry point into a stub that ignores its
+// arguments, hence the `unused_variables` allows on those taking any.
+#[odra::module(factory = on)]
 impl AToken {
+    #[allow(unused_variables)]
     pub fn init(&mut self, name: String, symbol: String, decimals: u8, market: Address) {
         self.market.set(market);
         self.token.init(symbol, name, decimals, U256::zero());
@@ -23,24 +64,192 @@ impl AToken {
             fn symbol(&self) -> String;
             fn decimals(&self) -> u8;
             fn total_supply(&self) -> U256;
+            #[allow(unused_variables)]
             fn balance_of(&self, address: &Address) -> U256;
-            fn allowance(&self, owner: &Address, spender: &Address) -> U256;
-            fn approve(&mut self, spender: &Address, amount: &U256);
-            fn decrease_allowance(&mut self, spender: &Address, decr_by: &U256);
-            fn increase_allowance(&mut self, spender: &Address, inc_by: &U256);
-            fn transfer(&mut self, recipient: &Address, amount: &U256);
-            fn transfer_from(&mut self, owner: &Address, recipient: &Address, amount: &U256);
         }
     }
 
+    #[allow(unused_variables)]
+    pub fn allowance(&self, owner: &Address, spender: &Address) -> U256 {
+        self.allowances.get_or_default(&(*owner, *spender))
+    }
+
+    #[allow(unused_variables)]
+    pub fn approve(&mut self, spender: &Address, amount: &U256) {
+        let owner = self.env().caller();
+        self.set_allowance(owner, *spender, *amount);
+    }
+
+    #[allow(unused_variables)]
+    pub fn increase_allowance(&mut self, spender: &Address, inc_by: &U256) {
+        let owner = self.env().caller();
+        self.ensure_not_self(&owner, spender);
+        let allowance = self.allowance(&owner, spender).saturating_add(*inc_by);
+        self.allowances.set(&(owner, *spender), allowance);
+        self.env().emit_event(IncreaseAllowance {
+            owner,
+            spender: *spender,
+            allowance,
+            inc_by: *inc_by,
+        });
+    }
+
+    #[allow(unused_variables)]
+    pub fn decrease_allowance(&mut self, spender: &Address, decr_by: &U256) {
+        let owner = self.env().caller();
+        self.ensure_not_self(&owner, spender);
+        let allowance = self.allowance(&owner, spender).saturating_sub(*decr_by);
+        self.allowances.set(&(owner, *spender), allowance);
+        self.env().emit_event(DecreaseAllowance {
+            owner,
+            spender: *spender,
+            allowance,
+            decr_by: *decr_by,
+        });
+    }
+
+    /// Sets `spender`'s allowance over `owner`'s tokens from an off-chain
+    /// signature, so a relayer can submit approvals for many users.
+    ///
+    /// `owner` signs `get_permit_message(owner, spender, amount, deadline)`;
+    /// `deadline` is a block time in milliseconds.
+    #[allow(unused_variables)]
+    pub fn permit(
+        &mut self,
+        owner: PublicKey,
+        spender: Address,
+        amount: U256,
+        deadline: u64,
+        signature: Bytes,
+    ) {
+        if self.env().get_block_time() > deadline {
+            self.env().revert(Error::PermitExpired);
+        }
+        let owner_address = Address::from(owner.to_account_hash());
+        let message = self.get_permit_message(owner_address, spender, amount, deadline);
+        if !self.env().verify_signature(&message, &signature, &owner) {
+            self.env().revert(Error::InvalidPermitSignature);
+        }
+        let nonce = self.get_permit_nonce(owner_address);
+        self.permit_nonces.set(&owner_address, nonce + 1);
+        self.set_allowance(owner_address, spender, amount);
+    }
+
+    /// Nonce the next permit from `owner` must be signed with.
+    #[allow(unused_variables)]
+    pub fn get_permit_nonce(&self, owner: Address) -> u64 {
+        self.permit_nonces.get_or_default(&owner)
+    }
+
+    /// The 32-byte digest `owner` signs to permit `spender`, bound to this
+    /// contract and to `owner`'s current nonce.
+    #[allow(unused_variables)]
+    pub fn get_permit_message(
+        &self,
+        owner: Address,
+        spender: Address,
+        amount: U256,
+        deadline: u64,
+    ) -> Bytes {
+        let mut payload = PERMIT_DOMAIN.to_vec();
+        let fields = (
+            self.env().self_address(),
+            owner,
+            spender,
+            amount,
+            self.get_permit_nonce(owner),
+            deadline,
+        );
+        payload.extend(
+            fields
+                .to_bytes()
+                .unwrap_or_revert_with(&self.env(), Error::InvalidParam),
+        );
+        Bytes::from(self.env().hash(payload).to_vec())
+    }
+
+    #[allow(unused_variables)]
+    pub fn transfer(&mut self, recipient: &Address, amount: &U256) {
+        let sender = self.env().caller();
+        self.ensure_transfer_allowed(sender, *recipient, amount);
+        self.notify_rewards(sender);
+        self.notify_rewards(*recipient);
+        self.token.transfer(recipient, amount);
+        self.checkpoint_balance(sender);
+        self.checkpoint_balance(*recipient);
+    }
+
+    #[allow(unused_variables)]
+    pub fn transfer_from(&mut self, owner: &Address, recipient: &Address, amount: &U256) {
+        let spender = self.env().caller();
+        if owner == recipient {
+            self.env().revert(Cep18Error::CannotTargetSelfUser);
+        }
+        if amount.is_zero() {
+            return;
+        }
+        let allowance = self
+            .allowance(owner, &spender)
+            .checked_sub(*amount)
+            .unwrap_or_revert_with(&self.env(), Cep18Error::InsufficientAllowance);
+        self.ensure_transfer_allowed(*owner, *recipient, amount);
+        self.allowances.set(&(*owner, spender), allowance);
+        self.notify_rewards(*owner);
+        self.notify_rewards(*recipient);
+        self.token.raw_transfer(owner, recipient, amount);
+        self.checkpoint_balance(*owner);
+        self.checkpoint_balance(*recipient);
+        self.env().emit_event(TransferFrom {
+            spender,
+            owner: *owner,
+            recipient: *recipient,
+            amount: *amount,
+        });
+    }
+
+    #[allow(unused_variables)]
     pub fn mint(&mut self, to: Address, amount: U256) {
         self.ensure_market();
+        self.notify_rewards(to);
         self.token.raw_mint(&to, &amount);
+        self.checkpoint_balance(to);
+        self.checkpoint_supply();
     }
 
+    #[allow(unused_variables)]
     pub fn burn(&mut self, from: Address, amount: U256) {
         self.ensure_market();
+        self.notify_rewards(from);
         self.token.raw_burn(&from, &amount);
+        self.checkpoint_balance(from);
+        self.checkpoint_supply();
+    }
+
+    /// `owner`'s balance at the end of block time `timestamp` (milliseconds).
+    /// For the current block it is the live balance.
+    #[allow(unused_variables)]
+    pub fn balance_of_at(&self, owner: Address, timestamp: Timestamp) -> U256 {
+        let count = self.balance_checkpoint_count.get_or_default(&owner);
+        value_at(count, timestamp, |index| {
+            self.balance_checkpoints.get(&(owner, index))
+        })
+    }
+
+    /// Total supply at the end of block time `timestamp` (milliseconds).
+    #[allow(unused_variables)]
+    pub fn total_supply_at(&self, timestamp: Timestamp) -> U256 {
+        let count = self.supply_checkpoint_count.get_or_default();
+        value_at(count, timestamp, |index| self.supply_checkpoints.get(&index))
+    }
+
+    #[allow(unused_variables)]
+    pub fn set_rewards_controller(&mut self, rewards_controller: Address) {
+        self.ensure_market();
+        self.rewards_controller.set(rewards_controller);
+    }
+
+    pub fn get_rewards_controller(&self) -> Option<Address> {
+        self.rewards_controller.get()
     }
 
     pub fn get_market(&self) -> Address {
@@ -51,6 +260,74 @@ impl AToken {
 }
 
 impl AToken {
+    fn set_allowance(&mut self, owner: Address, spender: Address, allowance: U256) {
+        self.ensure_not_self(&owner, &spender);
+        self.allowances.set(&(owner, spender), allowance);
+        self.env().emit_event(SetAllowance {
+            owner,
+            spender,
+            allowance,
+        });
+    }
+
+    fn notify_rewards(&self, account: Address) {
+        let Some(controller) = self.rewards_controller.get() else {
+            return;
+        };
+        RewardsControllerContractRef::new(self.env(), controller).handle_supply_action(
+            account,
+            self.token.balance_of(&account),
+            self.token.total_supply(),
+        );
+    }
+
+    fn checkpoint_balance(&mut self, owner: Address) {
+        let value = self.token.balance_of(&owner);
+        let timestamp = self.env().get_block_time();
+        let count = self.balance_checkpoint_count.get_or_default(&owner);
+        let last = count.checked_sub(1);
+        let index = match last.and_then(|i| self.balance_checkpoints.get(&(owner, i))) {
+            Some(checkpoint) if checkpoint.timestamp == timestamp => count - 1,
+            _ => {
+                self.balance_checkpoint_count.set(&owner, count + 1);
+                count
+            }
+        };
+        self.balance_checkpoints
+            .set(&(owner, index), Checkpoint { timestamp, value });
+    }
+
+    fn checkpoint_supply(&mut self) {
+        let value = self.token.total_supply();
+        let timestamp = self.env().get_block_time();
+        let count = self.supply_checkpoint_count.get_or_default();
+        let last = count.checked_sub(1);
+        let index = match last.and_then(|i| self.supply_checkpoints.get(&i)) {
+            Some(checkpoint) if checkpoint.timestamp == timestamp => count - 1,
+            _ => {
+                self.supply_checkpoint_count.set(count + 1);
+                count
+            }
+        };
+        self.supply_checkpoints
+            .set(&index, Checkpoint { timestamp, value });
+    }
+
+    fn ensure_not_self(&self, owner: &Address, spender: &Address) {
+        if owner == spender {
+            self.env().revert(Cep18Error::CannotTargetSelfUser);
+        }
+    }
+
+    fn ensure_transfer_allowed(&self, owner: Address, recipient: Address, amount: &U256) {
+        // An overdraft is left for Cep18 to reject.
+        let Some(remaining) = self.token.balance_of(&owner).checked_sub(*amount) else {
+            return;
+        };
+        LendingMarketContractRef::new(self.env(), self.get_market())
+            .before_a_token_transfer(owner, recipient, remaining);
+    }
+
     fn ensure_market(&self) {
         let market = self
             .market
@@ -62,14 +339,46 @@ impl AToken {
     }
 }
 
+/// Binary search for the last of `count` time-ordered checkpoints taken at or
+/// before `timestamp`; zero when there is none.
+fn value_at(
+    count: u32,
+    timestamp: Timestamp,
+    checkpoint: impl Fn(u32) -> Option<Checkpoint>,
+) -> U256 {
+    let (mut low, mut high) = (0u32, count);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        match checkpoint(mid) {
+            Some(found) if found.timestamp > timestamp => high = mid,
+            _ => low = mid + 1,
+        }
+    }
+    low.checked_sub(1)
+        .and_then(checkpoint)
+        .map(|found| found.value)
+        .unwrap_or_default()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::errors::Error;
-    use odra::host::Deployer;
+    use crate::test_stub_market::StubMarket;
+    use odra::host::{Deployer, HostEnv, NoArgs};
+
+    fn deploy_with_market(env: &HostEnv, market: Address) -> ATokenHostRef {
+        let init_args = ATokenInitArgs {
+            name: "Anchor Token".to_string(),
+            symbol: "aTKN".to_string(),
+            decimals: 9,
+            market,
+        };
+        AToken::deploy(env, init_args)
+    }
 
     #[test]
-    fn market_can_mint_and_transfer() {
+    fn market_can_mint() {
         let env = odra_test::env();
         let market = env.get_account(0);
         let user = env.get_account(1);
@@ -87,10 +396,6 @@ mod tests {
         let balance = token.balance_of(&user);
         assert_eq!(balance, U256::from(100u64));
         assert_eq!(token.total_supply(), U256::from(100u64));
-
-        env.set_caller(user);
-        token.transfer(&market, &U256::from(40u64));
-        assert_eq!(token.balance_of(&user), U256::from(60u64));
     }
 
     #[test]
@@ -110,4 +415,136 @@ mod tests {
         let result = token.try_mint(user, U256::from(1u64));
         assert_eq!(result, Err(Error::Unauthorized.into()));
     }
+
+    #[test]
+    fn relayer_submits_signed_permit_once() {
+        let env = odra_test::env();
+        let owner = env.get_account(1);
+        let spender = env.get_account(2);
+        let relayer = env.get_account(3);
+        let mut token = deploy_with_market(&env, env.get_account(0));
+        let amount = U256::from(500u64);
+        let deadline = env.block_time() + 60_000;
+
+        let message = token.get_permit_message(owner, spender, amount, deadline);
+        let signature = env.sign_message(&message, &owner);
+        env.set_caller(relayer);
+        token.permit(
+            env.public_key(&owner),
+            spender,
+            amount,
+            deadline,
+            signature.clone(),
+        );
+
+        assert_eq!(token.allowance(&owner, &spender), amount);
+        assert_eq!(token.get_permit_nonce(owner), 1);
+        assert!(env.emitted_event(
+            &token,
+            SetAllowance {
+                owner,
+                spender,
+                allowance: amount,
+            }
+        ));
+
+        // The nonce moved on, so the same signature no longer verifies.
+        let result =
+            token.try_permit(env.public_key(&owner), spender, amount, deadline, signature);
+        assert_eq!(result, Err(Error::InvalidPermitSignature.into()));
+    }
+
+    #[test]
+    fn permit_rejects_expired_and_foreign_signatures() {
+        let env = odra_test::env();
+        let owner = env.get_account(1);
+        let spender = env.get_account(2);
+        let mut token = deploy_with_market(&env, env.get_account(0));
+        let amount = U256::from(500u64);
+        let deadline = env.block_time() + 1_000;
+        let message = token.get_permit_message(owner, spender, amount, deadline);
+
+        let forged = env.sign_message(&message, &spender);
+        let result =
+            token.try_permit(env.public_key(&owner), spender, amount, deadline, forged);
+        assert_eq!(result, Err(Error::InvalidPermitSignature.into()));
+
+        let signature = env.sign_message(&message, &owner);
+        env.advance_block_time(1_001);
+        let result =
+            token.try_permit(env.public_key(&owner), spender, amount, deadline, signature);
+        assert_eq!(result, Err(Error::PermitExpired.into()));
+        assert_eq!(token.allowance(&owner, &spender), U256::zero());
+        assert_eq!(token.get_permit_nonce(owner), 0);
+    }
+
+    #[test]
+    fn mints_and_burns_are_checkpointed_by_block_time() {
+        let env = odra_test::env();
+        let market = env.get_account(0);
+        let user = env.get_account(1);
+        let other = env.get_account(2);
+        let mut token = deploy_with_market(&env, market);
+        env.set_caller(market);
+
+        let before = env.block_time();
+        env.advance_block_time(1_000);
+        let first = env.block_time();
+        token.mint(user, U256::from(100u64));
+        // Same block: the checkpoint is overwritten, not appended.
+        token.mint(user, U256::from(50u64));
+        env.advance_block_time(1_000);
+        let second = env.block_time();
+        token.mint(other, U256::from(30u64));
+        env.advance_block_time(1_000);
+        let third = env.block_time();
+        token.burn(user, U256::from(120u64));
+
+        assert_eq!(token.balance_of_at(user, before), U256::zero());
+        assert_eq!(token.balance_of_at(user, first), U256::from(150u64));
+        assert_eq!(token.balance_of_at(user, second + 999), U256::from(150u64));
+        assert_eq!(token.balance_of_at(user, third), U256::from(30u64));
+        assert_eq!(token.balance_of_at(other, first), U256::zero());
+        assert_eq!(token.balance_of_at(other, third), U256::from(30u64));
+
+        assert_eq!(token.total_supply_at(before), U256::zero());
+        assert_eq!(token.total_supply_at(first), U256::from(150u64));
+        assert_eq!(token.total_supply_at(second), U256::from(180u64));
+        assert_eq!(token.total_supply_at(third), U256::from(60u64));
+        assert_eq!(token.total_supply_at(third + 1), token.total_supply());
+    }
+
+    /// Prints the CasperVM gas of each checkpointed call, as listed in
+    /// MARKET_DESIGN.md. Mints go through `StubMarket`, so they include one
+    /// cross-contract call.
+    #[test]
+    #[ignore = "measures CasperVM gas: build wasm and set ODRA_BACKEND=casper"]
+    fn checkpoint_gas_report() {
+        let env = odra_test::env();
+        let first = env.get_account(1);
+        let second = env.get_account(2);
+        let third = env.get_account(3);
+        let mut market = StubMarket::deploy(&env, NoArgs);
+        let mut token = deploy_with_market(&env, market.address());
+        let a_token = token.address();
+        let last_call = |label: &str| {
+            let report = env.gas_report().into_iter().last().unwrap();
+            println!("{label}: {report}");
+        };
+
+        market.mint(a_token, first, U256::from(1_000u64));
+        last_call("mint, first holder");
+        env.advance_block_time(1_000);
+        market.mint(a_token, second, U256::from(1_000u64));
+        last_call("mint, later block");
+        env.advance_block_time(1_000);
+        env.set_caller(first);
+        token.transfer(&second, &U256::from(10u64));
+        last_call("transfer, both sides in an earlier block");
+        token.transfer(&second, &U256::from(10u64));
+        last_call("transfer, same block as the last one");
+        env.advance_block_time(1_000);
+        token.transfer(&third, &U256::from(10u64));
+        last_call("transfer to an account with no checkpoint");
+    }
 }
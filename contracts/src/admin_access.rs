@@ -0,0 +1,119 @@
+use odra::prelude::*;
+use odra_modules::access::{AccessControl, Role, DEFAULT_ADMIN_ROLE};
+
+use crate::errors::Error;
+use crate::events::{AdminTransferCancelled, AdminTransferProposed, AdminTransferred};
+
+/// `AccessControl` with a two-step handover of `DEFAULT_ADMIN_ROLE`.
+///
+/// Invariants (MVP):
+/// - Exactly one address holds `DEFAULT_ADMIN_ROLE`; it is recorded in `admin`.
+/// - The role only moves when the pending admin accepts, so a mistyped
+///   address can be cancelled instead of locking the contract.
+/// - Operational roles (market, protocol, oracle admin) are not moved; the new
+///   admin grants and revokes them after accepting.
+/// - Only the `DEFAULT_ADMIN_ROLE` holder may propose or cancel a handover.
+#[odra::module(
+    events = [AdminTransferProposed, AdminTransferCancelled, AdminTransferred],
+    errors = Error
+)]
+pub struct AdminAccess {
+    roles: SubModule<AccessControl>,
+    admin: Var<Address>,
+    pending_admin: Var<Option<Address>>,
+}
+
+#[odra::module]
+impl AdminAccess {
+    pub fn init_admin(&mut self, admin: Address) {
+        self.roles.unchecked_grant_role(&DEFAULT_ADMIN_ROLE, &admin);
+        self.admin.set(admin);
+    }
+
+    /// Starts a two-step handover of `DEFAULT_ADMIN_ROLE` to `pending_admin`.
+    pub fn propose_admin(&mut self, pending_admin: Address) {
+        self.ensure_default_admin();
+        let admin = self.get_admin();
+        if pending_admin == admin {
+            self.env().revert(Error::InvalidParam);
+        }
+        self.pending_admin.set(Some(pending_admin));
+        self.env().emit_event(AdminTransferProposed {
+            admin,
+            pending_admin,
+        });
+    }
+
+    pub fn cancel_admin_transfer(&mut self) {
+        self.ensure_default_admin();
+        let pending_admin = self.require_pending_admin();
+        self.pending_admin.set(None);
+        self.env()
+            .emit_event(AdminTransferCancelled { pending_admin });
+    }
+
+    /// Moves `DEFAULT_ADMIN_ROLE` to the caller, who must be the pending admin.
+    pub fn accept_admin(&mut self) {
+        let new_admin = self.require_pending_admin();
+        if self.env().caller() != new_admin {
+            self.env().revert(Error::NotPendingAdmin);
+        }
+        let previous_admin = self.get_admin();
+        self.roles
+            .unchecked_revoke_role(&DEFAULT_ADMIN_ROLE, &previous_admin);
+        self.roles
+            .unchecked_grant_role(&DEFAULT_ADMIN_ROLE, &new_admin);
+        self.admin.set(new_admin);
+        self.pending_admin.set(None);
+        self.env().emit_event(AdminTransferred {
+            previous_admin,
+            new_admin,
+        });
+    }
+
+    pub fn get_admin(&self) -> Address {
+        self.admin
+            .get()
+            .unwrap_or_revert_with(&self.env(), Error::Unauthorized)
+    }
+
+    pub fn get_pending_admin(&self) -> Option<Address> {
+        self.pending_admin.get().flatten()
+    }
+
+    pub fn ensure_default_admin(&self) {
+        self.roles
+            .check_role(&DEFAULT_ADMIN_ROLE, &self.env().caller());
+    }
+
+    pub fn has_role(&self, role: &Role, address: &Address) -> bool {
+        self.roles.has_role(role, address)
+    }
+
+    pub fn check_role(&self, role: &Role, address: &Address) {
+        self.roles.check_role(role, address);
+    }
+
+    pub fn grant_role(&mut self, role: &Role, address: &Address) {
+        self.roles.grant_role(role, address);
+    }
+
+    pub fn revoke_role(&mut self, role: &Role, address: &Address) {
+        self.roles.revoke_role(role, address);
+    }
+
+    pub fn unchecked_grant_role(&mut self, role: &Role, address: &Address) {
+        self.roles.unchecked_grant_role(role, address);
+    }
+
+    pub fn set_admin_role(&mut self, role: &Role, admin_role: &Role) {
+        self.roles.set_admin_role(role, admin_role);
+    }
+}
+
+impl AdminAccess {
+    fn require_pending_admin(&self) -> Address {
+        self.get_pending_admin()
+            .unwrap_or_revert_with(&self.env(), Error::NoPendingAdmin)
+    }
+}
@@ -0,0 +1,33 @@
+use odra::prelude::*;
+
+use crate::errors::Error;
+use crate::events::DebtCeilingUpdated;
+use crate::types::DebtCeiling;
+
+/// Per-market debt ceilings, embedded by `MarketRegistry`.
+///
+/// Invariants (MVP):
+/// - Ceilings are checked against the market's own total borrows, interest
+///   included, so nothing here has to mirror borrows and repayments.
+/// - Lowering a ceiling below outstanding debt only blocks new borrows.
+/// - Role checks and pricing are left to the embedding contract.
+#[odra::module(events = [DebtCeilingUpdated], errors = Error)]
+pub struct DebtCeilings {
+    ceilings: Mapping<Address, DebtCeiling>,
+}
+
+#[odra::module]
+impl DebtCeilings {
+    pub fn set_ceiling(&mut self, asset: Address, ceiling: DebtCeiling) {
+        self.ceilings.set(&asset, ceiling.clone());
+        self.env().emit_event(DebtCeilingUpdated {
+            asset,
+            amount: ceiling.amount,
+            in_usd: ceiling.in_usd,
+        });
+    }
+
+    pub fn get_ceiling(&self, asset: Address) -> DebtCeiling {
+        self.ceilings.get_or_default(&asset)
+    }
+}
@@ -16,4 +16,42 @@ pub enum Error {
     MarketAlreadyRegistered = 1_011,
     InsufficientBalance = 1_012,
     NotLiquidatable = 1_013,
+    AssetNotRegistered = 1_014,
+    LiquidationGracePeriod = 1_015,
+    ExchangeRateOutOfBounds = 1_016,
+    TimelockNotReady = 1_017,
+    TimelockActionExpired = 1_018,
+    TimelockActionNotQueued = 1_019,
+    CollateralFactorAboveThreshold = 1_020,
+    LiquidationThresholdTooHigh = 1_021,
+    LiquidationBonusTooHigh = 1_022,
+    ReserveFactorTooHigh = 1_023,
+    CloseFactorOutOfRange = 1_024,
+    BaseRateTooHigh = 1_025,
+    BorrowRateTooHigh = 1_026,
+    InvalidMarketTransition = 1_027,
+    MarketNotActive = 1_028,
+    MarketHasOpenBorrows = 1_029,
+    NoGuardianPause = 1_030,
+    NoPendingAdmin = 1_031,
+    NotPendingAdmin = 1_032,
+    NotSigner = 1_033,
+    ProposalNotFound = 1_034,
+    ProposalNotPending = 1_035,
+    ProposalExpired = 1_036,
+    AlreadyApproved = 1_037,
+    ApprovalThresholdNotMet = 1_038,
+    UnsupportedMarketVersion = 1_039,
+    PermitExpired = 1_040,
+    InvalidPermitSignature = 1_041,
+    DebtTokenNotTransferable = 1_042,
+    InsufficientBorrowAllowance = 1_043,
+    RewardsUnderfunded = 1_044,
+    ReferralCodeTaken = 1_045,
+    UnknownReferralCode = 1_046,
+    EModeCategoryMismatch = 1_047,
+    DebtCeilingExceeded = 1_048,
+    AccountNotAllowed = 1_049,
+    TimelockDelayTooShort = 1_050,
+    MarketTemplatesNotSet = 1_051,
 }
@@ -8,6 +8,18 @@ pub struct MarketRegistered {
     pub oracle: Address,
 }
 
+#[odra::event]
+pub struct MarketTemplatesUpdated {
+    pub market_factory: Address,
+    pub a_token_factory: Address,
+}
+
+#[odra::event]
+pub struct DebtTokenLinked {
+    pub asset: Address,
+    pub debt_token: Address,
+}
+
 #[odra::event]
 pub struct MarketActiveUpdated {
     pub asset: Address,
@@ -15,13 +27,153 @@ pub struct MarketActiveUpdated {
 }
 
 #[odra::event]
-pub struct PauseFlagsUpdated {
+pub struct MarketFrozen {
+    pub asset: Address,
+}
+
+#[odra::event]
+pub struct MarketUnfrozen {
+    pub asset: Address,
+}
+
+#[odra::event]
+pub struct MarketDeprecated {
+    pub asset: Address,
+}
+
+#[odra::event]
+pub struct MarketClosed {
+    pub asset: Address,
+}
+
+pub use wide::{GlobalPauseUpdated, PauseFlagsUpdated};
+
+/// Events with more fields than clippy's argument limit. `#[odra::event]`
+/// generates a `new` taking every field, which an `#[allow]` on the struct
+/// does not reach, and the fields cannot be grouped into a struct because
+/// event fields must have a concrete `CLType`.
+#[allow(clippy::too_many_arguments)]
+mod wide {
+    use odra::prelude::*;
+
+    #[odra::event]
+    pub struct PauseFlagsUpdated {
+        pub asset: Address,
+        pub supply_paused: bool,
+        pub borrow_paused: bool,
+        pub withdraw_paused: bool,
+        pub repay_paused: bool,
+        pub liquidation_paused: bool,
+        pub reason_code: u32,
+        pub timestamp: u64,
+        pub expires_at: u64,
+    }
+
+    #[odra::event]
+    pub struct GlobalPauseUpdated {
+        pub supply_paused: bool,
+        pub borrow_paused: bool,
+        pub withdraw_paused: bool,
+        pub repay_paused: bool,
+        pub liquidation_paused: bool,
+        pub reason_code: u32,
+        pub timestamp: u64,
+        pub expires_at: u64,
+    }
+}
+
+/// `asset` is `None` for the global pause.
+#[odra::event]
+pub struct GuardianPauseExtended {
+    pub asset: Option<Address>,
+    pub expires_at: u64,
+}
+
+#[odra::event]
+pub struct GuardianPauseDurationUpdated {
+    pub duration_millis: u64,
+}
+
+#[odra::event]
+pub struct AdminTransferProposed {
+    pub admin: Address,
+    pub pending_admin: Address,
+}
+
+#[odra::event]
+pub struct AdminTransferCancelled {
+    pub pending_admin: Address,
+}
+
+#[odra::event]
+pub struct AdminTransferred {
+    pub previous_admin: Address,
+    pub new_admin: Address,
+}
+
+#[odra::event]
+pub struct ProposalCreated {
+    pub id: u64,
+    pub proposer: Address,
+    pub target: Address,
+    pub entry_point: String,
+    pub expires_at: u64,
+}
+
+#[odra::event]
+pub struct ProposalApproved {
+    pub id: u64,
+    pub signer: Address,
+}
+
+#[odra::event]
+pub struct ProposalExecuted {
+    pub id: u64,
+    pub executor: Address,
+}
+
+#[odra::event]
+pub struct ProposalCancelled {
+    pub id: u64,
+}
+
+#[odra::event]
+pub struct SignerAdded {
+    pub signer: Address,
+}
+
+#[odra::event]
+pub struct SignerRemoved {
+    pub signer: Address,
+}
+
+#[odra::event]
+pub struct ThresholdUpdated {
+    pub threshold: u32,
+}
+
+#[odra::event]
+pub struct TimelockActionQueued {
+    pub id: u64,
+    pub asset: Address,
+    pub eta: u64,
+}
+
+#[odra::event]
+pub struct TimelockActionCancelled {
+    pub id: u64,
     pub asset: Address,
-    pub supply_paused: bool,
-    pub borrow_paused: bool,
-    pub withdraw_paused: bool,
-    pub repay_paused: bool,
-    pub liquidation_paused: bool,
+}
+
+#[odra::event]
+pub struct TimelockActionExecuted {
+    pub id: u64,
+    pub asset: Address,
+}
+
+#[odra::event]
+pub struct TimelockDelayUpdated {
+    pub delay_millis: u64,
 }
 
 #[odra::event]
@@ -31,6 +183,60 @@ pub struct PriceUpdated {
     pub timestamp: u64,
 }
 
+#[odra::event]
+pub struct PriceRecovered {
+    pub asset: Address,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct LiquidationGracePeriodUpdated {
+    pub grace_period_millis: u64,
+}
+
+#[odra::event]
+pub struct MarketUpgraded {
+    pub from_version: u32,
+    pub to_version: u32,
+}
+
+#[odra::event]
+pub struct AssetRegistered {
+    pub asset: Address,
+    pub decimals: u8,
+}
+
+#[odra::event]
+pub struct AssetMaxStaleUpdated {
+    pub asset: Address,
+    pub max_stale_millis: u64,
+}
+
+#[odra::event]
+pub struct ExchangeRateFeedConfigured {
+    pub asset: Address,
+    pub base_asset: Address,
+    pub rate_source: Address,
+    pub rate_entrypoint: String,
+    pub max_growth_per_sec: odra::casper_types::U256,
+    pub max_drop_per_update: odra::casper_types::U256,
+}
+
+#[odra::event]
+pub struct ExchangeRateUpdated {
+    pub asset: Address,
+    pub rate: odra::casper_types::U256,
+    pub timestamp: u64,
+}
+
+/// The oracle admin accepted a rate outside the growth and drop bounds.
+#[odra::event]
+pub struct ExchangeRateOverridden {
+    pub asset: Address,
+    pub previous_rate: odra::casper_types::U256,
+    pub rate: odra::casper_types::U256,
+}
+
 #[odra::event]
 pub struct RateModelUpdated {
     pub base_rate_per_sec: odra::casper_types::U256,
@@ -92,3 +298,103 @@ pub struct Liquidate {
     pub repay_amount: odra::casper_types::U256,
     pub seize_amount: odra::casper_types::U256,
 }
+
+#[odra::event]
+pub struct BorrowAllowanceDelegated {
+    pub delegator: Address,
+    pub delegatee: Address,
+    pub amount: odra::casper_types::U256,
+}
+
+#[odra::event]
+pub struct BorrowOnBehalf {
+    pub delegator: Address,
+    pub delegatee: Address,
+    pub amount: odra::casper_types::U256,
+    pub remaining_allowance: odra::casper_types::U256,
+}
+
+#[odra::event]
+pub struct RewardEmissionsUpdated {
+    pub market: Address,
+    pub supply_per_sec: odra::casper_types::U256,
+    pub borrow_per_sec: odra::casper_types::U256,
+}
+
+#[odra::event]
+pub struct RewardsFunded {
+    pub funder: Address,
+    pub amount: odra::casper_types::U256,
+}
+
+#[odra::event]
+pub struct RewardsClaimed {
+    pub account: Address,
+    pub amount: odra::casper_types::U256,
+}
+
+#[odra::event]
+pub struct ReferralCodeRegistered {
+    pub code: u32,
+    pub owner: Address,
+}
+
+#[odra::event]
+pub struct ReferralCodeTransferred {
+    pub code: u32,
+    pub previous_owner: Address,
+    pub new_owner: Address,
+}
+
+#[odra::event]
+pub struct Referral {
+    pub code: u32,
+    pub owner: Address,
+    pub account: Address,
+    pub asset: Address,
+    pub amount: odra::casper_types::U256,
+    pub is_borrow: bool,
+}
+
+#[odra::event]
+pub struct EModeCategoryUpdated {
+    pub category_id: u8,
+    pub collateral_factor: odra::casper_types::U256,
+    pub liquidation_threshold: odra::casper_types::U256,
+    pub liquidation_bonus: odra::casper_types::U256,
+    pub label: String,
+}
+
+#[odra::event]
+pub struct AssetEModeCategoryUpdated {
+    pub asset: Address,
+    pub category_id: u8,
+}
+
+#[odra::event]
+pub struct UserEModeSet {
+    pub account: Address,
+    pub category_id: u8,
+}
+
+#[odra::event]
+pub struct DebtCeilingUpdated {
+    pub asset: Address,
+    pub amount: odra::casper_types::U256,
+    pub in_usd: bool,
+}
+
+/// `mode` is 0 for open, 1 for allowlist and 2 for a compliance contract.
+#[odra::event]
+pub struct AccessModeUpdated {
+    pub asset: Address,
+    pub mode: u8,
+    pub compliance_contract: Option<Address>,
+}
+
+#[odra::event]
+pub struct AllowlistUpdated {
+    pub asset: Address,
+    pub account: Address,
+    pub allowed: bool,
+}
@@ -0,0 +1,597 @@
+use odra::casper_types::{RuntimeArgs, U256};
+use odra::prelude::*;
+use odra::{CallDef, ContractRef};
+use odra_modules::access::{Role, DEFAULT_ADMIN_ROLE};
+
+use crate::admin_access::AdminAccess;
+use crate::cep18_interface::Cep18TokenContractRef;
+use crate::errors::Error;
+use crate::events::{ExchangeRateFeedConfigured, ExchangeRateOverridden, ExchangeRateUpdated};
+use crate::math::{wad_mul, WAD_U128};
+use crate::price_oracle::PriceOracleContractRef;
+use crate::types::ExchangeRateFeed;
+
+const ORACLE_ADMIN_ROLE: Role = *b"ORACLE_ADMIN_ROLE_______________";
+
+/// Oracle adapter pricing wrapped or liquid-staked assets as
+/// `base price * exchange rate`.
+///
+/// Invariants (MVP):
+/// - The base price comes from `PriceOracle::get_price_checked`, so staleness
+///   and recovery follow the base feed.
+/// - The exchange rate is read live from the configured entrypoint and may not
+///   exceed the last snapshot grown by `max_growth_per_sec` for the elapsed time,
+///   nor fall more than `max_drop_per_update` below it.
+/// - A rate outside those bounds makes every price read revert, which halts
+///   dependent markets, liquidations included, until the oracle admin
+///   accepts it with `accept_exchange_rate`.
+/// - Exposes the same price views as `PriceOracle`, so a `LendingMarket` can use
+///   it as its oracle.
+#[odra::module(
+    events = [ExchangeRateFeedConfigured, ExchangeRateUpdated, ExchangeRateOverridden],
+    errors = Error
+)]
+pub struct ExchangeRateOracle {
+    access_control: SubModule<AdminAccess>,
+    base_oracle: Var<Address>,
+    feeds: Mapping<Address, ExchangeRateFeed>,
+}
+
+#[odra::module]
+impl ExchangeRateOracle {
+    pub fn init(&mut self, admin: Address, base_oracle: Address) {
+        self.base_oracle.set(base_oracle);
+        self.access_control.init_admin(admin);
+        self.access_control
+            .unchecked_grant_role(&ORACLE_ADMIN_ROLE, &admin);
+        self.access_control
+            .set_admin_role(&ORACLE_ADMIN_ROLE, &DEFAULT_ADMIN_ROLE);
+    }
+
+    /// Prices `asset` from `base_asset` times the `U256` returned by
+    /// `rate_entrypoint` on `rate_source`. `max_growth_per_sec` and
+    /// `max_drop_per_update` are WAD fractions of the snapshot rate.
+    pub fn configure_feed(
+        &mut self,
+        asset: Address,
+        base_asset: Address,
+        rate_source: Address,
+        rate_entrypoint: String,
+        max_growth_per_sec: U256,
+        max_drop_per_update: U256,
+    ) {
+        self.ensure_oracle_admin();
+        if max_drop_per_update >= U256::from(WAD_U128) {
+            self.env().revert(Error::InvalidParam);
+        }
+        let decimals = Cep18TokenContractRef::new(self.env(), asset).decimals();
+        let rate = self.fetch_rate(rate_source, &rate_entrypoint);
+        let feed = ExchangeRateFeed {
+            base_asset,
+            rate_source,
+            rate_entrypoint: rate_entrypoint.clone(),
+            max_growth_per_sec,
+            max_drop_per_update,
+            decimals,
+            last_rate: rate,
+            last_updated: self.env().get_block_time(),
+        };
+        self.feeds.set(&asset, feed);
+        self.env().emit_event(ExchangeRateFeedConfigured {
+            asset,
+            base_asset,
+            rate_source,
+            rate_entrypoint,
+            max_growth_per_sec,
+            max_drop_per_update,
+        });
+        self.env().emit_event(ExchangeRateUpdated {
+            asset,
+            rate,
+            timestamp: self.env().get_block_time(),
+        });
+    }
+
+    /// Moves the rate snapshot forward. Callable by anyone, since the new
+    /// rate must pass the same growth and drop bounds.
+    pub fn update_exchange_rate(&mut self, asset: Address) {
+        let mut feed = self.get_feed(asset);
+        let rate = self.checked_rate(&feed);
+        let now = self.env().get_block_time();
+        feed.last_rate = rate;
+        feed.last_updated = now;
+        self.feeds.set(&asset, feed);
+        self.env().emit_event(ExchangeRateUpdated {
+            asset,
+            rate,
+            timestamp: now,
+        });
+    }
+
+    /// Oracle admin only: snapshots the rate `asset`'s source reports now,
+    /// skipping the growth and drop bounds. Meant for a real move past them,
+    /// such as a slashing larger than `max_drop_per_update`, which would
+    /// otherwise leave the feed, and every market priced by it, reverting.
+    pub fn accept_exchange_rate(&mut self, asset: Address) {
+        self.ensure_oracle_admin();
+        let mut feed = self.get_feed(asset);
+        let rate = self.fetch_rate(feed.rate_source, &feed.rate_entrypoint);
+        let previous_rate = feed.last_rate;
+        let now = self.env().get_block_time();
+        feed.last_rate = rate;
+        feed.last_updated = now;
+        self.feeds.set(&asset, feed);
+        self.env().emit_event(ExchangeRateOverridden {
+            asset,
+            previous_rate,
+            rate,
+        });
+        self.env().emit_event(ExchangeRateUpdated {
+            asset,
+            rate,
+            timestamp: now,
+        });
+    }
+
+    pub fn get_exchange_rate(&self, asset: Address) -> U256 {
+        let feed = self.get_feed(asset);
+        self.checked_rate(&feed)
+    }
+
+    pub fn get_price_checked(&self, asset: Address) -> U256 {
+        let feed = self.get_feed(asset);
+        let base_price = self.load_base_oracle().get_price_checked(feed.base_asset);
+        wad_mul(base_price, self.checked_rate(&feed))
+    }
+
+    pub fn get_asset_decimals(&self, asset: Address) -> u8 {
+        self.get_feed(asset).decimals
+    }
+
+    pub fn get_recovered_at(&self, asset: Address) -> u64 {
+        let feed = self.get_feed(asset);
+        self.load_base_oracle().get_recovered_at(feed.base_asset)
+    }
+
+    pub fn get_feed(&self, asset: Address) -> ExchangeRateFeed {
+        self.feeds
+            .get(&asset)
+            .unwrap_or_revert_with(&self.env(), Error::PriceMissing)
+    }
+
+    pub fn get_base_oracle(&self) -> Address {
+        self.base_oracle
+            .get()
+            .unwrap_or_revert_with(&self.env(), Error::InvalidParam)
+    }
+
+    delegate! {
+        to self.access_control {
+            fn propose_admin(&mut self, pending_admin: Address);
+            fn cancel_admin_transfer(&mut self);
+            fn accept_admin(&mut self);
+            fn get_admin(&self) -> Address;
+            fn get_pending_admin(&self) -> Option<Address>;
+        }
+    }
+
+    pub fn grant_oracle_admin(&mut self, address: Address) {
+        self.access_control
+            .grant_role(&ORACLE_ADMIN_ROLE, &address);
+    }
+
+    pub fn revoke_oracle_admin(&mut self, address: Address) {
+        self.access_control
+            .revoke_role(&ORACLE_ADMIN_ROLE, &address);
+    }
+
+    pub fn has_oracle_admin(&self, address: Address) -> bool {
+        self.access_control.has_role(&ORACLE_ADMIN_ROLE, &address)
+    }
+}
+
+impl ExchangeRateOracle {
+    fn ensure_oracle_admin(&self) {
+        self.access_control
+            .check_role(&ORACLE_ADMIN_ROLE, &self.env().caller());
+    }
+
+    fn load_base_oracle(&self) -> PriceOracleContractRef {
+        PriceOracleContractRef::new(self.env(), self.get_base_oracle())
+    }
+
+    fn fetch_rate(&self, rate_source: Address, rate_entrypoint: &str) -> U256 {
+        let call = CallDef::new(rate_entrypoint, false, RuntimeArgs::new());
+        let rate: U256 = self.env().call_contract(rate_source, call);
+        if rate.is_zero() {
+            self.env().revert(Error::ExchangeRateOutOfBounds);
+        }
+        rate
+    }
+
+    fn checked_rate(&self, feed: &ExchangeRateFeed) -> U256 {
+        let rate = self.fetch_rate(feed.rate_source, &feed.rate_entrypoint);
+        let elapsed_secs = self
+            .env()
+            .get_block_time()
+            .saturating_sub(feed.last_updated)
+            / 1000;
+        let growth = feed
+            .max_growth_per_sec
+            .checked_mul(U256::from(elapsed_secs))
+            .unwrap_or_else(|| self.env().revert(Error::MathOverflow));
+        let one = U256::from(WAD_U128);
+        let max_rate = wad_mul(feed.last_rate, one + growth);
+        let min_rate = wad_mul(feed.last_rate, one - feed.max_drop_per_update);
+        if rate > max_rate || rate < min_rate {
+            self.env().revert(Error::ExchangeRateOutOfBounds);
+        }
+        rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::a_token::{AToken, ATokenInitArgs};
+    use crate::errors::Error;
+    use crate::lending_market::{LendingMarket, LendingMarketInitArgs};
+    use crate::market_registry::{MarketRegistry, MarketRegistryInitArgs};
+    use crate::math::value_of;
+    use crate::price_oracle::{PriceOracle, PriceOracleInitArgs};
+    use crate::test_token::{TestToken, TestTokenInitArgs};
+    use crate::types::{MarketConfig, RateModel, RiskParams};
+    use odra::host::{Deployer, NoArgs};
+
+    /// Stand-in for a liquid staking contract exposing its exchange rate.
+    #[odra::module]
+    pub struct MockRateSource {
+        rate: Var<U256>,
+    }
+
+    #[odra::module]
+    impl MockRateSource {
+        pub fn set_rate(&mut self, rate: U256) {
+            self.rate.set(rate);
+        }
+
+        pub fn get_rate(&self) -> U256 {
+            self.rate.get_or_default()
+        }
+    }
+
+    #[test]
+    fn price_is_base_price_times_rate() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let base_asset = env.get_account(1);
+        let wad = U256::from(WAD_U128);
+
+        let mut base_oracle = PriceOracle::deploy(
+            &env,
+            PriceOracleInitArgs {
+                admin,
+                max_stale_millis: 10_000u64,
+            },
+        );
+        let mut source = MockRateSource::deploy(&env, NoArgs);
+        let token = AToken::deploy(
+            &env,
+            ATokenInitArgs {
+                name: "Staked CSPR".to_string(),
+                symbol: "sCSPR".to_string(),
+                decimals: 9,
+                market: admin,
+            },
+        );
+        let mut adapter = ExchangeRateOracle::deploy(
+            &env,
+            ExchangeRateOracleInitArgs {
+                admin,
+                base_oracle: base_oracle.address(),
+            },
+        );
+
+        env.set_caller(admin);
+        base_oracle.set_price(base_asset, wad * U256::from(2u8));
+        source.set_rate(wad * U256::from(11u8) / U256::from(10u8));
+        adapter.configure_feed(
+            token.address(),
+            base_asset,
+            source.address(),
+            "get_rate".to_string(),
+            U256::from(WAD_U128 / 1_000),
+            U256::from(WAD_U128 / 20),
+        );
+
+        assert_eq!(adapter.get_asset_decimals(token.address()), 9);
+        assert_eq!(
+            adapter.get_price_checked(token.address()),
+            wad * U256::from(22u8) / U256::from(10u8)
+        );
+
+        env.advance_block_time(11_000u64);
+        let result = adapter.try_get_price_checked(token.address());
+        assert_eq!(result, Err(Error::PriceStale.into()));
+    }
+
+    #[test]
+    fn rate_growth_is_bounded() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let base_asset = env.get_account(1);
+        let wad = U256::from(WAD_U128);
+
+        let mut base_oracle = PriceOracle::deploy(
+            &env,
+            PriceOracleInitArgs {
+                admin,
+                max_stale_millis: 100_000u64,
+            },
+        );
+        let mut source = MockRateSource::deploy(&env, NoArgs);
+        let token = AToken::deploy(
+            &env,
+            ATokenInitArgs {
+                name: "Staked CSPR".to_string(),
+                symbol: "sCSPR".to_string(),
+                decimals: 9,
+                market: admin,
+            },
+        );
+        let mut adapter = ExchangeRateOracle::deploy(
+            &env,
+            ExchangeRateOracleInitArgs {
+                admin,
+                base_oracle: base_oracle.address(),
+            },
+        );
+
+        env.set_caller(admin);
+        base_oracle.set_price(base_asset, wad);
+        source.set_rate(wad);
+        adapter.configure_feed(
+            token.address(),
+            base_asset,
+            source.address(),
+            "get_rate".to_string(),
+            U256::from(WAD_U128 / 1_000),
+            U256::from(WAD_U128 / 20),
+        );
+
+        // One percent in one second against a 0.1%/sec bound.
+        env.advance_block_time(1_000u64);
+        source.set_rate(wad * U256::from(101u8) / U256::from(100u8));
+        let result = adapter.try_get_price_checked(token.address());
+        assert_eq!(result, Err(Error::ExchangeRateOutOfBounds.into()));
+        let result = adapter.try_update_exchange_rate(token.address());
+        assert_eq!(result, Err(Error::ExchangeRateOutOfBounds.into()));
+
+        env.advance_block_time(9_000u64);
+        adapter.update_exchange_rate(token.address());
+        assert_eq!(
+            adapter.get_feed(token.address()).last_rate,
+            wad * U256::from(101u8) / U256::from(100u8)
+        );
+
+        source.set_rate(U256::zero());
+        let result = adapter.try_get_exchange_rate(token.address());
+        assert_eq!(result, Err(Error::ExchangeRateOutOfBounds.into()));
+
+        // A 5% drop per update is tolerated (slashing); a collapse is not.
+        source.set_rate(U256::one());
+        let result = adapter.try_update_exchange_rate(token.address());
+        assert_eq!(result, Err(Error::ExchangeRateOutOfBounds.into()));
+        let floor = wad * U256::from(101u8) / U256::from(100u8) * U256::from(95u8)
+            / U256::from(100u8);
+        source.set_rate(floor - U256::one());
+        let result = adapter.try_get_price_checked(token.address());
+        assert_eq!(result, Err(Error::ExchangeRateOutOfBounds.into()));
+        source.set_rate(floor);
+        adapter.update_exchange_rate(token.address());
+        assert_eq!(adapter.get_feed(token.address()).last_rate, floor);
+
+        let result = adapter.try_configure_feed(
+            token.address(),
+            base_asset,
+            source.address(),
+            "get_rate".to_string(),
+            U256::zero(),
+            wad,
+        );
+        assert_eq!(result, Err(Error::InvalidParam.into()));
+    }
+
+    #[test]
+    fn admin_accepts_a_drop_past_the_bound() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let base_asset = env.get_account(1);
+        let wad = U256::from(WAD_U128);
+
+        let mut base_oracle = PriceOracle::deploy(
+            &env,
+            PriceOracleInitArgs {
+                admin,
+                max_stale_millis: 100_000u64,
+            },
+        );
+        let mut source = MockRateSource::deploy(&env, NoArgs);
+        let token = AToken::deploy(
+            &env,
+            ATokenInitArgs {
+                name: "Staked CSPR".to_string(),
+                symbol: "sCSPR".to_string(),
+                decimals: 9,
+                market: admin,
+            },
+        );
+        let mut adapter = ExchangeRateOracle::deploy(
+            &env,
+            ExchangeRateOracleInitArgs {
+                admin,
+                base_oracle: base_oracle.address(),
+            },
+        );
+
+        env.set_caller(admin);
+        base_oracle.set_price(base_asset, wad);
+        source.set_rate(wad);
+        adapter.configure_feed(
+            token.address(),
+            base_asset,
+            source.address(),
+            "get_rate".to_string(),
+            U256::from(WAD_U128 / 1_000),
+            U256::from(WAD_U128 / 20),
+        );
+
+        // A 20% slashing is past the 5% bound, so nobody can move the feed.
+        let slashed = wad * U256::from(8u8) / U256::from(10u8);
+        source.set_rate(slashed);
+        let result = adapter.try_update_exchange_rate(token.address());
+        assert_eq!(result, Err(Error::ExchangeRateOutOfBounds.into()));
+        let result = adapter.try_get_price_checked(token.address());
+        assert_eq!(result, Err(Error::ExchangeRateOutOfBounds.into()));
+
+        env.set_caller(env.get_account(2));
+        let result = adapter.try_accept_exchange_rate(token.address());
+        assert!(result.is_err());
+
+        env.set_caller(admin);
+        adapter.accept_exchange_rate(token.address());
+        assert!(env.emitted_event(
+            &adapter,
+            ExchangeRateOverridden {
+                asset: token.address(),
+                previous_rate: wad,
+                rate: slashed,
+            }
+        ));
+        assert_eq!(adapter.get_feed(token.address()).last_rate, slashed);
+        assert_eq!(adapter.get_price_checked(token.address()), slashed);
+
+        // A zero rate is never accepted.
+        source.set_rate(U256::zero());
+        let result = adapter.try_accept_exchange_rate(token.address());
+        assert_eq!(result, Err(Error::ExchangeRateOutOfBounds.into()));
+    }
+
+    #[test]
+    fn non_admin_cannot_configure_feed() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let asset = env.get_account(1);
+        let mut adapter = ExchangeRateOracle::deploy(
+            &env,
+            ExchangeRateOracleInitArgs {
+                admin,
+                base_oracle: env.get_account(2),
+            },
+        );
+
+        env.set_caller(env.get_account(3));
+        let result = adapter.try_configure_feed(
+            asset,
+            env.get_account(4),
+            env.get_account(5),
+            "get_rate".to_string(),
+            U256::zero(),
+            U256::zero(),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn market_values_collateral_through_adapter() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let user = env.get_account(1);
+        let base_asset = env.get_account(2);
+        let wad = U256::from(WAD_U128);
+
+        let mut underlying = TestToken::deploy(
+            &env,
+            TestTokenInitArgs {
+                name: "Staked CSPR".to_string(),
+                symbol: "sCSPR".to_string(),
+                decimals: 9,
+            },
+        );
+        let asset = underlying.address();
+        let mut base_oracle = PriceOracle::deploy(
+            &env,
+            PriceOracleInitArgs {
+                admin,
+                max_stale_millis: 10_000u64,
+            },
+        );
+        let mut source = MockRateSource::deploy(&env, NoArgs);
+        let mut adapter = ExchangeRateOracle::deploy(
+            &env,
+            ExchangeRateOracleInitArgs {
+                admin,
+                base_oracle: base_oracle.address(),
+            },
+        );
+
+        env.set_caller(admin);
+        base_oracle.set_price(base_asset, wad);
+        source.set_rate(wad * U256::from(11u8) / U256::from(10u8));
+        adapter.configure_feed(
+            asset,
+            base_asset,
+            source.address(),
+            "get_rate".to_string(),
+            U256::from(WAD_U128 / 1_000),
+            U256::from(WAD_U128 / 20),
+        );
+
+        let mut market = LendingMarket::deploy(
+            &env,
+            LendingMarketInitArgs {
+                config: MarketConfig {
+                    admin,
+                    asset,
+                    a_token: env.get_account(4),
+                    oracle: adapter.address(),
+                    registry: admin,
+                    rate_model: RateModel::default(),
+                    risk_params: RiskParams {
+                        collateral_factor: U256::from(WAD_U128 * 75 / 100),
+                        liquidation_threshold: U256::from(WAD_U128 * 80 / 100),
+                        close_factor: U256::from(WAD_U128 / 2),
+                        liquidation_bonus: U256::from(WAD_U128 * 5 / 100),
+                        reserve_factor: U256::zero(),
+                        borrow_cap: U256::zero(),
+                        supply_cap: U256::zero(),
+                    },
+                },
+            },
+        );
+        let a_token = AToken::deploy(
+            &env,
+            ATokenInitArgs {
+                name: "Anchor Staked CSPR".to_string(),
+                symbol: "asCSPR".to_string(),
+                decimals: 9,
+                market: market.address(),
+            },
+        );
+        let mut registry = MarketRegistry::deploy(&env, MarketRegistryInitArgs { admin });
+        registry.register_market(asset, market.address(), a_token.address(), adapter.address());
+        market.set_a_token(a_token.address());
+        market.set_registry(registry.address());
+
+        let amount = U256::from(100_000_000_000u64);
+        underlying.mint(user, amount);
+        env.set_caller(user);
+        underlying.approve(&market.address(), &amount);
+        market.deposit(amount);
+
+        assert_eq!(
+            market.get_collateral_value(user),
+            value_of(amount, wad * U256::from(11u8) / U256::from(10u8), 9)
+        );
+    }
+}
@@ -0,0 +1,477 @@
+use odra::casper_types::bytesrepr::{self, Bytes, FromBytes};
+use odra::casper_types::RuntimeArgs;
+use odra::prelude::*;
+use odra::CallDef;
+
+use crate::errors::Error;
+use crate::events::{
+    ProposalApproved, ProposalCancelled, ProposalCreated, ProposalExecuted, SignerAdded,
+    SignerRemoved, ThresholdUpdated,
+};
+use crate::types::{GovernanceProposal, ProposalStatus};
+
+const MAX_SIGNERS: u32 = 20;
+
+/// M-of-N executor meant to hold the admin roles of `MarketRegistry` and
+/// `LendingMarket`.
+///
+/// Invariants (MVP):
+/// - Only registered signers propose and approve; a proposal can run once it
+///   has `threshold` approvals from current signers.
+/// - A proposal executes at most once and never after `expires_at`; it is marked
+///   executed before the call is made.
+/// - Approvals are counted at execution time, so removing a signer drops theirs.
+///   Removal also bumps the signer's epoch, so the dropped approvals stay void
+///   if the same address is added back.
+/// - Signers and threshold change only through executed proposals that target
+///   this contract.
+#[odra::module(
+    events = [
+        ProposalCreated,
+        ProposalApproved,
+        ProposalExecuted,
+        ProposalCancelled,
+        SignerAdded,
+        SignerRemoved,
+        ThresholdUpdated
+    ],
+    errors = Error
+)]
+pub struct GovernanceExecutor {
+    signer_count: Var<u32>,
+    signer_by_index: Mapping<u32, Address>,
+    is_signer: Mapping<Address, bool>,
+    threshold: Var<u32>,
+    proposal_ttl_millis: Var<u64>,
+    proposal_count: Var<u64>,
+    proposals: Mapping<u64, GovernanceProposal>,
+    approvals: Mapping<(u64, Address, u32), bool>,
+    signer_epochs: Mapping<Address, u32>,
+}
+
+#[odra::module]
+impl GovernanceExecutor {
+    pub fn init(&mut self, signers: Vec<Address>, threshold: u32, proposal_ttl_millis: u64) {
+        if proposal_ttl_millis == 0 {
+            self.env().revert(Error::InvalidParam);
+        }
+        for signer in signers {
+            self.store_signer(signer);
+        }
+        self.store_threshold(threshold);
+        self.proposal_ttl_millis.set(proposal_ttl_millis);
+    }
+
+    /// Proposes calling `entry_point` on `target` with serialized `RuntimeArgs`.
+    /// The proposer's approval is recorded with the proposal.
+    pub fn propose(&mut self, target: Address, entry_point: String, args: Bytes) -> u64 {
+        let proposer = self.ensure_signer();
+        if entry_point.is_empty() || RuntimeArgs::from_bytes(&args).is_err() {
+            self.env().revert(Error::InvalidParam);
+        }
+        let expires_at = self
+            .env()
+            .get_block_time()
+            .checked_add(self.proposal_ttl_millis.get_or_default())
+            .unwrap_or_else(|| self.env().revert(Error::MathOverflow));
+        let id = self.proposal_count.get_or_default();
+        self.proposals.set(
+            &id,
+            GovernanceProposal {
+                proposer,
+                target,
+                entry_point: entry_point.clone(),
+                args,
+                expires_at,
+                status: ProposalStatus::Pending,
+            },
+        );
+        self.proposal_count.set(id + 1);
+        self.env().emit_event(ProposalCreated {
+            id,
+            proposer,
+            target,
+            entry_point,
+            expires_at,
+        });
+        self.record_approval(id, proposer);
+        id
+    }
+
+    pub fn approve(&mut self, id: u64) {
+        let signer = self.ensure_signer();
+        self.get_open_proposal(id);
+        if self.has_approved(id, signer) {
+            self.env().revert(Error::AlreadyApproved);
+        }
+        self.record_approval(id, signer);
+    }
+
+    pub fn execute(&mut self, id: u64) {
+        let executor = self.ensure_signer();
+        let mut proposal = self.get_open_proposal(id);
+        if self.get_approval_count(id) < self.threshold.get_or_default() {
+            self.env().revert(Error::ApprovalThresholdNotMet);
+        }
+        proposal.status = ProposalStatus::Executed;
+        self.proposals.set(&id, proposal.clone());
+
+        let args = RuntimeArgs::from_bytes(&proposal.args)
+            .map(|(args, _)| args)
+            .unwrap_or_else(|_| self.env().revert(Error::InvalidParam));
+        let call = CallDef::new(proposal.entry_point, true, args);
+        let _: ReturnValue = self.env().call_contract(proposal.target, call);
+        self.env().emit_event(ProposalExecuted { id, executor });
+    }
+
+    /// Withdraws a pending proposal; only its proposer may do so.
+    pub fn cancel(&mut self, id: u64) {
+        let caller = self.env().caller();
+        let mut proposal = self.get_open_proposal(id);
+        if proposal.proposer != caller {
+            self.env().revert(Error::Unauthorized);
+        }
+        proposal.status = ProposalStatus::Cancelled;
+        self.proposals.set(&id, proposal);
+        self.env().emit_event(ProposalCancelled { id });
+    }
+
+    pub fn add_signer(&mut self, signer: Address) {
+        self.ensure_self();
+        self.store_signer(signer);
+    }
+
+    pub fn remove_signer(&mut self, signer: Address) {
+        self.ensure_self();
+        if !self.is_signer.get_or_default(&signer) {
+            self.env().revert(Error::NotSigner);
+        }
+        let count = self.signer_count.get_or_default();
+        if count <= self.threshold.get_or_default() {
+            self.env().revert(Error::InvalidParam);
+        }
+        let last = count - 1;
+        for index in 0..count {
+            if self.signer_by_index.get(&index) == Some(signer) {
+                let moved = self
+                    .signer_by_index
+                    .get(&last)
+                    .unwrap_or_revert_with(&self.env(), Error::NotSigner);
+                self.signer_by_index.set(&index, moved);
+                break;
+            }
+        }
+        self.signer_count.set(last);
+        self.is_signer.set(&signer, false);
+        let epoch = self.signer_epochs.get_or_default(&signer);
+        self.signer_epochs.set(&signer, epoch + 1);
+        self.env().emit_event(SignerRemoved { signer });
+    }
+
+    pub fn set_threshold(&mut self, threshold: u32) {
+        self.ensure_self();
+        self.store_threshold(threshold);
+    }
+
+    pub fn get_proposal(&self, id: u64) -> GovernanceProposal {
+        self.proposals
+            .get(&id)
+            .unwrap_or_revert_with(&self.env(), Error::ProposalNotFound)
+    }
+
+    pub fn get_proposal_count(&self) -> u64 {
+        self.proposal_count.get_or_default()
+    }
+
+    /// Approvals on `id` from addresses that are signers right now.
+    pub fn get_approval_count(&self, id: u64) -> u32 {
+        self.get_signers()
+            .iter()
+            .filter(|signer| self.has_approved(id, **signer))
+            .count() as u32
+    }
+
+    /// Whether `signer` approved `id` since they were last added as a signer.
+    pub fn has_approved(&self, id: u64, signer: Address) -> bool {
+        self.approvals.get_or_default(&self.approval_key(id, signer))
+    }
+
+    pub fn get_signers(&self) -> Vec<Address> {
+        (0..self.signer_count.get_or_default())
+            .filter_map(|index| self.signer_by_index.get(&index))
+            .collect()
+    }
+
+    pub fn is_signer(&self, address: Address) -> bool {
+        self.is_signer.get_or_default(&address)
+    }
+
+    pub fn get_threshold(&self) -> u32 {
+        self.threshold.get_or_default()
+    }
+
+    pub fn get_proposal_ttl_millis(&self) -> u64 {
+        self.proposal_ttl_millis.get_or_default()
+    }
+}
+
+impl GovernanceExecutor {
+    fn ensure_signer(&self) -> Address {
+        let caller = self.env().caller();
+        if !self.is_signer.get_or_default(&caller) {
+            self.env().revert(Error::NotSigner);
+        }
+        caller
+    }
+
+    fn ensure_self(&self) {
+        if self.env().caller() != self.env().self_address() {
+            self.env().revert(Error::Unauthorized);
+        }
+    }
+
+    fn get_open_proposal(&self, id: u64) -> GovernanceProposal {
+        let proposal = self.get_proposal(id);
+        if proposal.status != ProposalStatus::Pending {
+            self.env().revert(Error::ProposalNotPending);
+        }
+        if self.env().get_block_time() > proposal.expires_at {
+            self.env().revert(Error::ProposalExpired);
+        }
+        proposal
+    }
+
+    fn approval_key(&self, id: u64, signer: Address) -> (u64, Address, u32) {
+        (id, signer, self.signer_epochs.get_or_default(&signer))
+    }
+
+    fn record_approval(&mut self, id: u64, signer: Address) {
+        self.approvals.set(&self.approval_key(id, signer), true);
+        self.env().emit_event(ProposalApproved { id, signer });
+    }
+
+    fn store_signer(&mut self, signer: Address) {
+        if self.is_signer.get_or_default(&signer) {
+            self.env().revert(Error::InvalidParam);
+        }
+        let count = self.signer_count.get_or_default();
+        if count >= MAX_SIGNERS {
+            self.env().revert(Error::InvalidParam);
+        }
+        self.signer_by_index.set(&count, signer);
+        self.signer_count.set(count + 1);
+        self.is_signer.set(&signer, true);
+        self.env().emit_event(SignerAdded { signer });
+    }
+
+    fn store_threshold(&mut self, threshold: u32) {
+        if threshold == 0 || threshold > self.signer_count.get_or_default() {
+            self.env().revert(Error::InvalidParam);
+        }
+        self.threshold.set(threshold);
+        self.env().emit_event(ThresholdUpdated { threshold });
+    }
+}
+
+/// Whatever the target entry point returned; the executor does not interpret it.
+struct ReturnValue;
+
+impl FromBytes for ReturnValue {
+    fn from_bytes(_bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        Ok((ReturnValue, &[]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lending_market::{LendingMarket, LendingMarketInitArgs};
+    use crate::market_registry::{MarketRegistry, MarketRegistryInitArgs};
+    use crate::math::WAD_U128;
+    use crate::types::{MarketConfig, RateModel, RiskParams};
+    use odra::casper_types::bytesrepr::ToBytes;
+    use odra::casper_types::{runtime_args, U256};
+    use odra::host::{Deployer, HostEnv};
+
+    const TTL: u64 = 60 * 60 * 1000;
+
+    fn deploy_executor(env: &HostEnv) -> GovernanceExecutorHostRef {
+        let init_args = GovernanceExecutorInitArgs {
+            signers: vec![env.get_account(1), env.get_account(2), env.get_account(3)],
+            threshold: 2,
+            proposal_ttl_millis: TTL,
+        };
+        GovernanceExecutor::deploy(env, init_args)
+    }
+
+    fn encode(args: RuntimeArgs) -> Bytes {
+        Bytes::from(args.to_bytes().unwrap())
+    }
+
+    #[test]
+    fn registry_call_needs_threshold_and_runs_once() {
+        let env = odra_test::env();
+        let mut governance = deploy_executor(&env);
+        let init_args = MarketRegistryInitArgs {
+            admin: governance.address(),
+        };
+        let registry = MarketRegistry::deploy(&env, init_args);
+
+        env.set_caller(env.get_account(1));
+        let args = encode(runtime_args! { "duration_millis" => 60_000u64 });
+        let id = governance.propose(
+            registry.address(),
+            "set_guardian_pause_duration".to_string(),
+            args,
+        );
+        assert!(env.emitted_event(
+            &governance,
+            ProposalCreated {
+                id,
+                proposer: env.get_account(1),
+                target: registry.address(),
+                entry_point: "set_guardian_pause_duration".to_string(),
+                expires_at: env.block_time() + TTL,
+            }
+        ));
+        assert_eq!(
+            governance.try_execute(id),
+            Err(Error::ApprovalThresholdNotMet.into())
+        );
+        assert_eq!(governance.try_approve(id), Err(Error::AlreadyApproved.into()));
+
+        env.set_caller(env.get_account(4));
+        assert_eq!(governance.try_approve(id), Err(Error::NotSigner.into()));
+
+        env.set_caller(env.get_account(2));
+        governance.approve(id);
+        assert_eq!(governance.get_approval_count(id), 2);
+        governance.execute(id);
+        assert_eq!(registry.get_guardian_pause_duration(), 60_000u64);
+        assert_eq!(governance.get_proposal(id).status, ProposalStatus::Executed);
+        assert!(env.emitted_event(
+            &governance,
+            ProposalExecuted {
+                id,
+                executor: env.get_account(2),
+            }
+        ));
+
+        assert_eq!(
+            governance.try_execute(id),
+            Err(Error::ProposalNotPending.into())
+        );
+    }
+
+    #[test]
+    fn market_call_expires_and_can_be_cancelled() {
+        let env = odra_test::env();
+        let mut governance = deploy_executor(&env);
+        let new_admin = env.get_account(5);
+        let init_args = LendingMarketInitArgs {
+            config: MarketConfig {
+                admin: governance.address(),
+                asset: env.get_account(6),
+                a_token: env.get_account(7),
+                oracle: env.get_account(8),
+                registry: env.get_account(9),
+                rate_model: RateModel {
+                    base_rate_per_sec: U256::zero(),
+                    slope_rate_per_sec: U256::zero(),
+                },
+                risk_params: RiskParams {
+                    close_factor: U256::from(WAD_U128 / 2),
+                    ..Default::default()
+                },
+            },
+        };
+        let market = LendingMarket::deploy(&env, init_args);
+        let args = encode(runtime_args! { "address" => new_admin });
+        let entry_point = "grant_market_admin".to_string();
+
+        env.set_caller(env.get_account(1));
+        let stale = governance.propose(market.address(), entry_point.clone(), args.clone());
+        env.set_caller(env.get_account(2));
+        governance.approve(stale);
+        env.advance_block_time(TTL + 1);
+        assert_eq!(governance.try_execute(stale), Err(Error::ProposalExpired.into()));
+
+        let cancelled = governance.propose(market.address(), entry_point.clone(), args.clone());
+        env.set_caller(env.get_account(1));
+        assert!(governance.try_cancel(cancelled).is_err());
+        env.set_caller(env.get_account(2));
+        governance.cancel(cancelled);
+        env.set_caller(env.get_account(3));
+        assert_eq!(
+            governance.try_approve(cancelled),
+            Err(Error::ProposalNotPending.into())
+        );
+
+        let id = governance.propose(market.address(), entry_point, args);
+        env.set_caller(env.get_account(1));
+        governance.approve(id);
+        governance.execute(id);
+        assert!(market.has_market_admin(new_admin));
+    }
+
+    #[test]
+    fn signer_set_changes_only_through_proposals() {
+        let env = odra_test::env();
+        let mut governance = deploy_executor(&env);
+        let newcomer = env.get_account(4);
+        let removed = env.get_account(3);
+
+        env.set_caller(env.get_account(1));
+        assert_eq!(governance.try_add_signer(newcomer), Err(Error::Unauthorized.into()));
+
+        let target = governance.address();
+        let id = governance.propose(
+            target,
+            "add_signer".to_string(),
+            encode(runtime_args! { "signer" => newcomer }),
+        );
+        env.set_caller(env.get_account(2));
+        governance.approve(id);
+        governance.execute(id);
+        assert!(governance.is_signer(newcomer));
+
+        // An approval from a signer who is later removed no longer counts.
+        let pending = governance.propose(
+            target,
+            "set_threshold".to_string(),
+            encode(runtime_args! { "threshold" => 3u32 }),
+        );
+        env.set_caller(removed);
+        governance.approve(pending);
+        let id = governance.propose(
+            target,
+            "remove_signer".to_string(),
+            encode(runtime_args! { "signer" => removed }),
+        );
+        env.set_caller(newcomer);
+        governance.approve(id);
+        governance.execute(id);
+        assert!(!governance.is_signer(removed));
+        assert_eq!(governance.get_signers().len(), 3);
+        assert_eq!(governance.get_approval_count(pending), 1);
+        assert_eq!(
+            governance.try_execute(pending),
+            Err(Error::ApprovalThresholdNotMet.into())
+        );
+
+        // Nor does it come back when the same address is added again.
+        let id = governance.propose(
+            target,
+            "add_signer".to_string(),
+            encode(runtime_args! { "signer" => removed }),
+        );
+        env.set_caller(env.get_account(1));
+        governance.approve(id);
+        governance.execute(id);
+        assert!(!governance.has_approved(pending, removed));
+        assert_eq!(governance.get_approval_count(pending), 1);
+        env.set_caller(removed);
+        governance.approve(pending);
+        assert_eq!(governance.get_approval_count(pending), 2);
+    }
+}
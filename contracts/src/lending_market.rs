@@ -1,28 +1,71 @@
 use odra::casper_types::U256;
 use odra::prelude::*;
 use odra::ContractRef;
-use odra_modules::access::{AccessControl, Role, DEFAULT_ADMIN_ROLE};
+use odra_modules::access::{Role, DEFAULT_ADMIN_ROLE};
 
 use crate::a_token::ATokenContractRef;
+use crate::admin_access::AdminAccess;
 use crate::cep18_interface::Cep18TokenContractRef;
 use crate::errors::Error;
 use crate::events::{
-    Borrow, Deposit, Liquidate, MarketStateUpdated, RateModelUpdated, Repay, RiskParamsUpdated,
-    Withdraw,
+    Borrow, BorrowAllowanceDelegated, BorrowOnBehalf, Deposit, Liquidate,
+    LiquidationGracePeriodUpdated, MarketStateUpdated, MarketUpgraded, RateModelUpdated, Repay,
+    RiskParamsUpdated, UserEModeSet, Withdraw,
 };
-use crate::math::{utilization_rate, wad_mul, WAD_U128};
+use crate::math::{utilization_rate, value_of, wad_div, wad_mul, WAD_U128};
+use crate::market_links::MarketLinks;
 use crate::market_registry::MarketRegistryContractRef;
+use crate::params::{validate_rate_model, validate_risk_params, MAX_BORROW_RATE_PER_SEC};
 use crate::price_oracle::PriceOracleContractRef;
-use crate::types::{PauseFlags, RateModel, RiskParams};
+use crate::rewards_controller::RewardsControllerContractRef;
+use crate::types::{MarketConfig, MarketStatus, PauseFlags, RateModel, RiskParams};
+use crate::variable_debt_token::VariableDebtTokenContractRef;
 
 const MARKET_ADMIN_ROLE: Role = *b"MARKET_ADMIN_ROLE_______________";
+/// Time for the deprecation premium to reach `MAX_BORROW_RATE_PER_SEC`.
+const DEPRECATION_RAMP_MILLIS: u64 = 30 * 24 * 60 * 60 * 1000;
+/// Storage layout version written by `init` and by `upgrade`.
+///
+/// 1: initial layout; 2: debt-token link; 3: `borrow_allowances`;
+/// 4: rewards-controller link; 5: `user_emode`; 6: debt scaled by `borrow_index`.
+pub const MARKET_VERSION: u32 = 6;
 
 /// Lending market for a single asset.
 ///
 /// Invariants (MVP):
-/// - `cash`, `total_borrows`, `total_reserves` are never negative.
-/// - `total_borrows` equals the sum of all `borrow_balances` (no external debt).
+/// - `cash`, `total_scaled_borrows`, `total_reserves` are never negative.
+/// - Debt is stored divided by `borrow_index`, so accrued interest is charged to
+///   each borrower; `total_scaled_borrows` equals the sum of all `scaled_borrows`.
 /// - aToken supply tracks user deposits minus withdrawals (ownership in aToken).
+/// - aToken amounts are denominated in underlying token units.
+/// - aToken transfers cannot leave the sender liquidatable (`before_a_token_transfer`).
+/// - Once a debt token is linked, borrow, repay and liquidate mint and burn it
+///   alongside `scaled_borrows`.
+/// - With a rewards controller set, every borrow-balance change is reported to
+///   it first, and the aToken reports supply changes.
+/// - A position opted into the E-mode category its asset belongs to uses the
+///   category's collateral factor, threshold and bonus instead of the market's.
+///   Collateral and debt are always this market's asset, so E-mode never lets
+///   one market's deposits back another market's debt.
+/// - `borrow_on_behalf` books debt on the delegator, checks the delegator's
+///   health and spends the delegatee's borrow allowance.
+/// - Issued principal is reported to the registry, which enforces the market's
+///   debt ceiling on every borrow.
+/// - Depositors, borrowers, borrow recipients, aToken recipients and liquidators
+///   must pass the registry's access mode; withdraw and repay never check it.
+/// - Risk params and rate models set after `init` satisfy `params` bounds.
+/// - Risk params, rate model and liquidation grace period only change through
+///   the registry, so every change waits out its timelock. Only the registry
+///   can move the registry link.
+/// - Risk values are normalised to WAD using the oracle's recorded asset decimals.
+/// - Status is read from the registry: only Active markets accept supply and borrow;
+///   Deprecated markets ramp up rates, and `liquidate` accepts any position with
+///   debt there, healthy or not, at half the bonus. Health checks elsewhere
+///   ignore the status.
+/// - Liquidations (not repayments) wait out `liquidation_grace_period` after the
+///   oracle reports a recovery from staleness.
+/// - Storage fields keep their order across versions; `upgrade` migrates state
+///   in place and never moves balances.
 #[odra::module(
     events = [
         Deposit,
@@ -32,78 +75,150 @@ const MARKET_ADMIN_ROLE: Role = *b"MARKET_ADMIN_ROLE_______________";
         Liquidate,
         MarketStateUpdated,
         RateModelUpdated,
-        RiskParamsUpdated
+        RiskParamsUpdated,
+        LiquidationGracePeriodUpdated,
+        MarketUpgraded,
+        BorrowAllowanceDelegated,
+        BorrowOnBehalf,
+        UserEModeSet
     ],
-    errors = Error
+    errors = Error,
+    factory = on
 )]
 pub struct LendingMarket {
-    access_control: SubModule<AccessControl>,
-    asset: Var<Address>,
-    a_token: Var<Address>,
-    oracle: Var<Address>,
-    registry: Var<Address>,
+    access_control: SubModule<AdminAccess>,
+    links: SubModule<MarketLinks>,
     rate_model: Var<RateModel>,
     risk_params: Var<RiskParams>,
     cash: Var<U256>,
-    total_borrows: Var<U256>,
+    total_scaled_borrows: Var<U256>,
     total_reserves: Var<U256>,
     supply_index: Var<U256>,
     borrow_index: Var<U256>,
     last_accrual: Var<u64>,
-    borrow_balances: Mapping<Address, U256>,
+    scaled_borrows: Mapping<Address, U256>,
+    liquidation_grace_period: Var<u64>,
+    version: Var<u32>,
+    borrow_allowances: Mapping<(Address, Address), U256>,
+    user_emode: Mapping<Address, u8>,
 }
 
-#[odra::module]
+// `factory = on` copies each entry point into a stub that ignores its
+// arguments, hence the `unused_variables` allows on those taking any.
+#[odra::module(factory = on)]
 impl LendingMarket {
-    pub fn init(
-        &mut self,
-        admin: Address,
-        asset: Address,
-        a_token: Address,
-        oracle: Address,
-        registry: Address,
-        rate_model: RateModel,
-        risk_params: RiskParams,
-    ) {
-        self.access_control
-            .unchecked_grant_role(&DEFAULT_ADMIN_ROLE, &admin);
+    #[allow(unused_variables)]
+    pub fn init(&mut self, config: MarketConfig) {
+        if let Err(error) = validate_rate_model(&config.rate_model) {
+            self.env().revert(error);
+        }
+        if let Err(error) = validate_risk_params(&config.risk_params) {
+            self.env().revert(error);
+        }
+        self.access_control.init_admin(config.admin);
         self.access_control
-            .unchecked_grant_role(&MARKET_ADMIN_ROLE, &admin);
+            .unchecked_grant_role(&MARKET_ADMIN_ROLE, &config.admin);
         self.access_control
             .set_admin_role(&MARKET_ADMIN_ROLE, &DEFAULT_ADMIN_ROLE);
 
-        self.asset.set(asset);
-        self.a_token.set(a_token);
-        self.oracle.set(oracle);
-        self.registry.set(registry);
-        self.rate_model.set(rate_model);
-        self.risk_params.set(risk_params);
+        self.links.init(config.asset, config.a_token, config.oracle, config.registry);
+        self.rate_model.set(config.rate_model);
+        self.risk_params.set(config.risk_params);
 
         let one = U256::from(WAD_U128);
         self.supply_index.set(one);
         self.borrow_index.set(one);
         self.last_accrual.set(self.env().get_block_time());
         self.cash.set(U256::zero());
-        self.total_borrows.set(U256::zero());
+        self.total_scaled_borrows.set(U256::zero());
         self.total_reserves.set(U256::zero());
+        self.version.set(MARKET_VERSION);
+    }
+
+    /// Runs when a new contract version is installed over this market.
+    ///
+    /// Casper only lets the package owner install it; per-version migrations
+    /// go here, keyed on the stored version. Running it twice is harmless.
+    /// Versions 2 to 5 only appended fields that start out empty.
+    pub fn upgrade(&mut self) {
+        let from_version = self.version.get_or_default();
+        if from_version > MARKET_VERSION {
+            self.env().revert(Error::UnsupportedMarketVersion);
+        }
+        if from_version < 6 {
+            self.migrate_to_scaled_debt();
+        }
+        self.version.set(MARKET_VERSION);
+        self.env().emit_event(MarketUpgraded {
+            from_version,
+            to_version: MARKET_VERSION,
+        });
+    }
+
+    /// Called by the aToken before `owner` sends aTokens, with the balance
+    /// `owner` keeps; reverts if that balance would leave `owner` liquidatable.
+    #[allow(unused_variables)]
+    pub fn before_a_token_transfer(
+        &mut self,
+        owner: Address,
+        recipient: Address,
+        remaining_balance: U256,
+    ) {
+        if self.env().caller() != self.links.get_a_token() {
+            self.env().revert(Error::Unauthorized);
+        }
+        self.ensure_account_allowed(recipient);
+        if self.scaled_borrows.get_or_default(&owner).is_zero() {
+            return;
+        }
+        self.accrue_interest();
+        if self.is_liquidatable_with(owner, remaining_balance) {
+            self.env().revert(Error::HealthFactorTooLow);
+        }
     }
 
+    #[allow(unused_variables)]
     pub fn set_a_token(&mut self, a_token: Address) {
-        self.ensure_admin();
-        self.a_token.set(a_token);
+        self.ensure_admin_or_registry();
+        self.links.set_a_token(a_token);
     }
 
+    /// Registry-only: a market admin who could move this link could also
+    /// call the timelocked setters directly.
+    #[allow(unused_variables)]
     pub fn set_registry(&mut self, registry: Address) {
-        self.ensure_admin();
-        self.registry.set(registry);
+        self.ensure_registry();
+        self.links.set_registry(registry);
     }
 
-    pub fn set_rate_model(&mut self, base_rate_per_sec: U256, slope_rate_per_sec: U256) {
+    /// Links the variable-debt token; only the registry, through
+    /// `link_debt_token`, may do so.
+    #[allow(unused_variables)]
+    pub fn set_debt_token(&mut self, debt_token: Address) {
+        self.ensure_registry();
+        self.links.set_debt_token(debt_token);
+    }
+
+    /// Points the market and its aToken at `rewards_controller`.
+    #[allow(unused_variables)]
+    pub fn set_rewards_controller(&mut self, rewards_controller: Address) {
         self.ensure_admin_or_registry();
+        self.links.set_rewards_controller(rewards_controller);
+        self.load_a_token()
+            .set_rewards_controller(rewards_controller);
+    }
+
+    /// Registry-only, so every change waits out the registry timelock.
+    #[allow(unused_variables)]
+    pub fn set_rate_model(&mut self, base_rate_per_sec: U256, slope_rate_per_sec: U256) {
+        self.ensure_registry();
         let rate_model = RateModel {
             base_rate_per_sec,
             slope_rate_per_sec,
         };
+        if let Err(error) = validate_rate_model(&rate_model) {
+            self.env().revert(error);
+        }
         self.rate_model.set(rate_model);
         self.env().emit_event(RateModelUpdated {
             base_rate_per_sec,
@@ -111,44 +226,44 @@ impl LendingMarket {
         });
     }
 
-    pub fn set_risk_params(
-        &mut self,
-        collateral_factor: U256,
-        liquidation_threshold: U256,
-        close_factor: U256,
-        liquidation_bonus: U256,
-        reserve_factor: U256,
-        borrow_cap: U256,
-        supply_cap: U256,
-    ) {
-        self.ensure_admin_or_registry();
-        let risk_params = RiskParams {
-            collateral_factor,
-            liquidation_threshold,
-            close_factor,
-            liquidation_bonus,
-            reserve_factor,
-            borrow_cap,
-            supply_cap,
-        };
-        self.risk_params.set(risk_params);
+    /// Registry-only, so every change waits out the registry timelock.
+    #[allow(unused_variables)]
+    pub fn set_risk_params(&mut self, risk_params: RiskParams) {
+        self.ensure_registry();
+        if let Err(error) = validate_risk_params(&risk_params) {
+            self.env().revert(error);
+        }
+        self.risk_params.set(risk_params.clone());
         self.env().emit_event(RiskParamsUpdated {
-            collateral_factor,
-            liquidation_threshold,
-            close_factor,
-            liquidation_bonus,
-            reserve_factor,
-            borrow_cap,
-            supply_cap,
+            collateral_factor: risk_params.collateral_factor,
+            liquidation_threshold: risk_params.liquidation_threshold,
+            close_factor: risk_params.close_factor,
+            liquidation_bonus: risk_params.liquidation_bonus,
+            reserve_factor: risk_params.reserve_factor,
+            borrow_cap: risk_params.borrow_cap,
+            supply_cap: risk_params.supply_cap,
+        });
+    }
+
+    /// Registry-only, so every change waits out the registry timelock.
+    #[allow(unused_variables)]
+    pub fn set_liquidation_grace_period(&mut self, grace_period_millis: u64) {
+        self.ensure_registry();
+        self.liquidation_grace_period.set(grace_period_millis);
+        self.env().emit_event(LiquidationGracePeriodUpdated {
+            grace_period_millis,
         });
     }
 
+    #[allow(unused_variables)]
     pub fn deposit(&mut self, amount: U256) {
         self.ensure_amount_nonzero(&amount);
+        self.ensure_market_active();
         self.ensure_supply_not_paused();
         self.accrue_interest();
         self.load_oracle().get_price_checked(self.load_asset());
         let caller = self.env().caller();
+        self.ensure_account_allowed(caller);
 
         let supply_cap = self.risk_params.get_or_default().supply_cap;
         if !supply_cap.is_zero() {
@@ -177,6 +292,14 @@ impl LendingMarket {
         self.env().emit_event(Deposit { account: caller, amount });
     }
 
+    /// `deposit`, attributing the amount to `referral_code` in the registry.
+    #[allow(unused_variables)]
+    pub fn deposit_with_referral(&mut self, amount: U256, referral_code: u32) {
+        self.deposit(amount);
+        self.record_referral(referral_code, amount, false);
+    }
+
+    #[allow(unused_variables)]
     pub fn withdraw(&mut self, amount: U256) {
         self.ensure_amount_nonzero(&amount);
         self.ensure_withdraw_not_paused();
@@ -197,7 +320,7 @@ impl LendingMarket {
         let mut a_token = self.load_a_token();
         a_token.burn(caller, amount);
 
-        if self.borrow_balances.get_or_default(&caller) > U256::zero()
+        if self.scaled_borrows.get_or_default(&caller) > U256::zero()
             && self.is_liquidatable(caller)
         {
             self.env().revert(Error::HealthFactorTooLow);
@@ -209,69 +332,86 @@ impl LendingMarket {
         self.env().emit_event(Withdraw { account: caller, amount });
     }
 
+    #[allow(unused_variables)]
     pub fn borrow(&mut self, amount: U256) {
-        self.ensure_amount_nonzero(&amount);
-        self.ensure_borrow_not_paused();
-        self.accrue_interest();
         let caller = self.env().caller();
+        self.borrow_for(caller, caller, amount);
+    }
 
-        let cash = self.cash.get_or_default();
-        if cash < amount {
-            self.env().revert(Error::InsufficientLiquidity);
+    /// Opts the caller's position into E-mode `category_id`, which must be
+    /// this asset's category; 0 opts out. The position must stay healthy.
+    #[allow(unused_variables)]
+    pub fn set_user_emode(&mut self, category_id: u8) {
+        let account = self.env().caller();
+        if category_id != 0
+            && self.load_registry().get_asset_emode_category(self.load_asset()) != category_id
+        {
+            self.env().revert(Error::EModeCategoryMismatch);
         }
-
-        let asset = self
-            .asset
-            .get()
-            .unwrap_or_revert_with(&self.env(), Error::InvalidParam);
-        let price = self.load_oracle().get_price_checked(asset);
-        let collateral = self.load_a_token().balance_of(&caller);
-        let collateral_value = wad_mul(collateral, price);
-
-        let current_borrow = self.borrow_balances.get_or_default(&caller);
-        let new_borrow = current_borrow
-            .checked_add(amount)
-            .unwrap_or_else(|| self.env().revert(Error::MathOverflow));
-        let borrow_value = wad_mul(new_borrow, price);
-
-        let max_borrow = wad_mul(
-            collateral_value,
-            self.risk_params.get_or_default().collateral_factor,
-        );
-        if borrow_value > max_borrow {
+        self.accrue_interest();
+        self.user_emode.set(&account, category_id);
+        if self.is_liquidatable(account) {
             self.env().revert(Error::HealthFactorTooLow);
         }
+        self.env()
+            .emit_event(UserEModeSet { account, category_id });
+    }
 
-        let borrow_cap = self.risk_params.get_or_default().borrow_cap;
-        if !borrow_cap.is_zero() {
-            let total = self
-                .total_borrows
-                .get_or_default()
-                .checked_add(amount)
-                .unwrap_or_else(|| self.env().revert(Error::MathOverflow));
-            if total > borrow_cap {
-                self.env().revert(Error::BorrowTooLarge);
-            }
-        }
+    /// `borrow`, attributing the amount to `referral_code` in the registry.
+    #[allow(unused_variables)]
+    pub fn borrow_with_referral(&mut self, amount: U256, referral_code: u32) {
+        self.borrow(amount);
+        self.record_referral(referral_code, amount, true);
+    }
 
-        let mut asset_token = self.load_asset_token();
-        asset_token.transfer(&caller, &amount);
+    /// Lets `delegatee` borrow up to `amount` against the caller's collateral;
+    /// the debt stays with the caller. Replaces any previous allowance.
+    #[allow(unused_variables)]
+    pub fn approve_delegation(&mut self, delegatee: Address, amount: U256) {
+        let delegator = self.env().caller();
+        if delegator == delegatee {
+            self.env().revert(Error::InvalidParam);
+        }
+        self.borrow_allowances.set(&(delegator, delegatee), amount);
+        self.env().emit_event(BorrowAllowanceDelegated {
+            delegator,
+            delegatee,
+            amount,
+        });
+    }
 
-        self.borrow_balances.set(&caller, new_borrow);
-        self.total_borrows
-            .set(self.total_borrows.get_or_default() + amount);
-        self.cash.set(cash - amount);
+    /// Borrows against `delegator`'s position and sends the funds to the caller.
+    #[allow(unused_variables)]
+    pub fn borrow_on_behalf(&mut self, delegator: Address, amount: U256) {
+        let delegatee = self.env().caller();
+        let remaining_allowance = self
+            .get_borrow_allowance(delegator, delegatee)
+            .checked_sub(amount)
+            .unwrap_or_else(|| self.env().revert(Error::InsufficientBorrowAllowance));
+        self.borrow_allowances
+            .set(&(delegator, delegatee), remaining_allowance);
+        self.borrow_for(delegator, delegatee, amount);
+        self.env().emit_event(BorrowOnBehalf {
+            delegator,
+            delegatee,
+            amount,
+            remaining_allowance,
+        });
+    }
 
-        self.emit_market_state();
-        self.env().emit_event(Borrow { account: caller, amount });
+    #[allow(unused_variables)]
+    pub fn get_borrow_allowance(&self, delegator: Address, delegatee: Address) -> U256 {
+        self.borrow_allowances
+            .get_or_default(&(delegator, delegatee))
     }
 
+    #[allow(unused_variables)]
     pub fn repay(&mut self, amount: U256) {
         self.ensure_amount_nonzero(&amount);
         self.accrue_interest();
         self.load_oracle().get_price_checked(self.load_asset());
         let caller = self.env().caller();
-        let current = self.borrow_balances.get_or_default(&caller);
+        let current = self.get_borrow_balance(caller);
         if current.is_zero() {
             return;
         }
@@ -279,11 +419,8 @@ impl LendingMarket {
         let market = self.env().self_address();
         let mut asset_token = self.load_asset_token();
         asset_token.transfer_from(&caller, &market, &repay_amount);
-        self.borrow_balances.set(&caller, current - repay_amount);
-        self.total_borrows
-            .set(self.total_borrows.get_or_default() - repay_amount);
-        self.cash
-            .set(self.cash.get_or_default() + repay_amount);
+        self.notify_borrow_rewards(caller);
+        self.reduce_debt(caller, current, repay_amount);
 
         self.emit_market_state();
         self.env().emit_event(Repay {
@@ -292,18 +429,23 @@ impl LendingMarket {
         });
     }
 
+    #[allow(unused_variables)]
     pub fn liquidate(&mut self, borrower: Address, repay_amount: U256) {
         self.ensure_amount_nonzero(&repay_amount);
         self.ensure_liquidation_not_paused();
         self.accrue_interest();
 
         self.load_oracle().get_price_checked(self.load_asset());
-        if !self.is_liquidatable(borrower) {
+        self.ensure_liquidation_grace_elapsed();
+        self.ensure_account_allowed(self.env().caller());
+        // Deprecated markets wind down: any debt may be liquidated, at half the bonus.
+        let deprecated = self.load_status() == MarketStatus::Deprecated;
+        let borrow = self.get_borrow_balance(borrower);
+        if borrow.is_zero() || !(deprecated || self.is_liquidatable(borrower)) {
             self.env().revert(Error::NotLiquidatable);
         }
 
         let close_factor = self.risk_params.get_or_default().close_factor;
-        let borrow = self.borrow_balances.get_or_default(&borrower);
         let max_repay = wad_mul(borrow, close_factor);
         let actual_repay = if repay_amount > max_repay {
             max_repay
@@ -314,20 +456,22 @@ impl LendingMarket {
             self.env().revert(Error::LiquidationTooLarge);
         }
 
-        let bonus = self.risk_params.get_or_default().liquidation_bonus;
+        let mut bonus = self.get_account_risk_params(borrower).liquidation_bonus;
+        if deprecated {
+            bonus /= U256::from(2u8);
+        }
         let one = U256::from(WAD_U128);
         let seize = wad_mul(actual_repay, one + bonus);
         let collateral = self.load_a_token().balance_of(&borrower);
         let seize_amount = if seize > collateral { collateral } else { seize };
 
-        self.borrow_balances
-            .set(&borrower, borrow - actual_repay);
-        self.total_borrows
-            .set(self.total_borrows.get_or_default() - actual_repay);
-        self.cash
-            .set(self.cash.get_or_default() + actual_repay);
-
         let liquidator = self.env().caller();
+        let market = self.env().self_address();
+        let mut asset_token = self.load_asset_token();
+        asset_token.transfer_from(&liquidator, &market, &actual_repay);
+        self.notify_borrow_rewards(borrower);
+        self.reduce_debt(borrower, borrow, actual_repay);
+
         let mut a_token = self.load_a_token();
         a_token.burn(borrower, seize_amount);
         a_token.mint(liquidator, seize_amount);
@@ -341,6 +485,34 @@ impl LendingMarket {
         });
     }
 
+    pub fn get_version(&self) -> u32 {
+        self.version.get_or_default()
+    }
+
+    pub fn get_asset(&self) -> Address {
+        self.load_asset()
+    }
+
+    pub fn get_a_token(&self) -> Address {
+        self.links.get_a_token()
+    }
+
+    pub fn get_oracle(&self) -> Address {
+        self.links.get_oracle()
+    }
+
+    pub fn get_registry(&self) -> Address {
+        self.links.get_registry()
+    }
+
+    pub fn get_debt_token(&self) -> Option<Address> {
+        self.links.get_debt_token()
+    }
+
+    pub fn get_rewards_controller(&self) -> Option<Address> {
+        self.links.get_rewards_controller()
+    }
+
     pub fn get_cash(&self) -> U256 {
         self.cash.get_or_default()
     }
@@ -353,32 +525,57 @@ impl LendingMarket {
         self.supply_index.get_or_default()
     }
 
+    /// Debt across all borrowers at the last accrued borrow index.
     pub fn get_total_borrows(&self) -> U256 {
-        self.total_borrows.get_or_default()
+        wad_mul(
+            self.total_scaled_borrows.get_or_default(),
+            self.borrow_index.get_or_default(),
+        )
     }
 
     pub fn get_total_reserves(&self) -> U256 {
         self.total_reserves.get_or_default()
     }
 
+    /// `owner`'s debt, interest included, at the last accrued borrow index.
+    #[allow(unused_variables)]
     pub fn get_borrow_balance(&self, owner: Address) -> U256 {
-        self.borrow_balances.get_or_default(&owner)
+        wad_mul(
+            self.scaled_borrows.get_or_default(&owner),
+            self.borrow_index.get_or_default(),
+        )
     }
 
-    pub fn health_factor(&self, owner: Address) -> U256 {
-        let price = self.load_oracle().get_price_checked(self.load_asset());
+    /// Collateral value of `owner` in WAD, independent of the asset's decimals.
+    #[allow(unused_variables)]
+    pub fn get_collateral_value(&self, owner: Address) -> U256 {
+        let (price, decimals) = self.load_price_and_decimals();
         let collateral = self.load_a_token().balance_of(&owner);
-        let collateral_value = wad_mul(collateral, price);
-        let borrow = self.borrow_balances.get_or_default(&owner);
-        if borrow.is_zero() {
+        value_of(collateral, price, decimals)
+    }
+
+    /// Debt value of `owner` in WAD, independent of the asset's decimals.
+    #[allow(unused_variables)]
+    pub fn get_borrow_value(&self, owner: Address) -> U256 {
+        let (price, decimals) = self.load_price_and_decimals();
+        value_of(self.get_borrow_balance(owner), price, decimals)
+    }
+
+    #[allow(unused_variables)]
+    pub fn health_factor(&self, owner: Address) -> U256 {
+        let borrow_value = self.get_borrow_value(owner);
+        if borrow_value.is_zero() {
             return U256::from(WAD_U128);
         }
-        let borrow_value = wad_mul(borrow, price);
         let threshold = wad_mul(
-            collateral_value,
-            self.risk_params.get_or_default().liquidation_threshold,
+            self.get_collateral_value(owner),
+            self.get_account_risk_params(owner).liquidation_threshold,
         );
-        wad_mul(threshold, U256::from(WAD_U128) / borrow_value)
+        wad_div(threshold, borrow_value)
+    }
+
+    pub fn get_liquidation_grace_period(&self) -> u64 {
+        self.liquidation_grace_period.get_or_default()
     }
 
     pub fn get_rate_model(&self) -> RateModel {
@@ -389,26 +586,45 @@ impl LendingMarket {
         self.risk_params.get_or_default()
     }
 
+    /// Market risk params with `account`'s E-mode category applied, if it
+    /// still covers this market's asset.
+    #[allow(unused_variables)]
+    pub fn get_account_risk_params(&self, account: Address) -> RiskParams {
+        let mut params = self.risk_params.get_or_default();
+        let category_id = self.get_user_emode(account);
+        if category_id == 0 {
+            return params;
+        }
+        let registry = self.load_registry();
+        if registry.get_asset_emode_category(self.load_asset()) != category_id {
+            return params;
+        }
+        if let Some(category) = registry.get_emode_category(category_id) {
+            params.collateral_factor = category.collateral_factor;
+            params.liquidation_threshold = category.liquidation_threshold;
+            params.liquidation_bonus = category.liquidation_bonus;
+        }
+        params
+    }
+
+    #[allow(unused_variables)]
+    pub fn get_user_emode(&self, account: Address) -> u8 {
+        self.user_emode.get_or_default(&account)
+    }
+
+    #[allow(unused_variables)]
     pub fn is_liquidatable(&self, owner: Address) -> bool {
-        let price = self.load_oracle().get_price_checked(self.load_asset());
-        let collateral = self.load_a_token().balance_of(&owner);
-        let collateral_value = wad_mul(collateral, price);
-        let borrow = self.borrow_balances.get_or_default(&owner);
-        if borrow.is_zero() {
+        if self.scaled_borrows.get_or_default(&owner).is_zero() {
             return false;
         }
-        let borrow_value = wad_mul(borrow, price);
-        let threshold = wad_mul(
-            collateral_value,
-            self.risk_params.get_or_default().liquidation_threshold,
-        );
-        borrow_value > threshold
+        let collateral = self.load_a_token().balance_of(&owner);
+        self.is_liquidatable_with(owner, collateral)
     }
 
     pub fn get_utilization(&self) -> U256 {
         utilization_rate(
             self.cash.get_or_default(),
-            self.total_borrows.get_or_default(),
+            self.get_total_borrows(),
             self.total_reserves.get_or_default(),
         )
     }
@@ -419,6 +635,7 @@ impl LendingMarket {
         let slope_part = wad_mul(rate.slope_rate_per_sec, util);
         rate.base_rate_per_sec
             .checked_add(slope_part)
+            .and_then(|v| v.checked_add(self.deprecation_premium()))
             .unwrap_or_else(|| self.env().revert(Error::MathOverflow))
     }
 
@@ -447,20 +664,22 @@ impl LendingMarket {
         }
         self.last_accrual.set(now);
 
-        let borrows = self.total_borrows.get_or_default();
-        if borrows.is_zero() {
+        if self.total_scaled_borrows.get_or_default().is_zero() {
             return;
         }
+        let borrows = self.get_total_borrows();
         let borrow_rate = self.get_borrow_rate_per_sec();
         let interest_factor = borrow_rate
             .checked_mul(U256::from(delta_secs))
             .unwrap_or_else(|| self.env().revert(Error::MathOverflow));
-        let interest = wad_mul(borrows, interest_factor);
 
-        let new_borrows = borrows
-            .checked_add(interest)
+        let one = U256::from(WAD_U128);
+        let borrow_index = self.borrow_index.get_or_default();
+        let borrow_index_factor = one
+            .checked_add(interest_factor)
             .unwrap_or_else(|| self.env().revert(Error::MathOverflow));
-        self.total_borrows.set(new_borrows);
+        self.borrow_index.set(wad_mul(borrow_index, borrow_index_factor));
+        let interest = self.get_total_borrows().saturating_sub(borrows);
 
         let reserve_factor = self.risk_params.get_or_default().reserve_factor;
         let reserve_add = wad_mul(interest, reserve_factor);
@@ -471,13 +690,6 @@ impl LendingMarket {
             .unwrap_or_else(|| self.env().revert(Error::MathOverflow));
         self.total_reserves.set(new_reserves);
 
-        let one = U256::from(WAD_U128);
-        let borrow_index = self.borrow_index.get_or_default();
-        let borrow_index_factor = one
-            .checked_add(interest_factor)
-            .unwrap_or_else(|| self.env().revert(Error::MathOverflow));
-        self.borrow_index.set(wad_mul(borrow_index, borrow_index_factor));
-
         let supply_rate = self.get_supply_rate_per_sec();
         let supply_factor = supply_rate
             .checked_mul(U256::from(delta_secs))
@@ -491,12 +703,39 @@ impl LendingMarket {
         self.emit_market_state();
     }
 
+    delegate! {
+        to self.access_control {
+            #[allow(unused_variables)]
+            fn propose_admin(&mut self, pending_admin: Address);
+            fn cancel_admin_transfer(&mut self);
+            fn accept_admin(&mut self);
+            fn get_admin(&self) -> Address;
+            fn get_pending_admin(&self) -> Option<Address>;
+        }
+    }
+
+    #[allow(unused_variables)]
+    pub fn grant_market_admin(&mut self, address: Address) {
+        self.access_control
+            .grant_role(&MARKET_ADMIN_ROLE, &address);
+    }
+
+    #[allow(unused_variables)]
+    pub fn revoke_market_admin(&mut self, address: Address) {
+        self.access_control
+            .revoke_role(&MARKET_ADMIN_ROLE, &address);
+    }
+
+    #[allow(unused_variables)]
+    pub fn has_market_admin(&self, address: Address) -> bool {
+        self.access_control.has_role(&MARKET_ADMIN_ROLE, &address)
+    }
 }
 
 impl LendingMarket {
     fn emit_market_state(&self) {
         let cash = self.cash.get_or_default();
-        let total_borrows = self.total_borrows.get_or_default();
+        let total_borrows = self.get_total_borrows();
         let total_reserves = self.total_reserves.get_or_default();
         let supply_index = self.supply_index.get_or_default();
         let borrow_index = self.borrow_index.get_or_default();
@@ -518,25 +757,23 @@ impl LendingMarket {
         });
     }
 
-    fn ensure_admin(&self) {
-        self.access_control
-            .check_role(&MARKET_ADMIN_ROLE, &self.env().caller());
-    }
-
     fn ensure_admin_or_registry(&self) {
         let caller = self.env().caller();
         let is_admin = self
             .access_control
             .has_role(&MARKET_ADMIN_ROLE, &caller);
-        let registry = self
-            .registry
-            .get()
-            .unwrap_or_revert_with(&self.env(), Error::InvalidParam);
+        let registry = self.links.get_registry();
         if !is_admin && caller != registry {
             self.env().revert(Error::Unauthorized);
         }
     }
 
+    fn ensure_registry(&self) {
+        if self.env().caller() != self.links.get_registry() {
+            self.env().revert(Error::Unauthorized);
+        }
+    }
+
     fn ensure_amount_nonzero(&self, amount: &U256) {
         if amount.is_zero() {
             self.env().revert(Error::InvalidParam);
@@ -544,41 +781,233 @@ impl LendingMarket {
     }
 
     fn load_a_token(&self) -> ATokenContractRef {
-        let address = self
-            .a_token
-            .get()
-            .unwrap_or_revert_with(&self.env(), Error::InvalidParam);
+        let address = self.links.get_a_token();
         ATokenContractRef::new(self.env(), address)
     }
 
+    /// Books `amount` of debt on `account` and sends the funds to `recipient`.
+    fn borrow_for(&mut self, account: Address, recipient: Address, amount: U256) {
+        self.ensure_amount_nonzero(&amount);
+        self.ensure_market_active();
+        self.ensure_borrow_not_paused();
+        self.ensure_account_allowed(account);
+        if recipient != account {
+            self.ensure_account_allowed(recipient);
+        }
+        self.accrue_interest();
+
+        let cash = self.cash.get_or_default();
+        if cash < amount {
+            self.env().revert(Error::InsufficientLiquidity);
+        }
+
+        let (price, decimals) = self.load_price_and_decimals();
+        let collateral = self.load_a_token().balance_of(&account);
+        let collateral_value = value_of(collateral, price, decimals);
+
+        let current_borrow = self.get_borrow_balance(account);
+        let new_borrow = current_borrow
+            .checked_add(amount)
+            .unwrap_or_else(|| self.env().revert(Error::MathOverflow));
+        let borrow_value = value_of(new_borrow, price, decimals);
+
+        let max_borrow = wad_mul(
+            collateral_value,
+            self.get_account_risk_params(account).collateral_factor,
+        );
+        if borrow_value > max_borrow {
+            self.env().revert(Error::HealthFactorTooLow);
+        }
+
+        let total = self
+            .get_total_borrows()
+            .checked_add(amount)
+            .unwrap_or_else(|| self.env().revert(Error::MathOverflow));
+        let borrow_cap = self.risk_params.get_or_default().borrow_cap;
+        if !borrow_cap.is_zero() && total > borrow_cap {
+            self.env().revert(Error::BorrowTooLarge);
+        }
+        self.load_registry().check_debt_ceiling(self.load_asset(), total);
+
+        let mut asset_token = self.load_asset_token();
+        asset_token.transfer(&recipient, &amount);
+
+        self.notify_borrow_rewards(account);
+        let scaled_amount = wad_div(amount, self.borrow_index.get_or_default());
+        let scaled_borrow = self
+            .scaled_borrows
+            .get_or_default(&account)
+            .checked_add(scaled_amount)
+            .unwrap_or_else(|| self.env().revert(Error::MathOverflow));
+        self.scaled_borrows.set(&account, scaled_borrow);
+        let total_scaled = self
+            .total_scaled_borrows
+            .get_or_default()
+            .checked_add(scaled_amount)
+            .unwrap_or_else(|| self.env().revert(Error::MathOverflow));
+        self.total_scaled_borrows.set(total_scaled);
+        self.cash.set(cash - amount);
+        if let Some(mut debt_token) = self.load_debt_token() {
+            debt_token.mint(account, amount);
+        }
+
+        self.emit_market_state();
+        self.env().emit_event(Borrow { account, amount });
+    }
+
+    fn record_referral(&self, referral_code: u32, amount: U256, is_borrow: bool) {
+        if referral_code == 0 {
+            return;
+        }
+        self.load_registry().record_referral(
+            self.load_asset(),
+            referral_code,
+            self.env().caller(),
+            amount,
+            is_borrow,
+        );
+    }
+
+    /// Takes `amount` off `account`'s `debt`; paying the whole debt clears the
+    /// scaled balance exactly, so no rounding dust is left behind.
+    fn reduce_debt(&mut self, account: Address, debt: U256, amount: U256) {
+        let scaled_borrow = self.scaled_borrows.get_or_default(&account);
+        let scaled_amount = if amount == debt {
+            scaled_borrow
+        } else {
+            wad_div(amount, self.borrow_index.get_or_default()).min(scaled_borrow)
+        };
+        self.scaled_borrows
+            .set(&account, scaled_borrow - scaled_amount);
+        let total_scaled = self
+            .total_scaled_borrows
+            .get_or_default()
+            .saturating_sub(scaled_amount);
+        self.total_scaled_borrows.set(total_scaled);
+        self.cash.set(self.cash.get_or_default() + amount);
+        if let Some(mut debt_token) = self.load_debt_token() {
+            debt_token.burn(account, amount);
+        }
+    }
+
+    /// Before v6 each balance held principal only, while the total also grew
+    /// with interest nobody was charged. v5 never recorded when a principal
+    /// was borrowed, so that interest is spread over borrowers by principal:
+    /// each stored principal becomes scaled debt at index `total / principal`.
+    /// The principal sum is `aToken supply - cash`, which v5 kept exact.
+    /// Interest that cannot be spread (rounding, or no borrowers left) is
+    /// dropped along with its share of reserves. Cash is then capped at the
+    /// asset actually held, as v5 liquidations booked repayments they never
+    /// collected.
+    fn migrate_to_scaled_debt(&mut self) {
+        let one = U256::from(WAD_U128);
+        let total = self.total_scaled_borrows.get_or_default();
+        let cash = self.cash.get_or_default();
+        let principal = self
+            .load_a_token()
+            .total_supply()
+            .saturating_sub(cash);
+        let index = if principal.is_zero() {
+            one
+        } else {
+            wad_div(total, principal).max(one)
+        };
+        self.borrow_index.set(index);
+        self.total_scaled_borrows.set(principal);
+
+        let dropped = total.saturating_sub(wad_mul(principal, index));
+        let reserve_factor = self.risk_params.get_or_default().reserve_factor;
+        let reserves = self.total_reserves.get_or_default();
+        self.total_reserves
+            .set(reserves.saturating_sub(wad_mul(dropped, reserve_factor)));
+
+        let held = self
+            .load_asset_token()
+            .balance_of(&self.env().self_address());
+        self.cash.set(cash.min(held));
+    }
+
+    /// Reports `account`'s borrow balance before it changes.
+    fn notify_borrow_rewards(&self, account: Address) {
+        let Some(controller) = self.links.get_rewards_controller() else {
+            return;
+        };
+        RewardsControllerContractRef::new(self.env(), controller).handle_borrow_action(
+            account,
+            self.get_borrow_balance(account),
+            self.get_total_borrows(),
+        );
+    }
+
+    fn load_debt_token(&self) -> Option<VariableDebtTokenContractRef> {
+        self.links
+            .get_debt_token()
+            .map(|address| VariableDebtTokenContractRef::new(self.env(), address))
+    }
+
     fn load_oracle(&self) -> PriceOracleContractRef {
-        let address = self
-            .oracle
-            .get()
-            .unwrap_or_revert_with(&self.env(), Error::InvalidParam);
+        let address = self.links.get_oracle();
         PriceOracleContractRef::new(self.env(), address)
     }
 
+    fn load_price_and_decimals(&self) -> (U256, u8) {
+        let asset = self.load_asset();
+        let oracle = self.load_oracle();
+        (oracle.get_price_checked(asset), oracle.get_asset_decimals(asset))
+    }
+
     fn load_asset_token(&self) -> Cep18TokenContractRef {
         Cep18TokenContractRef::new(self.env(), self.load_asset())
     }
 
     fn load_registry(&self) -> MarketRegistryContractRef {
-        let address = self
-            .registry
-            .get()
-            .unwrap_or_revert_with(&self.env(), Error::InvalidParam);
+        let address = self.links.get_registry();
         MarketRegistryContractRef::new(self.env(), address)
     }
 
     fn load_asset(&self) -> Address {
-        self.asset
-            .get()
-            .unwrap_or_revert_with(&self.env(), Error::InvalidParam)
+        self.links.get_asset()
+    }
+
+    fn is_liquidatable_with(&self, owner: Address, collateral: U256) -> bool {
+        let (price, decimals) = self.load_price_and_decimals();
+        let threshold = wad_mul(
+            value_of(collateral, price, decimals),
+            self.get_account_risk_params(owner).liquidation_threshold,
+        );
+        self.get_borrow_value(owner) > threshold
     }
 
     fn load_pause_flags(&self) -> PauseFlags {
-        self.load_registry().get_pause_flags(self.load_asset())
+        self.load_registry()
+            .get_effective_pause_flags(self.load_asset())
+    }
+
+    /// Extra borrow rate while Deprecated, growing linearly to the rate ceiling.
+    fn deprecation_premium(&self) -> U256 {
+        if self.load_status() != MarketStatus::Deprecated {
+            return U256::zero();
+        }
+        let since = self
+            .load_registry()
+            .get_market_status_updated_at(self.load_asset());
+        let elapsed = self
+            .env()
+            .get_block_time()
+            .saturating_sub(since)
+            .min(DEPRECATION_RAMP_MILLIS);
+        U256::from(MAX_BORROW_RATE_PER_SEC) * U256::from(elapsed)
+            / U256::from(DEPRECATION_RAMP_MILLIS)
+    }
+
+    fn ensure_market_active(&self) {
+        if self.load_status() != MarketStatus::Active {
+            self.env().revert(Error::MarketNotActive);
+        }
+    }
+
+    fn load_status(&self) -> MarketStatus {
+        self.load_registry().get_market_status(self.load_asset())
     }
 
     fn ensure_supply_not_paused(&self) {
@@ -587,6 +1016,15 @@ impl LendingMarket {
         }
     }
 
+    fn ensure_account_allowed(&self, account: Address) {
+        if !self
+            .load_registry()
+            .is_account_allowed(self.load_asset(), account)
+        {
+            self.env().revert(Error::AccountNotAllowed);
+        }
+    }
+
     fn ensure_borrow_not_paused(&self) {
         if self.load_pause_flags().borrow_paused {
             self.env().revert(Error::Paused);
@@ -604,32 +1042,57 @@ impl LendingMarket {
             self.env().revert(Error::Paused);
         }
     }
+
+    fn ensure_liquidation_grace_elapsed(&self) {
+        let recovered_at = self.load_oracle().get_recovered_at(self.load_asset());
+        if recovered_at == 0 {
+            return;
+        }
+        let grace_period = self.liquidation_grace_period.get_or_default();
+        let grace_ends = recovered_at.saturating_add(grace_period);
+        if self.env().get_block_time() < grace_ends {
+            self.env().revert(Error::LiquidationGracePeriod);
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::a_token::{AToken, ATokenInitArgs};
-    use crate::a_token::ATokenHostRef;
+    use crate::events::{AccessModeUpdated, AllowlistUpdated, DebtCeilingUpdated};
     use crate::market_registry::{MarketRegistry, MarketRegistryInitArgs, MarketRegistryHostRef};
-    use crate::price_oracle::{PriceOracle, PriceOracleInitArgs};
-    use odra::host::Deployer;
+    use crate::pause_control::unpaused;
+    use crate::params::{MAX_BASE_RATE_PER_SEC, MAX_BORROW_RATE_PER_SEC};
+    use crate::price_oracle::{PriceOracle, PriceOracleHostRef, PriceOracleInitArgs};
+    use crate::test_compliance::TestCompliance;
+    use crate::test_market_v1::{LendingMarketV1, LendingMarketV1InitArgs};
+    use crate::test_token::{TestToken, TestTokenHostRef, TestTokenInitArgs};
+    use crate::types::AccessMode;
+    use crate::variable_debt_token::{VariableDebtToken, VariableDebtTokenInitArgs};
+    use odra::host::{Deployer, NoArgs};
 
     const TEST_MINT_AMOUNT: u64 = 10_000;
 
-    fn deploy_underlying_token(env: &odra::host::HostEnv, admin: Address) -> ATokenHostRef {
-        let init_args = ATokenInitArgs {
+    fn deploy_underlying_token(env: &odra::host::HostEnv) -> TestTokenHostRef {
+        deploy_underlying_token_with_decimals(env, 9)
+    }
+
+    fn deploy_underlying_token_with_decimals(
+        env: &odra::host::HostEnv,
+        decimals: u8,
+    ) -> TestTokenHostRef {
+        let init_args = TestTokenInitArgs {
             name: "Underlying Token".to_string(),
             symbol: "uTKN".to_string(),
-            decimals: 9,
-            market: admin,
+            decimals,
         };
-        AToken::deploy(env, init_args)
+        TestToken::deploy(env, init_args)
     }
 
     fn seed_allowance(
         env: &odra::host::HostEnv,
-        token: &mut ATokenHostRef,
+        token: &mut TestTokenHostRef,
         admin: Address,
         owner: Address,
         spender: Address,
@@ -656,12 +1119,31 @@ mod tests {
         registry
     }
 
+    /// Pushes risk params through the registry timelock, then refreshes the
+    /// price that the wait left stale.
+    fn apply_risk_params(
+        env: &odra::host::HostEnv,
+        registry: &mut MarketRegistryHostRef,
+        oracle: &mut PriceOracleHostRef,
+        admin: Address,
+        oracle_admin: Address,
+        asset: Address,
+        risk_params: RiskParams,
+    ) {
+        env.set_caller(admin);
+        let id = registry.queue_market_risk_params(asset, risk_params);
+        env.advance_block_time(registry.get_timelock_delay());
+        registry.execute_queued_action(id);
+        env.set_caller(oracle_admin);
+        oracle.set_price(asset, U256::from(WAD_U128));
+    }
+
     #[test]
     fn deposit_mints_a_token_and_updates_cash() {
         let env = odra_test::env();
         let admin = env.get_account(0);
         let user = env.get_account(1);
-        let mut underlying = deploy_underlying_token(&env, admin);
+        let mut underlying = deploy_underlying_token(&env);
         let asset = underlying.address();
         let oracle_admin = env.get_account(3);
 
@@ -672,7 +1154,7 @@ mod tests {
         let risk_params = RiskParams {
             collateral_factor: U256::from(0u8),
             liquidation_threshold: U256::from(0u8),
-            close_factor: U256::from(0u8),
+            close_factor: U256::from(WAD_U128 / 2),
             liquidation_bonus: U256::from(0u8),
             reserve_factor: U256::from(0u8),
             borrow_cap: U256::from(0u8),
@@ -686,16 +1168,19 @@ mod tests {
         };
         let mut oracle = PriceOracle::deploy(&env, oracle_init);
         env.set_caller(oracle_admin);
+        oracle.register_asset(asset);
         oracle.set_price(asset, U256::from(WAD_U128));
 
         let market_init = LendingMarketInitArgs {
-            admin,
-            asset,
-            a_token: placeholder_token,
-            oracle: oracle.address(),
-            registry: env.get_account(9),
-            rate_model,
-            risk_params,
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: placeholder_token,
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model,
+                risk_params,
+            },
         };
         let mut market = LendingMarket::deploy(&env, market_init);
 
@@ -739,18 +1224,18 @@ mod tests {
         let env = odra_test::env();
         let admin = env.get_account(0);
         let user = env.get_account(1);
-        let mut underlying = deploy_underlying_token(&env, admin);
+        let mut underlying = deploy_underlying_token(&env);
         let asset = underlying.address();
         let oracle_admin = env.get_account(3);
 
         let rate_model = RateModel {
             base_rate_per_sec: U256::zero(),
-            slope_rate_per_sec: U256::from(WAD_U128 / 10),
+            slope_rate_per_sec: U256::from(MAX_BORROW_RATE_PER_SEC),
         };
         let risk_params = RiskParams {
-            collateral_factor: U256::from(WAD_U128),
-            liquidation_threshold: U256::zero(),
-            close_factor: U256::zero(),
+            collateral_factor: U256::from(WAD_U128 * 90 / 100),
+            liquidation_threshold: U256::from(WAD_U128 * 95 / 100),
+            close_factor: U256::from(WAD_U128 / 2),
             liquidation_bonus: U256::zero(),
             reserve_factor: U256::from(WAD_U128 / 10),
             borrow_cap: U256::zero(),
@@ -764,16 +1249,19 @@ mod tests {
         };
         let mut oracle = PriceOracle::deploy(&env, oracle_init);
         env.set_caller(oracle_admin);
+        oracle.register_asset(asset);
         oracle.set_price(asset, U256::from(WAD_U128));
 
         let init_args = LendingMarketInitArgs {
-            admin,
-            asset,
-            a_token: placeholder_token,
-            oracle: oracle.address(),
-            registry: env.get_account(9),
-            rate_model,
-            risk_params,
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: placeholder_token,
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model,
+                risk_params,
+            },
         };
         let mut market = LendingMarket::deploy(&env, init_args);
         let token_init = ATokenInitArgs {
@@ -815,10 +1303,10 @@ mod tests {
         env.set_caller(admin);
         market.deposit(U256::from(100u64));
         env.set_caller(user);
-        market.deposit(U256::from(100u64));
+        market.deposit(U256::from(200u64));
         market.borrow(U256::from(100u64));
 
-        env.advance_block_time(10_000u64);
+        env.advance_block_time(4_750_000_000u64);
 
         market.accrue_interest();
 
@@ -833,7 +1321,7 @@ mod tests {
         let env = odra_test::env();
         let admin = env.get_account(0);
         let user = env.get_account(1);
-        let mut underlying = deploy_underlying_token(&env, admin);
+        let mut underlying = deploy_underlying_token(&env);
         let asset = underlying.address();
         let oracle_admin = env.get_account(3);
 
@@ -843,8 +1331,8 @@ mod tests {
         };
         let risk_params = RiskParams {
             collateral_factor: U256::from(WAD_U128 * 75 / 100),
-            liquidation_threshold: U256::zero(),
-            close_factor: U256::zero(),
+            liquidation_threshold: U256::from(WAD_U128 * 80 / 100),
+            close_factor: U256::from(WAD_U128 / 2),
             liquidation_bonus: U256::zero(),
             reserve_factor: U256::zero(),
             borrow_cap: U256::from(1_000u64),
@@ -858,16 +1346,19 @@ mod tests {
         };
         let mut oracle = PriceOracle::deploy(&env, oracle_init);
         env.set_caller(oracle_admin);
+        oracle.register_asset(asset);
         oracle.set_price(asset, U256::from(WAD_U128));
 
         let market_init = LendingMarketInitArgs {
-            admin,
-            asset,
-            a_token: placeholder_token,
-            oracle: oracle.address(),
-            registry: env.get_account(9),
-            rate_model,
-            risk_params,
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: placeholder_token,
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model,
+                risk_params,
+            },
         };
         let mut market = LendingMarket::deploy(&env, market_init);
 
@@ -914,7 +1405,7 @@ mod tests {
         let env = odra_test::env();
         let admin = env.get_account(0);
         let user = env.get_account(1);
-        let mut underlying = deploy_underlying_token(&env, admin);
+        let mut underlying = deploy_underlying_token(&env);
         let asset = underlying.address();
         let oracle_admin = env.get_account(3);
 
@@ -923,9 +1414,9 @@ mod tests {
             slope_rate_per_sec: U256::zero(),
         };
         let risk_params = RiskParams {
-            collateral_factor: U256::from(WAD_U128),
-            liquidation_threshold: U256::zero(),
-            close_factor: U256::zero(),
+            collateral_factor: U256::from(WAD_U128 * 90 / 100),
+            liquidation_threshold: U256::from(WAD_U128 * 95 / 100),
+            close_factor: U256::from(WAD_U128 / 2),
             liquidation_bonus: U256::zero(),
             reserve_factor: U256::zero(),
             borrow_cap: U256::from(1_000u64),
@@ -939,16 +1430,19 @@ mod tests {
         };
         let mut oracle = PriceOracle::deploy(&env, oracle_init);
         env.set_caller(oracle_admin);
+        oracle.register_asset(asset);
         oracle.set_price(asset, U256::from(WAD_U128));
 
         let market_init = LendingMarketInitArgs {
-            admin,
-            asset,
-            a_token: placeholder_token,
-            oracle: oracle.address(),
-            registry: env.get_account(9),
-            rate_model,
-            risk_params,
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: placeholder_token,
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model,
+                risk_params,
+            },
         };
         let mut market = LendingMarket::deploy(&env, market_init);
 
@@ -972,15 +1466,11 @@ mod tests {
         market.set_a_token(a_token.address());
         market.set_registry(registry.address());
 
-        let flags = registry.get_pause_flags(asset);
-        registry.set_pause_flags(
-            asset,
-            flags.supply_paused,
-            true,
-            flags.withdraw_paused,
-            flags.repay_paused,
-            flags.liquidation_paused,
-        );
+        let flags = PauseFlags {
+            borrow_paused: true,
+            ..registry.get_pause_flags(asset)
+        };
+        registry.set_pause_flags(asset, flags, 0);
 
         seed_allowance(
             &env,
@@ -1002,7 +1492,7 @@ mod tests {
         let admin = env.get_account(0);
         let user = env.get_account(1);
         let liquidator = env.get_account(2);
-        let mut underlying = deploy_underlying_token(&env, admin);
+        let mut underlying = deploy_underlying_token(&env);
         let asset = underlying.address();
         let oracle_admin = env.get_account(4);
 
@@ -1011,8 +1501,8 @@ mod tests {
             slope_rate_per_sec: U256::zero(),
         };
         let risk_params = RiskParams {
-            collateral_factor: U256::from(WAD_U128),
-            liquidation_threshold: U256::from(WAD_U128 * 80 / 100),
+            collateral_factor: U256::from(WAD_U128 * 90 / 100),
+            liquidation_threshold: U256::from(WAD_U128 * 95 / 100),
             close_factor: U256::from(WAD_U128 / 2),
             liquidation_bonus: U256::from(WAD_U128 * 5 / 100),
             reserve_factor: U256::zero(),
@@ -1027,16 +1517,19 @@ mod tests {
         };
         let mut oracle = PriceOracle::deploy(&env, oracle_init);
         env.set_caller(oracle_admin);
+        oracle.register_asset(asset);
         oracle.set_price(asset, U256::from(WAD_U128));
 
         let market_init = LendingMarketInitArgs {
-            admin,
-            asset,
-            a_token: placeholder_token,
-            oracle: oracle.address(),
-            registry: env.get_account(9),
-            rate_model,
-            risk_params,
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: placeholder_token,
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model,
+                risk_params,
+            },
         };
         let mut market = LendingMarket::deploy(&env, market_init);
 
@@ -1047,7 +1540,7 @@ mod tests {
             market: market.address(),
         };
         let a_token = AToken::deploy(&env, token_init);
-        let registry = setup_registry(
+        let mut registry = setup_registry(
             &env,
             admin,
             asset,
@@ -1071,12 +1564,40 @@ mod tests {
         env.set_caller(user);
         market.deposit(U256::from(100u64));
         market.borrow(U256::from(90u64));
+        apply_risk_params(
+            &env,
+            &mut registry,
+            &mut oracle,
+            admin,
+            oracle_admin,
+            asset,
+            RiskParams {
+                collateral_factor: U256::from(WAD_U128 * 75 / 100),
+                liquidation_threshold: U256::from(WAD_U128 * 80 / 100),
+                close_factor: U256::from(WAD_U128 / 2),
+                liquidation_bonus: U256::from(WAD_U128 * 5 / 100),
+                ..Default::default()
+            },
+        );
 
-        env.set_caller(liquidator);
+        seed_allowance(
+            &env,
+            &mut underlying,
+            admin,
+            liquidator,
+            market.address(),
+            U256::from(TEST_MINT_AMOUNT),
+        );
         market.liquidate(user, U256::from(50u64));
 
         assert_eq!(market.get_borrow_balance(user), U256::from(45u64));
         assert_eq!(a_token.balance_of(&liquidator), U256::from(47u64));
+        // The liquidator pays the 45 repaid; it lands in the market's cash.
+        assert_eq!(
+            underlying.balance_of(&liquidator),
+            U256::from(TEST_MINT_AMOUNT - 45)
+        );
+        assert_eq!(market.get_cash(), U256::from(55u64));
     }
 
     #[test]
@@ -1085,7 +1606,7 @@ mod tests {
         let admin = env.get_account(0);
         let user = env.get_account(1);
         let liquidator = env.get_account(2);
-        let mut underlying = deploy_underlying_token(&env, admin);
+        let mut underlying = deploy_underlying_token(&env);
         let asset = underlying.address();
         let oracle_admin = env.get_account(4);
 
@@ -1094,7 +1615,7 @@ mod tests {
             slope_rate_per_sec: U256::zero(),
         };
         let risk_params = RiskParams {
-            collateral_factor: U256::from(WAD_U128),
+            collateral_factor: U256::from(WAD_U128 * 90 / 100),
             liquidation_threshold: U256::from(WAD_U128 * 90 / 100),
             close_factor: U256::from(WAD_U128 / 2),
             liquidation_bonus: U256::from(WAD_U128 * 5 / 100),
@@ -1110,16 +1631,19 @@ mod tests {
         };
         let mut oracle = PriceOracle::deploy(&env, oracle_init);
         env.set_caller(oracle_admin);
+        oracle.register_asset(asset);
         oracle.set_price(asset, U256::from(WAD_U128));
 
         let market_init = LendingMarketInitArgs {
-            admin,
-            asset,
-            a_token: placeholder_token,
-            oracle: oracle.address(),
-            registry: env.get_account(9),
-            rate_model,
-            risk_params,
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: placeholder_token,
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model,
+                risk_params,
+            },
         };
         let mut market = LendingMarket::deploy(&env, market_init);
 
@@ -1165,7 +1689,7 @@ mod tests {
         let env = odra_test::env();
         let admin = env.get_account(0);
         let user = env.get_account(1);
-        let mut underlying = deploy_underlying_token(&env, admin);
+        let mut underlying = deploy_underlying_token(&env);
         let asset = underlying.address();
         let oracle_admin = env.get_account(3);
 
@@ -1173,7 +1697,10 @@ mod tests {
             base_rate_per_sec: U256::zero(),
             slope_rate_per_sec: U256::zero(),
         };
-        let risk_params = RiskParams::default();
+        let risk_params = RiskParams {
+            close_factor: U256::from(WAD_U128 / 2),
+            ..Default::default()
+        };
 
         let placeholder_token = env.get_account(4);
         let oracle_init = PriceOracleInitArgs {
@@ -1182,16 +1709,19 @@ mod tests {
         };
         let mut oracle = PriceOracle::deploy(&env, oracle_init);
         env.set_caller(oracle_admin);
+        oracle.register_asset(asset);
         oracle.set_price(asset, U256::from(WAD_U128));
 
         let market_init = LendingMarketInitArgs {
-            admin,
-            asset,
-            a_token: placeholder_token,
-            oracle: oracle.address(),
-            registry: env.get_account(9),
-            rate_model,
-            risk_params,
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: placeholder_token,
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model,
+                risk_params,
+            },
         };
         let mut market = LendingMarket::deploy(&env, market_init);
 
@@ -1240,7 +1770,7 @@ mod tests {
         let env = odra_test::env();
         let admin = env.get_account(0);
         let user = env.get_account(1);
-        let mut underlying = deploy_underlying_token(&env, admin);
+        let mut underlying = deploy_underlying_token(&env);
         let asset = underlying.address();
         let oracle_admin = env.get_account(3);
 
@@ -1249,9 +1779,9 @@ mod tests {
             slope_rate_per_sec: U256::zero(),
         };
         let risk_params = RiskParams {
-            collateral_factor: U256::from(WAD_U128),
-            liquidation_threshold: U256::zero(),
-            close_factor: U256::zero(),
+            collateral_factor: U256::from(WAD_U128 * 90 / 100),
+            liquidation_threshold: U256::from(WAD_U128 * 95 / 100),
+            close_factor: U256::from(WAD_U128 / 2),
             liquidation_bonus: U256::zero(),
             reserve_factor: U256::zero(),
             borrow_cap: U256::from(1_000u64),
@@ -1265,16 +1795,19 @@ mod tests {
         };
         let mut oracle = PriceOracle::deploy(&env, oracle_init);
         env.set_caller(oracle_admin);
+        oracle.register_asset(asset);
         oracle.set_price(asset, U256::from(WAD_U128));
 
         let market_init = LendingMarketInitArgs {
-            admin,
-            asset,
-            a_token: placeholder_token,
-            oracle: oracle.address(),
-            registry: env.get_account(9),
-            rate_model,
-            risk_params,
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: placeholder_token,
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model,
+                risk_params,
+            },
         };
         let mut market = LendingMarket::deploy(&env, market_init);
 
@@ -1324,7 +1857,7 @@ mod tests {
         let env = odra_test::env();
         let admin = env.get_account(0);
         let user = env.get_account(1);
-        let mut underlying = deploy_underlying_token(&env, admin);
+        let mut underlying = deploy_underlying_token(&env);
         let asset = underlying.address();
         let oracle_admin = env.get_account(3);
 
@@ -1333,9 +1866,9 @@ mod tests {
             slope_rate_per_sec: U256::zero(),
         };
         let risk_params = RiskParams {
-            collateral_factor: U256::from(WAD_U128),
-            liquidation_threshold: U256::zero(),
-            close_factor: U256::zero(),
+            collateral_factor: U256::from(WAD_U128 * 90 / 100),
+            liquidation_threshold: U256::from(WAD_U128 * 95 / 100),
+            close_factor: U256::from(WAD_U128 / 2),
             liquidation_bonus: U256::zero(),
             reserve_factor: U256::zero(),
             borrow_cap: U256::from(1_000u64),
@@ -1349,16 +1882,19 @@ mod tests {
         };
         let mut oracle = PriceOracle::deploy(&env, oracle_init);
         env.set_caller(oracle_admin);
+        oracle.register_asset(asset);
         oracle.set_price(asset, U256::from(WAD_U128));
 
         let market_init = LendingMarketInitArgs {
-            admin,
-            asset,
-            a_token: placeholder_token,
-            oracle: oracle.address(),
-            registry: env.get_account(9),
-            rate_model,
-            risk_params,
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: placeholder_token,
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model,
+                risk_params,
+            },
         };
         let mut market = LendingMarket::deploy(&env, market_init);
 
@@ -1409,7 +1945,7 @@ mod tests {
         let env = odra_test::env();
         let admin = env.get_account(0);
         let user = env.get_account(1);
-        let mut underlying = deploy_underlying_token(&env, admin);
+        let mut underlying = deploy_underlying_token(&env);
         let asset = underlying.address();
         let oracle_admin = env.get_account(3);
 
@@ -1417,7 +1953,10 @@ mod tests {
             base_rate_per_sec: U256::zero(),
             slope_rate_per_sec: U256::zero(),
         };
-        let risk_params = RiskParams::default();
+        let risk_params = RiskParams {
+            close_factor: U256::from(WAD_U128 / 2),
+            ..Default::default()
+        };
 
         let placeholder_token = env.get_account(4);
         let oracle_init = PriceOracleInitArgs {
@@ -1426,16 +1965,19 @@ mod tests {
         };
         let mut oracle = PriceOracle::deploy(&env, oracle_init);
         env.set_caller(oracle_admin);
+        oracle.register_asset(asset);
         oracle.set_price(asset, U256::from(WAD_U128));
 
         let market_init = LendingMarketInitArgs {
-            admin,
-            asset,
-            a_token: placeholder_token,
-            oracle: oracle.address(),
-            registry: env.get_account(9),
-            rate_model,
-            risk_params,
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: placeholder_token,
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model,
+                risk_params,
+            },
         };
         let mut market = LendingMarket::deploy(&env, market_init);
 
@@ -1486,7 +2028,7 @@ mod tests {
         let admin = env.get_account(0);
         let user = env.get_account(1);
         let liquidator = env.get_account(2);
-        let mut underlying = deploy_underlying_token(&env, admin);
+        let mut underlying = deploy_underlying_token(&env);
         let asset = underlying.address();
         let oracle_admin = env.get_account(4);
 
@@ -1495,8 +2037,8 @@ mod tests {
             slope_rate_per_sec: U256::zero(),
         };
         let risk_params = RiskParams {
-            collateral_factor: U256::from(WAD_U128),
-            liquidation_threshold: U256::from(WAD_U128 * 80 / 100),
+            collateral_factor: U256::from(WAD_U128 * 90 / 100),
+            liquidation_threshold: U256::from(WAD_U128 * 95 / 100),
             close_factor: U256::from(WAD_U128 / 2),
             liquidation_bonus: U256::from(WAD_U128 * 5 / 100),
             reserve_factor: U256::zero(),
@@ -1511,16 +2053,19 @@ mod tests {
         };
         let mut oracle = PriceOracle::deploy(&env, oracle_init);
         env.set_caller(oracle_admin);
+        oracle.register_asset(asset);
         oracle.set_price(asset, U256::from(WAD_U128));
 
         let market_init = LendingMarketInitArgs {
-            admin,
-            asset,
-            a_token: placeholder_token,
-            oracle: oracle.address(),
-            registry: env.get_account(9),
-            rate_model,
-            risk_params,
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: placeholder_token,
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model,
+                risk_params,
+            },
         };
         let mut market = LendingMarket::deploy(&env, market_init);
 
@@ -1531,7 +2076,7 @@ mod tests {
             market: market.address(),
         };
         let a_token = AToken::deploy(&env, token_init);
-        let registry = setup_registry(
+        let mut registry = setup_registry(
             &env,
             admin,
             asset,
@@ -1555,8 +2100,30 @@ mod tests {
         env.set_caller(user);
         market.deposit(U256::from(100u64));
         market.borrow(U256::from(90u64));
+        apply_risk_params(
+            &env,
+            &mut registry,
+            &mut oracle,
+            admin,
+            oracle_admin,
+            asset,
+            RiskParams {
+                collateral_factor: U256::from(WAD_U128 * 75 / 100),
+                liquidation_threshold: U256::from(WAD_U128 * 80 / 100),
+                close_factor: U256::from(WAD_U128 / 2),
+                liquidation_bonus: U256::from(WAD_U128 * 5 / 100),
+                ..Default::default()
+            },
+        );
 
-        env.set_caller(liquidator);
+        seed_allowance(
+            &env,
+            &mut underlying,
+            admin,
+            liquidator,
+            market.address(),
+            U256::from(TEST_MINT_AMOUNT),
+        );
         market.liquidate(user, U256::from(50u64));
 
         assert!(env.emitted_event(
@@ -1575,7 +2142,7 @@ mod tests {
         let env = odra_test::env();
         let admin = env.get_account(0);
         let user = env.get_account(1);
-        let mut underlying = deploy_underlying_token(&env, admin);
+        let mut underlying = deploy_underlying_token(&env);
         let asset = underlying.address();
         let oracle_admin = env.get_account(3);
 
@@ -1600,16 +2167,19 @@ mod tests {
         };
         let mut oracle = PriceOracle::deploy(&env, oracle_init);
         env.set_caller(oracle_admin);
+        oracle.register_asset(asset);
         oracle.set_price(asset, U256::from(WAD_U128));
 
         let market_init = LendingMarketInitArgs {
-            admin,
-            asset,
-            a_token: placeholder_token,
-            oracle: oracle.address(),
-            registry: env.get_account(9),
-            rate_model,
-            risk_params,
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: placeholder_token,
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model,
+                risk_params,
+            },
         };
         let mut market = LendingMarket::deploy(&env, market_init);
 
@@ -1666,7 +2236,7 @@ mod tests {
         let env = odra_test::env();
         let admin = env.get_account(0);
         let user = env.get_account(1);
-        let mut underlying = deploy_underlying_token(&env, admin);
+        let mut underlying = deploy_underlying_token(&env);
         let asset = underlying.address();
         let oracle_admin = env.get_account(3);
 
@@ -1675,9 +2245,9 @@ mod tests {
             slope_rate_per_sec: U256::zero(),
         };
         let risk_params = RiskParams {
-            collateral_factor: U256::from(WAD_U128),
-            liquidation_threshold: U256::zero(),
-            close_factor: U256::zero(),
+            collateral_factor: U256::from(WAD_U128 * 90 / 100),
+            liquidation_threshold: U256::from(WAD_U128 * 95 / 100),
+            close_factor: U256::from(WAD_U128 / 2),
             liquidation_bonus: U256::zero(),
             reserve_factor: U256::zero(),
             borrow_cap: U256::from(1_000u64),
@@ -1691,16 +2261,19 @@ mod tests {
         };
         let mut oracle = PriceOracle::deploy(&env, oracle_init);
         env.set_caller(oracle_admin);
+        oracle.register_asset(asset);
         oracle.set_price(asset, U256::from(WAD_U128));
 
         let market_init = LendingMarketInitArgs {
-            admin,
-            asset,
-            a_token: placeholder_token,
-            oracle: oracle.address(),
-            registry: env.get_account(9),
-            rate_model,
-            risk_params,
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: placeholder_token,
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model,
+                risk_params,
+            },
         };
         let mut market = LendingMarket::deploy(&env, market_init);
 
@@ -1746,7 +2319,7 @@ mod tests {
         let admin = env.get_account(0);
         let user = env.get_account(1);
         let liquidator = env.get_account(2);
-        let mut underlying = deploy_underlying_token(&env, admin);
+        let mut underlying = deploy_underlying_token(&env);
         let asset = underlying.address();
         let oracle_admin = env.get_account(4);
 
@@ -1755,8 +2328,8 @@ mod tests {
             slope_rate_per_sec: U256::zero(),
         };
         let risk_params = RiskParams {
-            collateral_factor: U256::from(WAD_U128),
-            liquidation_threshold: U256::from(WAD_U128 * 80 / 100),
+            collateral_factor: U256::from(WAD_U128 * 90 / 100),
+            liquidation_threshold: U256::from(WAD_U128 * 95 / 100),
             close_factor: U256::from(WAD_U128 / 2),
             liquidation_bonus: U256::from(WAD_U128 * 5 / 100),
             reserve_factor: U256::zero(),
@@ -1771,16 +2344,19 @@ mod tests {
         };
         let mut oracle = PriceOracle::deploy(&env, oracle_init);
         env.set_caller(oracle_admin);
+        oracle.register_asset(asset);
         oracle.set_price(asset, U256::from(WAD_U128));
 
         let market_init = LendingMarketInitArgs {
-            admin,
-            asset,
-            a_token: placeholder_token,
-            oracle: oracle.address(),
-            registry: env.get_account(9),
-            rate_model,
-            risk_params,
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: placeholder_token,
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model,
+                risk_params,
+            },
         };
         let mut market = LendingMarket::deploy(&env, market_init);
 
@@ -1791,7 +2367,7 @@ mod tests {
             market: market.address(),
         };
         let a_token = AToken::deploy(&env, token_init);
-        let registry = setup_registry(
+        let mut registry = setup_registry(
             &env,
             admin,
             asset,
@@ -1815,6 +2391,21 @@ mod tests {
         env.set_caller(user);
         market.deposit(U256::from(100u64));
         market.borrow(U256::from(90u64));
+        apply_risk_params(
+            &env,
+            &mut registry,
+            &mut oracle,
+            admin,
+            oracle_admin,
+            asset,
+            RiskParams {
+                collateral_factor: U256::from(WAD_U128 * 75 / 100),
+                liquidation_threshold: U256::from(WAD_U128 * 80 / 100),
+                close_factor: U256::from(WAD_U128 / 2),
+                liquidation_bonus: U256::from(WAD_U128 * 5 / 100),
+                ..Default::default()
+            },
+        );
 
         env.advance_block_time(2_000u64);
         env.set_caller(liquidator);
@@ -1827,7 +2418,7 @@ mod tests {
         let env = odra_test::env();
         let admin = env.get_account(0);
         let user = env.get_account(1);
-        let mut underlying = deploy_underlying_token(&env, admin);
+        let mut underlying = deploy_underlying_token(&env);
         let asset = underlying.address();
         let oracle_admin = env.get_account(3);
 
@@ -1835,7 +2426,10 @@ mod tests {
             base_rate_per_sec: U256::zero(),
             slope_rate_per_sec: U256::zero(),
         };
-        let risk_params = RiskParams::default();
+        let risk_params = RiskParams {
+            close_factor: U256::from(WAD_U128 / 2),
+            ..Default::default()
+        };
 
         let placeholder_token = env.get_account(4);
         let oracle_init = PriceOracleInitArgs {
@@ -1844,16 +2438,19 @@ mod tests {
         };
         let mut oracle = PriceOracle::deploy(&env, oracle_init);
         env.set_caller(oracle_admin);
+        oracle.register_asset(asset);
         oracle.set_price(asset, U256::from(WAD_U128));
 
         let market_init = LendingMarketInitArgs {
-            admin,
-            asset,
-            a_token: placeholder_token,
-            oracle: oracle.address(),
-            registry: env.get_account(9),
-            rate_model,
-            risk_params,
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: placeholder_token,
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model,
+                risk_params,
+            },
         };
         let mut market = LendingMarket::deploy(&env, market_init);
 
@@ -1898,7 +2495,7 @@ mod tests {
         let env = odra_test::env();
         let admin = env.get_account(0);
         let user = env.get_account(1);
-        let mut underlying = deploy_underlying_token(&env, admin);
+        let mut underlying = deploy_underlying_token(&env);
         let asset = underlying.address();
         let oracle_admin = env.get_account(3);
 
@@ -1907,9 +2504,9 @@ mod tests {
             slope_rate_per_sec: U256::zero(),
         };
         let risk_params = RiskParams {
-            collateral_factor: U256::from(WAD_U128),
-            liquidation_threshold: U256::zero(),
-            close_factor: U256::zero(),
+            collateral_factor: U256::from(WAD_U128 * 90 / 100),
+            liquidation_threshold: U256::from(WAD_U128 * 95 / 100),
+            close_factor: U256::from(WAD_U128 / 2),
             liquidation_bonus: U256::zero(),
             reserve_factor: U256::zero(),
             borrow_cap: U256::from(1_000u64),
@@ -1923,16 +2520,19 @@ mod tests {
         };
         let mut oracle = PriceOracle::deploy(&env, oracle_init);
         env.set_caller(oracle_admin);
+        oracle.register_asset(asset);
         oracle.set_price(asset, U256::from(WAD_U128));
 
         let market_init = LendingMarketInitArgs {
-            admin,
-            asset,
-            a_token: placeholder_token,
-            oracle: oracle.address(),
-            registry: env.get_account(9),
-            rate_model,
-            risk_params,
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: placeholder_token,
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model,
+                risk_params,
+            },
         };
         let mut market = LendingMarket::deploy(&env, market_init);
 
@@ -1985,35 +2585,39 @@ mod tests {
             base_rate_per_sec: U256::zero(),
             slope_rate_per_sec: U256::zero(),
         };
-        let risk_params = RiskParams::default();
+        let risk_params = RiskParams {
+            close_factor: U256::from(WAD_U128 / 2),
+            ..Default::default()
+        };
 
         let init_args = LendingMarketInitArgs {
-            admin,
-            asset,
-            a_token: env.get_account(4),
-            oracle,
-            registry,
-            rate_model,
-            risk_params,
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: env.get_account(4),
+                oracle,
+                registry,
+                rate_model,
+                risk_params,
+            },
         };
         let mut market = LendingMarket::deploy(&env, init_args);
 
-        env.set_caller(admin);
         let base_rate_per_sec = U256::from(1u8);
         let slope_rate_per_sec = U256::from(2u8);
         let mut new_params = RiskParams::default();
         new_params.reserve_factor = U256::from(WAD_U128 / 10);
+        new_params.close_factor = U256::from(WAD_U128 / 2);
+
+        env.set_caller(admin);
+        let result = market.try_set_rate_model(base_rate_per_sec, slope_rate_per_sec);
+        assert_eq!(result, Err(Error::Unauthorized.into()));
+        let result = market.try_set_risk_params(new_params.clone());
+        assert_eq!(result, Err(Error::Unauthorized.into()));
 
+        env.set_caller(registry);
         market.set_rate_model(base_rate_per_sec, slope_rate_per_sec);
-        market.set_risk_params(
-            new_params.collateral_factor,
-            new_params.liquidation_threshold,
-            new_params.close_factor,
-            new_params.liquidation_bonus,
-            new_params.reserve_factor,
-            new_params.borrow_cap,
-            new_params.supply_cap,
-        );
+        market.set_risk_params(new_params);
 
         let got_rate = market.get_rate_model();
         let got_params = market.get_risk_params();
@@ -2022,12 +2626,105 @@ mod tests {
         assert_eq!(got_params.reserve_factor, U256::from(WAD_U128 / 10));
     }
 
+    #[test]
+    fn invalid_params_are_rejected() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let asset = env.get_account(1);
+        let oracle = env.get_account(2);
+        let registry = env.get_account(3);
+
+        let rate_model = RateModel {
+            base_rate_per_sec: U256::zero(),
+            slope_rate_per_sec: U256::zero(),
+        };
+        let risk_params = RiskParams {
+            close_factor: U256::from(WAD_U128 / 2),
+            ..Default::default()
+        };
+
+        let init_args = LendingMarketInitArgs {
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: env.get_account(4),
+                oracle,
+                registry,
+                rate_model: rate_model.clone(),
+                risk_params: risk_params.clone(),
+            },
+        };
+        let mut market = LendingMarket::deploy(&env, init_args);
+
+        // Deployment runs the same checks as the registry-driven setters.
+        let result = LendingMarket::try_deploy(
+            &env,
+            LendingMarketInitArgs {
+                config: MarketConfig {
+                    admin,
+                    asset,
+                    a_token: env.get_account(4),
+                    oracle,
+                    registry,
+                    rate_model: rate_model.clone(),
+                    risk_params: RiskParams::default(),
+                },
+            },
+        );
+        assert_eq!(result.err(), Some(Error::CloseFactorOutOfRange.into()));
+        let result = LendingMarket::try_deploy(
+            &env,
+            LendingMarketInitArgs {
+                config: MarketConfig {
+                    admin,
+                    asset,
+                    a_token: env.get_account(4),
+                    oracle,
+                    registry,
+                    rate_model: RateModel {
+                        base_rate_per_sec: U256::zero(),
+                        slope_rate_per_sec: U256::from(WAD_U128),
+                    },
+                    risk_params: risk_params.clone(),
+                },
+            },
+        );
+        assert_eq!(result.err(), Some(Error::BorrowRateTooHigh.into()));
+
+        env.set_caller(registry);
+        let valid = RiskParams {
+            collateral_factor: U256::from(WAD_U128 * 75 / 100),
+            liquidation_threshold: U256::from(WAD_U128 * 80 / 100),
+            close_factor: U256::from(WAD_U128 / 2),
+            liquidation_bonus: U256::from(WAD_U128 * 5 / 100),
+            reserve_factor: U256::zero(),
+            borrow_cap: U256::zero(),
+            supply_cap: U256::zero(),
+        };
+        let result = market.try_set_risk_params(RiskParams {
+            reserve_factor: U256::from(WAD_U128 + 1),
+            ..valid.clone()
+        });
+        assert_eq!(result, Err(Error::ReserveFactorTooHigh.into()));
+
+        let result = market.try_set_risk_params(RiskParams {
+            collateral_factor: valid.liquidation_threshold,
+            liquidation_threshold: valid.collateral_factor,
+            ..valid
+        });
+        assert_eq!(result, Err(Error::CollateralFactorAboveThreshold.into()));
+
+        let result = market.try_set_rate_model(U256::zero(), U256::from(WAD_U128));
+        assert_eq!(result, Err(Error::BorrowRateTooHigh.into()));
+        assert_eq!(market.get_risk_params(), risk_params);
+    }
+
     #[test]
     fn supply_cap_enforced() {
         let env = odra_test::env();
         let admin = env.get_account(0);
         let user = env.get_account(1);
-        let mut underlying = deploy_underlying_token(&env, admin);
+        let mut underlying = deploy_underlying_token(&env);
         let asset = underlying.address();
         let oracle_admin = env.get_account(3);
 
@@ -2035,8 +2732,11 @@ mod tests {
             base_rate_per_sec: U256::zero(),
             slope_rate_per_sec: U256::zero(),
         };
-        let mut risk_params = RiskParams::default();
-        risk_params.supply_cap = U256::from(150u64);
+        let risk_params = RiskParams {
+            close_factor: U256::from(WAD_U128 / 2),
+            supply_cap: U256::from(150u64),
+            ..Default::default()
+        };
 
         let placeholder_token = env.get_account(4);
         let oracle_init = PriceOracleInitArgs {
@@ -2045,16 +2745,19 @@ mod tests {
         };
         let mut oracle = PriceOracle::deploy(&env, oracle_init);
         env.set_caller(oracle_admin);
+        oracle.register_asset(asset);
         oracle.set_price(asset, U256::from(WAD_U128));
 
         let market_init = LendingMarketInitArgs {
-            admin,
-            asset,
-            a_token: placeholder_token,
-            oracle: oracle.address(),
-            registry: env.get_account(9),
-            rate_model,
-            risk_params,
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: placeholder_token,
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model,
+                risk_params,
+            },
         };
         let mut market = LendingMarket::deploy(&env, market_init);
 
@@ -2091,4 +2794,1867 @@ mod tests {
         let result = market.try_deposit(U256::from(60u64));
         assert_eq!(result, Err(Error::BorrowTooLarge.into()));
     }
+
+    #[test]
+    fn valuation_normalised_across_token_decimals() {
+        for decimals in [6u8, 9u8, 18u8] {
+            let env = odra_test::env();
+            let admin = env.get_account(0);
+            let user = env.get_account(1);
+            let mut underlying = deploy_underlying_token_with_decimals(&env, decimals);
+            let asset = underlying.address();
+            let oracle_admin = env.get_account(3);
+            let unit = U256::from(10u8).pow(U256::from(decimals));
+            let wad = U256::from(WAD_U128);
+
+            let rate_model = RateModel {
+                base_rate_per_sec: U256::zero(),
+                slope_rate_per_sec: U256::zero(),
+            };
+            let risk_params = RiskParams {
+                collateral_factor: U256::from(WAD_U128 * 75 / 100),
+                liquidation_threshold: U256::from(WAD_U128 * 80 / 100),
+                close_factor: U256::from(WAD_U128 / 2),
+                liquidation_bonus: U256::from(WAD_U128 * 5 / 100),
+                reserve_factor: U256::zero(),
+                borrow_cap: U256::zero(),
+                supply_cap: U256::zero(),
+            };
+
+            let placeholder_token = env.get_account(4);
+            let oracle_init = PriceOracleInitArgs {
+                admin: oracle_admin,
+                max_stale_millis: 10_000u64,
+            };
+            let mut oracle = PriceOracle::deploy(&env, oracle_init);
+            env.set_caller(oracle_admin);
+            oracle.register_asset(asset);
+            oracle.set_price(asset, wad * U256::from(2u8));
+
+            let market_init = LendingMarketInitArgs {
+                config: MarketConfig {
+                    admin,
+                    asset,
+                    a_token: placeholder_token,
+                    oracle: oracle.address(),
+                    registry: admin,
+                    rate_model,
+                    risk_params,
+                },
+            };
+            let mut market = LendingMarket::deploy(&env, market_init);
+
+            let token_init = ATokenInitArgs {
+                name: "Anchor Token".to_string(),
+                symbol: "aTKN".to_string(),
+                decimals,
+                market: market.address(),
+            };
+            let a_token = AToken::deploy(&env, token_init);
+            let registry = setup_registry(
+                &env,
+                admin,
+                asset,
+                market.address(),
+                a_token.address(),
+                oracle.address(),
+            );
+
+            env.set_caller(admin);
+            market.set_a_token(a_token.address());
+            market.set_registry(registry.address());
+
+            seed_allowance(
+                &env,
+                &mut underlying,
+                admin,
+                user,
+                market.address(),
+                unit * U256::from(1_000u64),
+            );
+            env.set_caller(user);
+            market.deposit(unit * U256::from(100u64));
+            market.borrow(unit * U256::from(75u64));
+
+            assert_eq!(market.get_collateral_value(user), wad * U256::from(200u64));
+            assert_eq!(market.get_borrow_value(user), wad * U256::from(150u64));
+            assert_eq!(
+                market.health_factor(user),
+                wad_div(wad * U256::from(160u64), wad * U256::from(150u64))
+            );
+            let result = market.try_borrow(unit);
+            assert_eq!(result, Err(Error::HealthFactorTooLow.into()));
+        }
+    }
+
+    #[test]
+    fn borrow_rejected_for_unregistered_asset() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let user = env.get_account(1);
+        let mut underlying = deploy_underlying_token(&env);
+        let asset = underlying.address();
+        let oracle_admin = env.get_account(3);
+
+        let rate_model = RateModel {
+            base_rate_per_sec: U256::zero(),
+            slope_rate_per_sec: U256::zero(),
+        };
+        let risk_params = RiskParams {
+            collateral_factor: U256::from(WAD_U128 * 90 / 100),
+            liquidation_threshold: U256::from(WAD_U128 * 95 / 100),
+            close_factor: U256::from(WAD_U128 / 2),
+            liquidation_bonus: U256::zero(),
+            reserve_factor: U256::zero(),
+            borrow_cap: U256::zero(),
+            supply_cap: U256::zero(),
+        };
+
+        let placeholder_token = env.get_account(4);
+        let oracle_init = PriceOracleInitArgs {
+            admin: oracle_admin,
+            max_stale_millis: 10_000u64,
+        };
+        let mut oracle = PriceOracle::deploy(&env, oracle_init);
+        env.set_caller(oracle_admin);
+        oracle.set_price(asset, U256::from(WAD_U128));
+
+        let market_init = LendingMarketInitArgs {
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: placeholder_token,
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model,
+                risk_params,
+            },
+        };
+        let mut market = LendingMarket::deploy(&env, market_init);
+
+        let token_init = ATokenInitArgs {
+            name: "Anchor Token".to_string(),
+            symbol: "aTKN".to_string(),
+            decimals: 9,
+            market: market.address(),
+        };
+        let a_token = AToken::deploy(&env, token_init);
+        let registry = setup_registry(
+            &env,
+            admin,
+            asset,
+            market.address(),
+            a_token.address(),
+            oracle.address(),
+        );
+
+        env.set_caller(admin);
+        market.set_a_token(a_token.address());
+        market.set_registry(registry.address());
+
+        seed_allowance(
+            &env,
+            &mut underlying,
+            admin,
+            user,
+            market.address(),
+            U256::from(TEST_MINT_AMOUNT),
+        );
+        env.set_caller(user);
+        market.deposit(U256::from(100u64));
+        let result = market.try_borrow(U256::from(10u64));
+        assert_eq!(result, Err(Error::AssetNotRegistered.into()));
+    }
+
+    #[test]
+    fn liquidation_waits_for_grace_period_after_oracle_recovery() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let user = env.get_account(1);
+        let liquidator = env.get_account(2);
+        let mut underlying = deploy_underlying_token(&env);
+        let asset = underlying.address();
+        let oracle_admin = env.get_account(4);
+
+        let rate_model = RateModel {
+            base_rate_per_sec: U256::zero(),
+            slope_rate_per_sec: U256::zero(),
+        };
+        let risk_params = RiskParams {
+            collateral_factor: U256::from(WAD_U128 * 90 / 100),
+            liquidation_threshold: U256::from(WAD_U128 * 95 / 100),
+            close_factor: U256::from(WAD_U128 / 2),
+            liquidation_bonus: U256::from(WAD_U128 * 5 / 100),
+            reserve_factor: U256::zero(),
+            borrow_cap: U256::from(10_000u64),
+            supply_cap: U256::zero(),
+        };
+
+        let placeholder_token = env.get_account(5);
+        let oracle_init = PriceOracleInitArgs {
+            admin: oracle_admin,
+            max_stale_millis: 10_000u64,
+        };
+        let mut oracle = PriceOracle::deploy(&env, oracle_init);
+        env.set_caller(oracle_admin);
+        oracle.register_asset(asset);
+        oracle.set_price(asset, U256::from(WAD_U128));
+
+        let market_init = LendingMarketInitArgs {
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: placeholder_token,
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model,
+                risk_params,
+            },
+        };
+        let mut market = LendingMarket::deploy(&env, market_init);
+
+        let token_init = ATokenInitArgs {
+            name: "Anchor Token".to_string(),
+            symbol: "aTKN".to_string(),
+            decimals: 9,
+            market: market.address(),
+        };
+        let a_token = AToken::deploy(&env, token_init);
+        let mut registry = setup_registry(
+            &env,
+            admin,
+            asset,
+            market.address(),
+            a_token.address(),
+            oracle.address(),
+        );
+
+        env.set_caller(admin);
+        market.set_liquidation_grace_period(5_000u64);
+        market.set_a_token(a_token.address());
+        market.set_registry(registry.address());
+
+        seed_allowance(
+            &env,
+            &mut underlying,
+            admin,
+            user,
+            market.address(),
+            U256::from(TEST_MINT_AMOUNT),
+        );
+        env.set_caller(user);
+        market.deposit(U256::from(100u64));
+        market.borrow(U256::from(90u64));
+        apply_risk_params(
+            &env,
+            &mut registry,
+            &mut oracle,
+            admin,
+            oracle_admin,
+            asset,
+            RiskParams {
+                collateral_factor: U256::from(WAD_U128 * 75 / 100),
+                liquidation_threshold: U256::from(WAD_U128 * 80 / 100),
+                close_factor: U256::from(WAD_U128 / 2),
+                liquidation_bonus: U256::from(WAD_U128 * 5 / 100),
+                ..Default::default()
+            },
+        );
+
+        env.advance_block_time(11_000u64);
+        env.set_caller(oracle_admin);
+        oracle.set_price(asset, U256::from(WAD_U128));
+
+        env.set_caller(liquidator);
+        let result = market.try_liquidate(user, U256::from(50u64));
+        assert_eq!(result, Err(Error::LiquidationGracePeriod.into()));
+
+        env.set_caller(user);
+        market.repay(U256::from(5u64));
+        assert_eq!(market.get_borrow_balance(user), U256::from(85u64));
+
+        env.advance_block_time(5_000u64);
+        seed_allowance(
+            &env,
+            &mut underlying,
+            admin,
+            liquidator,
+            market.address(),
+            U256::from(TEST_MINT_AMOUNT),
+        );
+        market.liquidate(user, U256::from(50u64));
+        assert!(market.get_borrow_balance(user) < U256::from(85u64));
+    }
+
+    #[test]
+    fn frozen_market_blocks_new_positions_but_allows_exit() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let user = env.get_account(1);
+        let mut underlying = deploy_underlying_token(&env);
+        let asset = underlying.address();
+        let oracle_admin = env.get_account(4);
+
+        let rate_model = RateModel {
+            base_rate_per_sec: U256::zero(),
+            slope_rate_per_sec: U256::zero(),
+        };
+        let risk_params = RiskParams {
+            collateral_factor: U256::from(WAD_U128 * 75 / 100),
+            liquidation_threshold: U256::from(WAD_U128 * 80 / 100),
+            close_factor: U256::from(WAD_U128 / 2),
+            liquidation_bonus: U256::from(WAD_U128 * 5 / 100),
+            reserve_factor: U256::zero(),
+            borrow_cap: U256::zero(),
+            supply_cap: U256::zero(),
+        };
+
+        let placeholder_token = env.get_account(5);
+        let oracle_init = PriceOracleInitArgs {
+            admin: oracle_admin,
+            max_stale_millis: 10_000u64,
+        };
+        let mut oracle = PriceOracle::deploy(&env, oracle_init);
+        env.set_caller(oracle_admin);
+        oracle.register_asset(asset);
+        oracle.set_price(asset, U256::from(WAD_U128));
+
+        let market_init = LendingMarketInitArgs {
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: placeholder_token,
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model,
+                risk_params,
+            },
+        };
+        let mut market = LendingMarket::deploy(&env, market_init);
+
+        let token_init = ATokenInitArgs {
+            name: "Anchor Token".to_string(),
+            symbol: "aTKN".to_string(),
+            decimals: 9,
+            market: market.address(),
+        };
+        let a_token = AToken::deploy(&env, token_init);
+        let mut registry = setup_registry(
+            &env,
+            admin,
+            asset,
+            market.address(),
+            a_token.address(),
+            oracle.address(),
+        );
+
+        env.set_caller(admin);
+        market.set_a_token(a_token.address());
+        market.set_registry(registry.address());
+
+        seed_allowance(
+            &env,
+            &mut underlying,
+            admin,
+            user,
+            market.address(),
+            U256::from(TEST_MINT_AMOUNT),
+        );
+        env.set_caller(user);
+        market.deposit(U256::from(1_000u64));
+        market.borrow(U256::from(100u64));
+
+        env.set_caller(admin);
+        registry.freeze_market(asset);
+
+        env.set_caller(user);
+        let result = market.try_deposit(U256::from(10u64));
+        assert_eq!(result, Err(Error::MarketNotActive.into()));
+        let result = market.try_borrow(U256::from(10u64));
+        assert_eq!(result, Err(Error::MarketNotActive.into()));
+
+        market.repay(U256::from(100u64));
+        market.withdraw(U256::from(1_000u64));
+        assert_eq!(market.get_borrow_balance(user), U256::zero());
+        assert_eq!(a_token.balance_of(&user), U256::zero());
+    }
+
+    #[test]
+    fn deprecated_market_liquidates_any_debt_and_closes_once_repaid() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let user = env.get_account(1);
+        let liquidator = env.get_account(2);
+        let mut underlying = deploy_underlying_token(&env);
+        let asset = underlying.address();
+        let oracle_admin = env.get_account(4);
+
+        let rate_model = RateModel {
+            base_rate_per_sec: U256::zero(),
+            slope_rate_per_sec: U256::zero(),
+        };
+        let risk_params = RiskParams {
+            collateral_factor: U256::from(WAD_U128 * 75 / 100),
+            liquidation_threshold: U256::from(WAD_U128 * 80 / 100),
+            close_factor: U256::from(WAD_U128 / 2),
+            liquidation_bonus: U256::from(WAD_U128 * 5 / 100),
+            reserve_factor: U256::zero(),
+            borrow_cap: U256::zero(),
+            supply_cap: U256::zero(),
+        };
+
+        let placeholder_token = env.get_account(5);
+        let oracle_init = PriceOracleInitArgs {
+            admin: oracle_admin,
+            max_stale_millis: 10_000u64,
+        };
+        let mut oracle = PriceOracle::deploy(&env, oracle_init);
+        env.set_caller(oracle_admin);
+        oracle.register_asset(asset);
+        oracle.set_price(asset, U256::from(WAD_U128));
+
+        let market_init = LendingMarketInitArgs {
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: placeholder_token,
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model,
+                risk_params,
+            },
+        };
+        let mut market = LendingMarket::deploy(&env, market_init);
+
+        let token_init = ATokenInitArgs {
+            name: "Anchor Token".to_string(),
+            symbol: "aTKN".to_string(),
+            decimals: 9,
+            market: market.address(),
+        };
+        let a_token = AToken::deploy(&env, token_init);
+        let mut registry = setup_registry(
+            &env,
+            admin,
+            asset,
+            market.address(),
+            a_token.address(),
+            oracle.address(),
+        );
+
+        env.set_caller(admin);
+        market.set_a_token(a_token.address());
+        market.set_registry(registry.address());
+
+        seed_allowance(
+            &env,
+            &mut underlying,
+            admin,
+            user,
+            market.address(),
+            U256::from(TEST_MINT_AMOUNT),
+        );
+        env.set_caller(user);
+        market.deposit(U256::from(10_000u64));
+        market.borrow(U256::from(1_000u64));
+        assert!(!market.is_liquidatable(user));
+
+        env.set_caller(admin);
+        registry.deprecate_market(asset);
+        assert_eq!(market.get_borrow_rate_per_sec(), U256::zero());
+
+        // Deprecation does not make the position unhealthy elsewhere.
+        assert!(!market.is_liquidatable(user));
+        env.set_caller(user);
+        market.withdraw(U256::from(100u64));
+
+        // Bonus is halved to 2.5% while deprecated: 400 repaid seizes 410.
+        seed_allowance(
+            &env,
+            &mut underlying,
+            admin,
+            liquidator,
+            market.address(),
+            U256::from(TEST_MINT_AMOUNT),
+        );
+        market.liquidate(user, U256::from(400u64));
+        assert_eq!(market.get_borrow_balance(user), U256::from(600u64));
+        assert_eq!(a_token.balance_of(&liquidator), U256::from(410u64));
+        assert_eq!(
+            underlying.balance_of(&liquidator),
+            U256::from(TEST_MINT_AMOUNT - 400)
+        );
+        let result = market.try_liquidate(liquidator, U256::from(1u64));
+        assert_eq!(result, Err(Error::NotLiquidatable.into()));
+
+        env.set_caller(admin);
+        let result = registry.try_close_market(asset);
+        assert_eq!(result, Err(Error::MarketHasOpenBorrows.into()));
+
+        env.advance_block_time(DEPRECATION_RAMP_MILLIS / 2);
+        assert_eq!(
+            market.get_borrow_rate_per_sec(),
+            U256::from(MAX_BORROW_RATE_PER_SEC / 2)
+        );
+        env.advance_block_time(DEPRECATION_RAMP_MILLIS);
+        assert_eq!(
+            market.get_borrow_rate_per_sec(),
+            U256::from(MAX_BORROW_RATE_PER_SEC)
+        );
+
+        // Interest is charged to the borrower, so repaying in full closes out.
+        env.set_caller(oracle_admin);
+        oracle.set_price(asset, U256::from(WAD_U128));
+        env.set_caller(user);
+        market.accrue_interest();
+        let debt = market.get_borrow_balance(user);
+        assert!(debt > U256::from(600u64));
+        seed_allowance(&env, &mut underlying, admin, user, market.address(), debt);
+        market.repay(debt + U256::from(1u64));
+        assert_eq!(market.get_borrow_balance(user), U256::zero());
+        assert_eq!(market.get_total_borrows(), U256::zero());
+        env.set_caller(admin);
+        registry.close_market(asset);
+    }
+
+    #[test]
+    fn global_pause_applies_on_top_of_market_flags() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let user = env.get_account(1);
+        let mut underlying = deploy_underlying_token(&env);
+        let asset = underlying.address();
+        let oracle_admin = env.get_account(4);
+
+        let rate_model = RateModel {
+            base_rate_per_sec: U256::zero(),
+            slope_rate_per_sec: U256::zero(),
+        };
+        let risk_params = RiskParams {
+            collateral_factor: U256::from(WAD_U128 * 75 / 100),
+            liquidation_threshold: U256::from(WAD_U128 * 80 / 100),
+            close_factor: U256::from(WAD_U128 / 2),
+            liquidation_bonus: U256::from(WAD_U128 * 5 / 100),
+            reserve_factor: U256::zero(),
+            borrow_cap: U256::zero(),
+            supply_cap: U256::zero(),
+        };
+
+        let placeholder_token = env.get_account(5);
+        let oracle_init = PriceOracleInitArgs {
+            admin: oracle_admin,
+            max_stale_millis: 10_000u64,
+        };
+        let mut oracle = PriceOracle::deploy(&env, oracle_init);
+        env.set_caller(oracle_admin);
+        oracle.register_asset(asset);
+        oracle.set_price(asset, U256::from(WAD_U128));
+
+        let market_init = LendingMarketInitArgs {
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: placeholder_token,
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model,
+                risk_params,
+            },
+        };
+        let mut market = LendingMarket::deploy(&env, market_init);
+
+        let token_init = ATokenInitArgs {
+            name: "Anchor Token".to_string(),
+            symbol: "aTKN".to_string(),
+            decimals: 9,
+            market: market.address(),
+        };
+        let a_token = AToken::deploy(&env, token_init);
+        let mut registry = setup_registry(
+            &env,
+            admin,
+            asset,
+            market.address(),
+            a_token.address(),
+            oracle.address(),
+        );
+
+        env.set_caller(admin);
+        market.set_a_token(a_token.address());
+        market.set_registry(registry.address());
+
+        seed_allowance(
+            &env,
+            &mut underlying,
+            admin,
+            user,
+            market.address(),
+            U256::from(TEST_MINT_AMOUNT),
+        );
+        env.set_caller(user);
+        market.deposit(U256::from(1_000u64));
+
+        env.set_caller(admin);
+        let flags = PauseFlags {
+            supply_paused: true,
+            ..unpaused()
+        };
+        registry.set_global_pause(flags, 0);
+        assert!(!registry.get_pause_flags(asset).supply_paused);
+
+        env.set_caller(user);
+        let result = market.try_deposit(U256::from(10u64));
+        assert_eq!(result, Err(Error::Paused.into()));
+        market.borrow(U256::from(100u64));
+
+        env.set_caller(admin);
+        registry.set_global_pause(unpaused(), 0);
+        env.set_caller(user);
+        market.deposit(U256::from(10u64));
+        assert_eq!(a_token.balance_of(&user), U256::from(1_010u64));
+    }
+
+    #[test]
+    fn only_registry_can_set_liquidation_grace_period() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let asset = env.get_account(1);
+        let oracle = env.get_account(2);
+        let registry = env.get_account(3);
+
+        let init_args = LendingMarketInitArgs {
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: env.get_account(4),
+                oracle,
+                registry,
+                rate_model: RateModel::default(),
+                risk_params: RiskParams {
+                    close_factor: U256::from(WAD_U128 / 2),
+                    ..Default::default()
+                },
+            },
+        };
+        let mut market = LendingMarket::deploy(&env, init_args);
+
+        for caller in [admin, env.get_account(5)] {
+            env.set_caller(caller);
+            let result = market.try_set_liquidation_grace_period(1_000u64);
+            assert_eq!(result, Err(Error::Unauthorized.into()));
+        }
+
+        env.set_caller(registry);
+        market.set_liquidation_grace_period(1_000u64);
+        assert_eq!(market.get_liquidation_grace_period(), 1_000u64);
+    }
+
+    #[test]
+    fn admin_cannot_repoint_registry_to_skip_timelock() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let asset = env.get_account(1);
+        let oracle = env.get_account(2);
+        let registry = env.get_account(3);
+
+        let init_args = LendingMarketInitArgs {
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: env.get_account(4),
+                oracle,
+                registry,
+                rate_model: RateModel::default(),
+                risk_params: RiskParams {
+                    close_factor: U256::from(WAD_U128 / 2),
+                    ..Default::default()
+                },
+            },
+        };
+        let mut market = LendingMarket::deploy(&env, init_args);
+        let risk_params = RiskParams {
+            collateral_factor: U256::from(WAD_U128 * 9 / 10),
+            liquidation_threshold: U256::from(WAD_U128 * 95 / 100),
+            close_factor: U256::from(WAD_U128 / 2),
+            ..Default::default()
+        };
+
+        env.set_caller(admin);
+        let result = market.try_set_registry(admin);
+        assert_eq!(result, Err(Error::Unauthorized.into()));
+        let result = market.try_set_risk_params(risk_params);
+        assert_eq!(result, Err(Error::Unauthorized.into()));
+        assert_eq!(market.get_risk_params().collateral_factor, U256::zero());
+
+        env.set_caller(registry);
+        market.set_registry(env.get_account(5));
+        assert_eq!(market.get_registry(), env.get_account(5));
+    }
+
+    #[test]
+    fn admin_handover_moves_default_admin_only() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let multisig = env.get_account(7);
+        let init_args = LendingMarketInitArgs {
+            config: MarketConfig {
+                admin,
+                asset: env.get_account(1),
+                a_token: env.get_account(2),
+                oracle: env.get_account(3),
+                registry: env.get_account(4),
+                rate_model: RateModel {
+                    base_rate_per_sec: U256::zero(),
+                    slope_rate_per_sec: U256::zero(),
+                },
+                risk_params: RiskParams {
+                    close_factor: U256::from(WAD_U128 / 2),
+                    ..Default::default()
+                },
+            },
+        };
+        let mut market = LendingMarket::deploy(&env, init_args);
+
+        env.set_caller(admin);
+        market.propose_admin(multisig);
+        assert_eq!(market.get_pending_admin(), Some(multisig));
+        env.set_caller(multisig);
+        market.accept_admin();
+        assert_eq!(market.get_admin(), multisig);
+        assert_eq!(market.get_pending_admin(), None);
+
+        // Operational roles stay put until the new admin moves them.
+        assert!(market.has_market_admin(admin));
+        market.grant_market_admin(multisig);
+        market.revoke_market_admin(admin);
+        assert!(!market.has_market_admin(admin));
+
+        env.set_caller(admin);
+        assert!(market.try_grant_market_admin(admin).is_err());
+        assert!(market.try_propose_admin(admin).is_err());
+    }
+
+
+    #[test]
+    fn upgrade_keeps_live_positions_untouched() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let borrower = env.get_account(1);
+        let supplier = env.get_account(2);
+        let mut underlying = deploy_underlying_token(&env);
+        let asset = underlying.address();
+        let oracle_admin = env.get_account(4);
+
+        let oracle_init = PriceOracleInitArgs {
+            admin: oracle_admin,
+            max_stale_millis: 10_000_000u64,
+        };
+        let mut oracle = PriceOracle::deploy(&env, oracle_init);
+        env.set_caller(oracle_admin);
+        oracle.register_asset(asset);
+        oracle.set_price(asset, U256::from(WAD_U128));
+
+        let market_init = LendingMarketInitArgs {
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: env.get_account(5),
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model: RateModel {
+                    base_rate_per_sec: U256::from(MAX_BASE_RATE_PER_SEC),
+                    slope_rate_per_sec: U256::zero(),
+                },
+                risk_params: RiskParams {
+                    collateral_factor: U256::from(WAD_U128 * 75 / 100),
+                    liquidation_threshold: U256::from(WAD_U128 * 80 / 100),
+                    close_factor: U256::from(WAD_U128 / 2),
+                    reserve_factor: U256::from(WAD_U128 / 10),
+                    ..Default::default()
+                },
+            },
+        };
+        let mut market = LendingMarket::deploy(&env, market_init);
+        assert_eq!(market.get_version(), MARKET_VERSION);
+        let token_init = ATokenInitArgs {
+            name: "Anchor Token".to_string(),
+            symbol: "aTKN".to_string(),
+            decimals: 9,
+            market: market.address(),
+        };
+        let a_token = AToken::deploy(&env, token_init);
+        let registry = setup_registry(
+            &env,
+            admin,
+            asset,
+            market.address(),
+            a_token.address(),
+            oracle.address(),
+        );
+        env.set_caller(admin);
+        market.set_a_token(a_token.address());
+        market.set_registry(registry.address());
+
+        for account in [borrower, supplier] {
+            seed_allowance(
+                &env,
+                &mut underlying,
+                admin,
+                account,
+                market.address(),
+                U256::from(TEST_MINT_AMOUNT),
+            );
+        }
+        env.set_caller(supplier);
+        market.deposit(U256::from(5_000u64));
+        env.set_caller(borrower);
+        market.deposit(U256::from(1_000u64));
+        market.borrow(U256::from(500u64));
+        env.advance_block_time(60_000);
+        market.accrue_interest();
+
+        let snapshot = |market: &LendingMarketHostRef| {
+            (
+                market.get_cash(),
+                market.get_total_borrows(),
+                market.get_total_reserves(),
+                market.get_supply_index(),
+                market.get_borrow_index(),
+                market.get_borrow_balance(borrower),
+                market.get_a_token(),
+                a_token.balance_of(&borrower),
+                a_token.balance_of(&supplier),
+            )
+        };
+        let before = snapshot(&market);
+
+        env.set_caller(admin);
+        let upgraded = LendingMarket::try_upgrade(&env, market.address(), NoArgs);
+        let mut market = upgraded.unwrap();
+        assert!(env.emitted_event(
+            &market,
+            MarketUpgraded {
+                from_version: MARKET_VERSION,
+                to_version: MARKET_VERSION,
+            }
+        ));
+        assert_eq!(market.get_version(), MARKET_VERSION);
+        assert_eq!(snapshot(&market), before);
+
+        env.set_caller(borrower);
+        market.repay(U256::from(100u64));
+        assert!(market.get_borrow_balance(borrower) < before.5);
+    }
+
+
+    #[test]
+    fn upgrade_from_v1_layout_keeps_state() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let borrower = env.get_account(1);
+        let other_borrower = env.get_account(2);
+        let mut underlying = deploy_underlying_token(&env);
+        let asset = underlying.address();
+        let oracle = env.get_account(4);
+        let registry = env.get_account(9);
+        let rate_model = RateModel {
+            base_rate_per_sec: U256::from(MAX_BASE_RATE_PER_SEC),
+            slope_rate_per_sec: U256::zero(),
+        };
+        let risk_params = RiskParams {
+            collateral_factor: U256::from(WAD_U128 * 75 / 100),
+            liquidation_threshold: U256::from(WAD_U128 * 80 / 100),
+            close_factor: U256::from(WAD_U128 / 2),
+            reserve_factor: U256::from(WAD_U128 / 10),
+            ..Default::default()
+        };
+        let v1_init = LendingMarketV1InitArgs {
+            admin,
+            asset,
+            a_token: admin,
+            oracle,
+            registry,
+        };
+        let mut v1 = LendingMarketV1::deploy(&env, v1_init);
+        v1.set_params(rate_model.clone(), risk_params.clone(), 60_000);
+        let token_init = ATokenInitArgs {
+            name: "Anchor Token".to_string(),
+            symbol: "aTKN".to_string(),
+            decimals: 9,
+            market: v1.address(),
+        };
+        let a_token = AToken::deploy(&env, token_init);
+        v1.set_a_token(a_token.address());
+        v1.seed_position(borrower, U256::from(5_000u64), U256::from(500u64));
+        v1.seed_position(other_borrower, U256::from(3_000u64), U256::from(1_500u64));
+        // 10% interest on 2_000 of principal: the total and reserves grow,
+        // the balances stay at principal.
+        v1.seed_interest(U256::from(WAD_U128 * 11 / 10));
+        // Cash says 6_000, but a v1 liquidation never collected 100 of it.
+        env.set_caller(admin);
+        underlying.mint(v1.address(), U256::from(5_900u64));
+
+        let mut market = LendingMarket::try_upgrade(&env, v1.address(), NoArgs).unwrap();
+        assert!(env.emitted_event(
+            &market,
+            MarketUpgraded {
+                from_version: 1,
+                to_version: MARKET_VERSION,
+            }
+        ));
+        assert_eq!(market.get_version(), 6);
+        assert_eq!(market.get_admin(), admin);
+        assert!(market.has_market_admin(admin));
+        assert_eq!(market.get_asset(), asset);
+        assert_eq!(market.get_a_token(), a_token.address());
+        assert_eq!(market.get_oracle(), oracle);
+        assert_eq!(market.get_registry(), registry);
+        assert_eq!(market.get_rate_model(), rate_model);
+        assert_eq!(market.get_risk_params(), risk_params);
+        assert_eq!(market.get_supply_index(), U256::from(WAD_U128));
+        assert_eq!(market.get_liquidation_grace_period(), 60_000);
+        assert_eq!(a_token.balance_of(&borrower), U256::from(5_000u64));
+
+        // The uncharged 200 of interest is spread by principal, so borrowers
+        // now owe it and the 20 of reserves taken from it stay backed.
+        assert_eq!(market.get_borrow_index(), U256::from(WAD_U128 * 11 / 10));
+        assert_eq!(market.get_borrow_balance(borrower), U256::from(550u64));
+        assert_eq!(market.get_borrow_balance(other_borrower), U256::from(1_650u64));
+        assert_eq!(market.get_total_borrows(), U256::from(2_200u64));
+        assert_eq!(market.get_total_reserves(), U256::from(20u64));
+        // Cash drops to what the market holds.
+        assert_eq!(market.get_cash(), U256::from(5_900u64));
+
+        // Fields added after v1 start out empty and are writable.
+        assert_eq!(market.get_debt_token(), None);
+        assert_eq!(market.get_rewards_controller(), None);
+        assert_eq!(market.get_user_emode(borrower), 0);
+        assert_eq!(market.get_borrow_allowance(borrower, admin), U256::zero());
+        env.set_caller(borrower);
+        market.approve_delegation(admin, U256::from(10u64));
+        assert_eq!(market.get_borrow_allowance(borrower, admin), U256::from(10u64));
+    }
+
+    #[test]
+    fn upgrade_drops_v1_interest_left_without_borrowers() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let borrower = env.get_account(1);
+        let mut underlying = deploy_underlying_token(&env);
+        let v1_init = LendingMarketV1InitArgs {
+            admin,
+            asset: underlying.address(),
+            a_token: admin,
+            oracle: env.get_account(4),
+            registry: env.get_account(9),
+        };
+        let mut v1 = LendingMarketV1::deploy(&env, v1_init);
+        let risk_params = RiskParams {
+            close_factor: U256::from(WAD_U128 / 2),
+            reserve_factor: U256::from(WAD_U128 / 10),
+            ..Default::default()
+        };
+        v1.set_params(RateModel::default(), risk_params, 0);
+        let token_init = ATokenInitArgs {
+            name: "Anchor Token".to_string(),
+            symbol: "aTKN".to_string(),
+            decimals: 9,
+            market: v1.address(),
+        };
+        let a_token = AToken::deploy(&env, token_init);
+        v1.set_a_token(a_token.address());
+        v1.seed_position(borrower, U256::from(1_000u64), U256::from(500u64));
+        v1.seed_interest(U256::from(WAD_U128 * 11 / 10));
+        // v1 repaid principal only, leaving 50 of interest on the total.
+        v1.seed_repay(borrower, U256::from(500u64));
+        env.set_caller(admin);
+        underlying.mint(v1.address(), U256::from(1_000u64));
+
+        let market = LendingMarket::try_upgrade(&env, v1.address(), NoArgs).unwrap();
+        assert_eq!(market.get_borrow_index(), U256::from(WAD_U128));
+        assert_eq!(market.get_total_borrows(), U256::zero());
+        // The 5 of reserves booked from that interest go with it.
+        assert_eq!(market.get_total_reserves(), U256::zero());
+        assert_eq!(market.get_cash(), U256::from(1_000u64));
+    }
+
+    #[test]
+    fn a_token_transfers_cannot_strip_collateral_from_debt() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let borrower = env.get_account(1);
+        let accomplice = env.get_account(2);
+        let saver = env.get_account(3);
+        let mut underlying = deploy_underlying_token(&env);
+        let asset = underlying.address();
+        let oracle_admin = env.get_account(4);
+
+        let oracle_init = PriceOracleInitArgs {
+            admin: oracle_admin,
+            max_stale_millis: 10_000u64,
+        };
+        let mut oracle = PriceOracle::deploy(&env, oracle_init);
+        env.set_caller(oracle_admin);
+        oracle.register_asset(asset);
+        oracle.set_price(asset, U256::from(WAD_U128));
+
+        let market_init = LendingMarketInitArgs {
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: env.get_account(5),
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model: RateModel::default(),
+                risk_params: RiskParams {
+                    collateral_factor: U256::from(WAD_U128 * 75 / 100),
+                    liquidation_threshold: U256::from(WAD_U128 * 80 / 100),
+                    close_factor: U256::from(WAD_U128 / 2),
+                    ..Default::default()
+                },
+            },
+        };
+        let mut market = LendingMarket::deploy(&env, market_init);
+        let token_init = ATokenInitArgs {
+            name: "Anchor Token".to_string(),
+            symbol: "aTKN".to_string(),
+            decimals: 9,
+            market: market.address(),
+        };
+        let mut a_token = AToken::deploy(&env, token_init);
+        let registry = setup_registry(
+            &env,
+            admin,
+            asset,
+            market.address(),
+            a_token.address(),
+            oracle.address(),
+        );
+        env.set_caller(admin);
+        market.set_a_token(a_token.address());
+        market.set_registry(registry.address());
+
+        for account in [borrower, saver] {
+            seed_allowance(
+                &env,
+                &mut underlying,
+                admin,
+                account,
+                market.address(),
+                U256::from(TEST_MINT_AMOUNT),
+            );
+        }
+        env.set_caller(saver);
+        market.deposit(U256::from(1_000u64));
+        env.set_caller(borrower);
+        market.deposit(U256::from(1_000u64));
+        market.borrow(U256::from(700u64));
+
+        // Borrow then walk away with the collateral.
+        let result = a_token.try_transfer(&accomplice, &U256::from(1_000u64));
+        assert_eq!(result, Err(Error::HealthFactorTooLow.into()));
+        a_token.approve(&accomplice, &U256::from(1_000u64));
+        env.set_caller(accomplice);
+        let result = a_token.try_transfer_from(&borrower, &accomplice, &U256::from(1_000u64));
+        assert_eq!(result, Err(Error::HealthFactorTooLow.into()));
+
+        // 875 * 0.8 = 700 keeps the position exactly at the threshold.
+        a_token.transfer_from(&borrower, &accomplice, &U256::from(125u64));
+        assert_eq!(a_token.allowance(&borrower, &accomplice), U256::from(875u64));
+        env.set_caller(borrower);
+        let result = a_token.try_transfer(&accomplice, &U256::from(1u64));
+        assert_eq!(result, Err(Error::HealthFactorTooLow.into()));
+        assert!(!market.is_liquidatable(borrower));
+
+        // Holders without debt move aTokens freely; the hook is aToken-only.
+        let before_transfer = env.block_time();
+        env.advance_block_time(1_000);
+        env.set_caller(saver);
+        a_token.transfer(&accomplice, &U256::from(1_000u64));
+        assert_eq!(a_token.balance_of(&accomplice), U256::from(1_125u64));
+        assert_eq!(
+            a_token.balance_of_at(saver, before_transfer),
+            U256::from(1_000u64)
+        );
+        assert_eq!(
+            a_token.balance_of_at(accomplice, before_transfer),
+            U256::from(125u64)
+        );
+        assert_eq!(a_token.balance_of_at(saver, env.block_time()), U256::zero());
+        let result = market.try_before_a_token_transfer(borrower, accomplice, U256::zero());
+        assert_eq!(result, Err(Error::Unauthorized.into()));
+    }
+
+    #[test]
+    fn debt_token_follows_borrows_with_interest() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let borrower = env.get_account(1);
+        let saver = env.get_account(2);
+        let mut underlying = deploy_underlying_token(&env);
+        let asset = underlying.address();
+        let oracle_admin = env.get_account(4);
+
+        let oracle_init = PriceOracleInitArgs {
+            admin: oracle_admin,
+            max_stale_millis: 1_000_000u64,
+        };
+        let mut oracle = PriceOracle::deploy(&env, oracle_init);
+        env.set_caller(oracle_admin);
+        oracle.register_asset(asset);
+        oracle.set_price(asset, U256::from(WAD_U128));
+
+        let market_init = LendingMarketInitArgs {
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: env.get_account(5),
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model: RateModel {
+                    base_rate_per_sec: U256::zero(),
+                    slope_rate_per_sec: U256::from(MAX_BORROW_RATE_PER_SEC),
+                },
+                risk_params: RiskParams {
+                    collateral_factor: U256::from(WAD_U128 * 75 / 100),
+                    liquidation_threshold: U256::from(WAD_U128 * 80 / 100),
+                    close_factor: U256::from(WAD_U128 / 2),
+                    ..Default::default()
+                },
+            },
+        };
+        let mut market = LendingMarket::deploy(&env, market_init);
+        let token_init = ATokenInitArgs {
+            name: "Anchor Token".to_string(),
+            symbol: "aTKN".to_string(),
+            decimals: 9,
+            market: market.address(),
+        };
+        let a_token = AToken::deploy(&env, token_init);
+        let debt_init = VariableDebtTokenInitArgs {
+            name: "Anchor Variable Debt".to_string(),
+            symbol: "vdTKN".to_string(),
+            decimals: 9,
+            market: market.address(),
+        };
+        let debt_token = VariableDebtToken::deploy(&env, debt_init);
+        let mut registry = setup_registry(
+            &env,
+            admin,
+            asset,
+            market.address(),
+            a_token.address(),
+            oracle.address(),
+        );
+        env.set_caller(admin);
+        market.set_a_token(a_token.address());
+        market.set_registry(registry.address());
+        let result = market.try_set_debt_token(debt_token.address());
+        assert_eq!(result, Err(Error::Unauthorized.into()));
+
+        // Only a debt token deployed for this market can be linked.
+        let foreign_init = VariableDebtTokenInitArgs {
+            name: "Foreign Debt".to_string(),
+            symbol: "fdTKN".to_string(),
+            decimals: 9,
+            market: env.get_account(6),
+        };
+        let foreign = VariableDebtToken::deploy(&env, foreign_init);
+        let result = registry.try_link_debt_token(asset, foreign.address());
+        assert_eq!(result, Err(Error::InvalidParam.into()));
+        registry.link_debt_token(asset, debt_token.address());
+        assert_eq!(market.get_debt_token(), Some(debt_token.address()));
+        assert_eq!(
+            registry.get_market_addresses(asset).debt_token,
+            Some(debt_token.address())
+        );
+
+        for account in [borrower, saver] {
+            seed_allowance(
+                &env,
+                &mut underlying,
+                admin,
+                account,
+                market.address(),
+                U256::from(TEST_MINT_AMOUNT),
+            );
+        }
+        env.set_caller(saver);
+        market.deposit(U256::from(1_000u64));
+        env.set_caller(borrower);
+        market.deposit(U256::from(1_000u64));
+        market.borrow(U256::from(500u64));
+        assert_eq!(debt_token.balance_of(&borrower), U256::from(500u64));
+        assert_eq!(debt_token.total_supply(), U256::from(500u64));
+
+        env.advance_block_time(100_000_000);
+        env.set_caller(oracle_admin);
+        oracle.set_price(asset, U256::from(WAD_U128));
+        env.set_caller(borrower);
+        market.accrue_interest();
+        let with_interest = debt_token.balance_of(&borrower);
+        assert!(with_interest > U256::from(500u64));
+        assert_eq!(with_interest, market.get_borrow_balance(borrower));
+        assert_eq!(debt_token.total_supply(), market.get_total_borrows());
+
+        market.repay(U256::from(100u64));
+        let after_partial = debt_token.balance_of(&borrower);
+        assert_eq!(after_partial, with_interest - U256::from(100u64));
+
+        // The token shows only what the market collects, so a full repay clears it.
+        market.repay(U256::from(1_000u64));
+        assert_eq!(market.get_borrow_balance(borrower), U256::zero());
+        assert_eq!(debt_token.balance_of(&borrower), U256::zero());
+        assert_eq!(debt_token.total_supply(), U256::zero());
+    }
+
+    #[test]
+    fn delegatee_borrows_against_delegator_collateral() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let delegator = env.get_account(1);
+        let delegatee = env.get_account(2);
+        let stranger = env.get_account(3);
+        let mut underlying = deploy_underlying_token(&env);
+        let asset = underlying.address();
+        let oracle_admin = env.get_account(4);
+
+        let oracle_init = PriceOracleInitArgs {
+            admin: oracle_admin,
+            max_stale_millis: 10_000u64,
+        };
+        let mut oracle = PriceOracle::deploy(&env, oracle_init);
+        env.set_caller(oracle_admin);
+        oracle.register_asset(asset);
+        oracle.set_price(asset, U256::from(WAD_U128));
+
+        let market_init = LendingMarketInitArgs {
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: env.get_account(5),
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model: RateModel::default(),
+                risk_params: RiskParams {
+                    collateral_factor: U256::from(WAD_U128 * 75 / 100),
+                    liquidation_threshold: U256::from(WAD_U128 * 80 / 100),
+                    close_factor: U256::from(WAD_U128 / 2),
+                    ..Default::default()
+                },
+            },
+        };
+        let mut market = LendingMarket::deploy(&env, market_init);
+        let token_init = ATokenInitArgs {
+            name: "Anchor Token".to_string(),
+            symbol: "aTKN".to_string(),
+            decimals: 9,
+            market: market.address(),
+        };
+        let a_token = AToken::deploy(&env, token_init);
+        let registry = setup_registry(
+            &env,
+            admin,
+            asset,
+            market.address(),
+            a_token.address(),
+            oracle.address(),
+        );
+        env.set_caller(admin);
+        market.set_a_token(a_token.address());
+        market.set_registry(registry.address());
+
+        seed_allowance(
+            &env,
+            &mut underlying,
+            admin,
+            delegator,
+            market.address(),
+            U256::from(TEST_MINT_AMOUNT),
+        );
+        env.set_caller(delegator);
+        market.deposit(U256::from(1_000u64));
+        let result = market.try_approve_delegation(delegator, U256::from(600u64));
+        assert_eq!(result, Err(Error::InvalidParam.into()));
+        market.approve_delegation(delegatee, U256::from(600u64));
+        assert!(env.emitted_event(
+            &market,
+            BorrowAllowanceDelegated {
+                delegator,
+                delegatee,
+                amount: U256::from(600u64),
+            }
+        ));
+
+        env.set_caller(delegatee);
+        market.borrow_on_behalf(delegator, U256::from(500u64));
+        assert_eq!(underlying.balance_of(&delegatee), U256::from(500u64));
+        assert_eq!(market.get_borrow_balance(delegator), U256::from(500u64));
+        assert_eq!(market.get_borrow_balance(delegatee), U256::zero());
+        assert_eq!(
+            market.get_borrow_allowance(delegator, delegatee),
+            U256::from(100u64)
+        );
+        assert!(env.emitted_event(
+            &market,
+            BorrowOnBehalf {
+                delegator,
+                delegatee,
+                amount: U256::from(500u64),
+                remaining_allowance: U256::from(100u64),
+            }
+        ));
+
+        let result = market.try_borrow_on_behalf(delegator, U256::from(200u64));
+        assert_eq!(result, Err(Error::InsufficientBorrowAllowance.into()));
+        env.set_caller(stranger);
+        let result = market.try_borrow_on_behalf(delegator, U256::from(1u64));
+        assert_eq!(result, Err(Error::InsufficientBorrowAllowance.into()));
+
+        // The delegator's health still caps the total: 750 of 1000 collateral.
+        env.set_caller(delegator);
+        market.approve_delegation(delegatee, U256::from(1_000u64));
+        env.set_caller(delegatee);
+        let result = market.try_borrow_on_behalf(delegator, U256::from(300u64));
+        assert_eq!(result, Err(Error::HealthFactorTooLow.into()));
+        assert_eq!(
+            market.get_borrow_allowance(delegator, delegatee),
+            U256::from(1_000u64)
+        );
+    }
+
+    #[test]
+    fn referred_deposits_and_borrows_are_recorded_in_registry() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let user = env.get_account(1);
+        let partner = env.get_account(2);
+        let mut underlying = deploy_underlying_token(&env);
+        let asset = underlying.address();
+
+        let oracle_init = PriceOracleInitArgs {
+            admin,
+            max_stale_millis: 10_000u64,
+        };
+        let mut oracle = PriceOracle::deploy(&env, oracle_init);
+        oracle.register_asset(asset);
+        oracle.set_price(asset, U256::from(WAD_U128));
+        let market_init = LendingMarketInitArgs {
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: admin,
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model: RateModel::default(),
+                risk_params: RiskParams {
+                    collateral_factor: U256::from(WAD_U128 * 75 / 100),
+                    liquidation_threshold: U256::from(WAD_U128 * 80 / 100),
+                    close_factor: U256::from(WAD_U128 / 2),
+                    ..Default::default()
+                },
+            },
+        };
+        let mut market = LendingMarket::deploy(&env, market_init);
+        let token_init = ATokenInitArgs {
+            name: "Anchor Token".to_string(),
+            symbol: "aTKN".to_string(),
+            decimals: 9,
+            market: market.address(),
+        };
+        let a_token = AToken::deploy(&env, token_init);
+        let mut registry = setup_registry(
+            &env,
+            admin,
+            asset,
+            market.address(),
+            a_token.address(),
+            oracle.address(),
+        );
+        market.set_a_token(a_token.address());
+        market.set_registry(registry.address());
+        env.set_caller(admin);
+        registry.register_referral_code(7, partner);
+
+        seed_allowance(
+            &env,
+            &mut underlying,
+            admin,
+            user,
+            market.address(),
+            U256::from(TEST_MINT_AMOUNT),
+        );
+        env.set_caller(user);
+        // An unknown code is ignored rather than blocking the deposit.
+        market.deposit_with_referral(U256::from(1_000u64), 8);
+        assert_eq!(registry.get_referral_volume(8, asset).supplied, U256::zero());
+        market.deposit_with_referral(U256::from(1_000u64), 7);
+        market.deposit_with_referral(U256::from(100u64), 0);
+        market.borrow_with_referral(U256::from(300u64), 7);
+
+        assert_eq!(a_token.balance_of(&user), U256::from(2_100u64));
+        assert_eq!(market.get_borrow_balance(user), U256::from(300u64));
+        let volume = registry.get_referral_volume(7, asset);
+        assert_eq!(volume.supplied, U256::from(1_000u64));
+        assert_eq!(volume.borrowed, U256::from(300u64));
+    }
+
+    #[test]
+    fn emode_positions_borrow_and_liquidate_on_category_params() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let user = env.get_account(1);
+        let mut underlying = deploy_underlying_token(&env);
+        let asset = underlying.address();
+
+        let oracle_init = PriceOracleInitArgs {
+            admin,
+            max_stale_millis: 30 * 24 * 60 * 60 * 1000,
+        };
+        let mut oracle = PriceOracle::deploy(&env, oracle_init);
+        oracle.register_asset(asset);
+        oracle.set_price(asset, U256::from(WAD_U128));
+        let market_init = LendingMarketInitArgs {
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: admin,
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model: RateModel::default(),
+                risk_params: RiskParams {
+                    collateral_factor: U256::from(WAD_U128 * 50 / 100),
+                    liquidation_threshold: U256::from(WAD_U128 * 60 / 100),
+                    close_factor: U256::from(WAD_U128 / 2),
+                    liquidation_bonus: U256::from(WAD_U128 * 10 / 100),
+                    ..Default::default()
+                },
+            },
+        };
+        let mut market = LendingMarket::deploy(&env, market_init);
+        let token_init = ATokenInitArgs {
+            name: "Anchor Token".to_string(),
+            symbol: "aTKN".to_string(),
+            decimals: 9,
+            market: market.address(),
+        };
+        let a_token = AToken::deploy(&env, token_init);
+        let mut registry = setup_registry(
+            &env,
+            admin,
+            asset,
+            market.address(),
+            a_token.address(),
+            oracle.address(),
+        );
+        market.set_a_token(a_token.address());
+        market.set_registry(registry.address());
+
+        let delay = registry.get_timelock_delay();
+        let result = registry.try_queue_asset_emode_category(asset, 1);
+        assert_eq!(result, Err(Error::InvalidParam.into()));
+        let id = registry.queue_emode_category(
+            1,
+            U256::from(WAD_U128 * 90 / 100),
+            U256::from(WAD_U128 * 93 / 100),
+            U256::from(WAD_U128 * 2 / 100),
+            "Stablecoins".to_string(),
+        );
+        env.advance_block_time(delay);
+        registry.execute_queued_action(id);
+        let id = registry.queue_asset_emode_category(asset, 1);
+        env.advance_block_time(delay);
+        registry.execute_queued_action(id);
+        assert_eq!(registry.get_asset_emode_category(asset), 1);
+
+        seed_allowance(
+            &env,
+            &mut underlying,
+            admin,
+            user,
+            market.address(),
+            U256::from(TEST_MINT_AMOUNT),
+        );
+        env.set_caller(user);
+        market.deposit(U256::from(1_000u64));
+        let result = market.try_borrow(U256::from(850u64));
+        assert_eq!(result, Err(Error::HealthFactorTooLow.into()));
+        let result = market.try_set_user_emode(2);
+        assert_eq!(result, Err(Error::EModeCategoryMismatch.into()));
+
+        market.set_user_emode(1);
+        assert!(env.emitted_event(
+            &market,
+            UserEModeSet {
+                account: user,
+                category_id: 1,
+            }
+        ));
+        market.borrow(U256::from(850u64));
+        let result = market.try_borrow(U256::from(51u64));
+        assert_eq!(result, Err(Error::HealthFactorTooLow.into()));
+        let result = market.try_withdraw(U256::from(100u64));
+        assert_eq!(result, Err(Error::HealthFactorTooLow.into()));
+        let result = market.try_set_user_emode(0);
+        assert_eq!(result, Err(Error::HealthFactorTooLow.into()));
+        assert!(!market.is_liquidatable(user));
+        assert_eq!(
+            market.get_account_risk_params(user).liquidation_bonus,
+            U256::from(WAD_U128 * 2 / 100)
+        );
+
+        // Taking the asset out of the category drops the position to market params.
+        env.set_caller(admin);
+        let id = registry.queue_asset_emode_category(asset, 0);
+        env.advance_block_time(delay);
+        registry.execute_queued_action(id);
+        assert!(market.is_liquidatable(user));
+        assert_eq!(
+            market.get_account_risk_params(user).collateral_factor,
+            U256::from(WAD_U128 * 50 / 100)
+        );
+    }
+
+    #[test]
+    fn debt_ceiling_limits_market_debt_in_units_or_usd() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let user = env.get_account(1);
+        let liquidator = env.get_account(2);
+        let mut underlying = deploy_underlying_token(&env);
+        let asset = underlying.address();
+
+        let oracle_init = PriceOracleInitArgs {
+            admin,
+            max_stale_millis: 30 * 24 * 60 * 60 * 1000,
+        };
+        let mut oracle = PriceOracle::deploy(&env, oracle_init);
+        oracle.register_asset(asset);
+        oracle.set_price(asset, U256::from(WAD_U128));
+        let market_init = LendingMarketInitArgs {
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: admin,
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model: RateModel::default(),
+                risk_params: RiskParams {
+                    collateral_factor: U256::from(WAD_U128 * 50 / 100),
+                    liquidation_threshold: U256::from(WAD_U128 * 60 / 100),
+                    close_factor: U256::from(WAD_U128 / 2),
+                    liquidation_bonus: U256::from(WAD_U128 * 10 / 100),
+                    ..Default::default()
+                },
+            },
+        };
+        let mut market = LendingMarket::deploy(&env, market_init);
+        let token_init = ATokenInitArgs {
+            name: "Anchor Token".to_string(),
+            symbol: "aTKN".to_string(),
+            decimals: 9,
+            market: market.address(),
+        };
+        let a_token = AToken::deploy(&env, token_init);
+        let mut registry = setup_registry(
+            &env,
+            admin,
+            asset,
+            market.address(),
+            a_token.address(),
+            oracle.address(),
+        );
+        market.set_a_token(a_token.address());
+        market.set_registry(registry.address());
+        assert_eq!(registry.get_debt_ceiling_headroom(asset), U256::MAX);
+
+        env.set_caller(user);
+        let result = registry.try_queue_debt_ceiling(asset, U256::from(300u64), false);
+        assert!(result.is_err());
+        env.set_caller(admin);
+        let delay = registry.get_timelock_delay();
+        let id = registry.queue_debt_ceiling(asset, U256::from(300u64), false);
+        env.advance_block_time(delay);
+        registry.execute_queued_action(id);
+        assert!(env.emitted_event(
+            &registry,
+            DebtCeilingUpdated {
+                asset,
+                amount: U256::from(300u64),
+                in_usd: false,
+            }
+        ));
+
+        seed_allowance(
+            &env,
+            &mut underlying,
+            admin,
+            user,
+            market.address(),
+            U256::from(TEST_MINT_AMOUNT),
+        );
+        env.set_caller(user);
+        market.deposit(U256::from(1_000u64));
+        market.borrow(U256::from(200u64));
+        assert_eq!(registry.get_debt_ceiling_headroom(asset), U256::from(100u64));
+        let result = market.try_borrow(U256::from(101u64));
+        assert_eq!(result, Err(Error::DebtCeilingExceeded.into()));
+        market.borrow(U256::from(100u64));
+        assert_eq!(registry.get_debt_ceiling_headroom(asset), U256::zero());
+        market.repay(U256::from(150u64));
+        assert_eq!(registry.get_issued_debt(asset), U256::from(150u64));
+
+        // In USD the same 150 units at a price of 2 use 300 of a 400 ceiling.
+        env.set_caller(admin);
+        let usd_ceiling = U256::from(400u64 * 1_000_000_000);
+        let id = registry.queue_debt_ceiling(asset, usd_ceiling, true);
+        env.advance_block_time(delay);
+        registry.execute_queued_action(id);
+        oracle.set_price(asset, U256::from(2 * WAD_U128));
+        assert_eq!(
+            registry.get_debt_ceiling_headroom(asset),
+            U256::from(100u64 * 1_000_000_000)
+        );
+        env.set_caller(user);
+        let result = market.try_borrow(U256::from(51u64));
+        assert_eq!(result, Err(Error::DebtCeilingExceeded.into()));
+        market.borrow(U256::from(50u64));
+        assert_eq!(registry.get_debt_ceiling_headroom(asset), U256::zero());
+
+        seed_allowance(
+            &env,
+            &mut underlying,
+            admin,
+            liquidator,
+            market.address(),
+            U256::from(TEST_MINT_AMOUNT),
+        );
+        env.set_caller(admin);
+        registry.deprecate_market(asset);
+        env.set_caller(liquidator);
+        market.liquidate(user, U256::from(100u64));
+        assert_eq!(registry.get_issued_debt(asset), U256::from(100u64));
+        assert_eq!(registry.get_issued_debt(asset), market.get_total_borrows());
+    }
+
+    #[test]
+    fn debt_ceiling_counts_interest_left_after_a_repay() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let user = env.get_account(1);
+        let mut underlying = deploy_underlying_token(&env);
+        let asset = underlying.address();
+
+        let oracle_init = PriceOracleInitArgs {
+            admin,
+            max_stale_millis: 30 * 24 * 60 * 60 * 1000,
+        };
+        let mut oracle = PriceOracle::deploy(&env, oracle_init);
+        oracle.register_asset(asset);
+        oracle.set_price(asset, U256::from(WAD_U128));
+        let market_init = LendingMarketInitArgs {
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: admin,
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model: RateModel {
+                    base_rate_per_sec: U256::from(MAX_BASE_RATE_PER_SEC),
+                    slope_rate_per_sec: U256::zero(),
+                },
+                risk_params: RiskParams {
+                    collateral_factor: U256::from(WAD_U128 * 50 / 100),
+                    liquidation_threshold: U256::from(WAD_U128 * 60 / 100),
+                    close_factor: U256::from(WAD_U128 / 2),
+                    ..Default::default()
+                },
+            },
+        };
+        let mut market = LendingMarket::deploy(&env, market_init);
+        let token_init = ATokenInitArgs {
+            name: "Anchor Token".to_string(),
+            symbol: "aTKN".to_string(),
+            decimals: 9,
+            market: market.address(),
+        };
+        let a_token = AToken::deploy(&env, token_init);
+        let mut registry = setup_registry(
+            &env,
+            admin,
+            asset,
+            market.address(),
+            a_token.address(),
+            oracle.address(),
+        );
+        market.set_a_token(a_token.address());
+        market.set_registry(registry.address());
+        let id = registry.queue_debt_ceiling(asset, U256::from(300u64), false);
+        env.advance_block_time(registry.get_timelock_delay());
+        registry.execute_queued_action(id);
+        oracle.set_price(asset, U256::from(WAD_U128));
+
+        seed_allowance(
+            &env,
+            &mut underlying,
+            admin,
+            user,
+            market.address(),
+            U256::from(TEST_MINT_AMOUNT),
+        );
+        env.set_caller(user);
+        market.deposit(U256::from(1_000u64));
+        market.borrow(U256::from(300u64));
+
+        // About 10% interest, then a repay of 100 that covers part of it.
+        env.advance_block_time(3_153_600_000);
+        env.set_caller(admin);
+        oracle.set_price(asset, U256::from(WAD_U128));
+        env.set_caller(user);
+        market.repay(U256::from(100u64));
+        let debt = market.get_total_borrows();
+        assert!(debt > U256::from(220u64));
+        assert_eq!(registry.get_issued_debt(asset), debt);
+
+        // Principal bookkeeping would allow 100 more; only the real gap is left.
+        let headroom = registry.get_debt_ceiling_headroom(asset);
+        assert_eq!(headroom, U256::from(300u64) - debt);
+        let result = market.try_borrow(headroom + 1);
+        assert_eq!(result, Err(Error::DebtCeilingExceeded.into()));
+        market.borrow(headroom);
+        assert_eq!(market.get_total_borrows(), U256::from(300u64));
+    }
+
+    #[test]
+    fn restricted_markets_admit_only_allowed_accounts() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let user = env.get_account(1);
+        let outsider = env.get_account(2);
+        let officer = env.get_account(3);
+        let mut underlying = deploy_underlying_token(&env);
+        let asset = underlying.address();
+
+        let oracle_init = PriceOracleInitArgs {
+            admin,
+            max_stale_millis: 30 * 24 * 60 * 60 * 1000,
+        };
+        let mut oracle = PriceOracle::deploy(&env, oracle_init);
+        oracle.register_asset(asset);
+        oracle.set_price(asset, U256::from(WAD_U128));
+        let market_init = LendingMarketInitArgs {
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: admin,
+                oracle: oracle.address(),
+                registry: admin,
+                rate_model: RateModel::default(),
+                risk_params: RiskParams {
+                    collateral_factor: U256::from(WAD_U128 * 50 / 100),
+                    liquidation_threshold: U256::from(WAD_U128 * 60 / 100),
+                    close_factor: U256::from(WAD_U128 / 2),
+                    liquidation_bonus: U256::from(WAD_U128 * 10 / 100),
+                    ..Default::default()
+                },
+            },
+        };
+        let mut market = LendingMarket::deploy(&env, market_init);
+        let token_init = ATokenInitArgs {
+            name: "Anchor Token".to_string(),
+            symbol: "aTKN".to_string(),
+            decimals: 9,
+            market: market.address(),
+        };
+        let mut a_token = AToken::deploy(&env, token_init);
+        let mut registry = setup_registry(
+            &env,
+            admin,
+            asset,
+            market.address(),
+            a_token.address(),
+            oracle.address(),
+        );
+        market.set_a_token(a_token.address());
+        market.set_registry(registry.address());
+        registry.grant_compliance_officer(officer);
+        for account in [user, outsider] {
+            seed_allowance(
+                &env,
+                &mut underlying,
+                admin,
+                account,
+                market.address(),
+                U256::from(TEST_MINT_AMOUNT),
+            );
+        }
+
+        env.set_caller(officer);
+        assert!(registry.try_set_access_mode(asset, AccessMode::Allowlist).is_err());
+        env.set_caller(admin);
+        assert!(registry.try_set_allowlisted(asset, user, true).is_err());
+        registry.set_access_mode(asset, AccessMode::Allowlist);
+        assert!(env.emitted_event(
+            &registry,
+            AccessModeUpdated {
+                asset,
+                mode: 1,
+                compliance_contract: None,
+            }
+        ));
+        env.set_caller(officer);
+        registry.set_allowlisted(asset, user, true);
+        assert!(env.emitted_event(
+            &registry,
+            AllowlistUpdated {
+                asset,
+                account: user,
+                allowed: true,
+            }
+        ));
+
+        env.set_caller(outsider);
+        let result = market.try_deposit(U256::from(1_000u64));
+        assert_eq!(result, Err(Error::AccountNotAllowed.into()));
+        env.set_caller(user);
+        market.deposit(U256::from(1_000u64));
+        market.borrow(U256::from(100u64));
+        let result = a_token.try_transfer(&outsider, &U256::from(10u64));
+        assert_eq!(result, Err(Error::AccountNotAllowed.into()));
+        market.approve_delegation(outsider, U256::from(50u64));
+        env.set_caller(outsider);
+        let result = market.try_borrow_on_behalf(user, U256::from(50u64));
+        assert_eq!(result, Err(Error::AccountNotAllowed.into()));
+
+        // Under a compliance contract the allowlist no longer applies.
+        let mut compliance = TestCompliance::deploy(&env, NoArgs);
+        compliance.set_compliant(outsider, true);
+        env.set_caller(admin);
+        registry.set_access_mode(asset, AccessMode::Compliance(compliance.address()));
+        assert!(env.emitted_event(
+            &registry,
+            AccessModeUpdated {
+                asset,
+                mode: 2,
+                compliance_contract: Some(compliance.address()),
+            }
+        ));
+        assert!(registry.is_allowlisted(asset, user));
+        assert!(!registry.is_account_allowed(asset, user));
+        env.set_caller(user);
+        let result = market.try_deposit(U256::from(1u64));
+        assert_eq!(result, Err(Error::AccountNotAllowed.into()));
+        market.repay(U256::from(20u64));
+        market.withdraw(U256::from(100u64));
+        env.set_caller(outsider);
+        market.deposit(U256::from(1_000u64));
+
+        env.set_caller(admin);
+        registry.deprecate_market(asset);
+        let result = market.try_liquidate(user, U256::from(40u64));
+        assert_eq!(result, Err(Error::AccountNotAllowed.into()));
+        env.set_caller(outsider);
+        market.liquidate(user, U256::from(40u64));
+        assert_eq!(market.get_borrow_balance(user), U256::from(40u64));
+    }
 }
@@ -3,12 +3,31 @@
 extern crate alloc;
 
 pub mod flipper;
+pub mod admin_access;
 pub mod market_registry;
+pub mod governance;
+pub mod timelock;
+pub mod pause_control;
+pub mod debt_ceiling;
+pub mod market_access;
+pub mod referrals;
 pub mod lending_market;
+pub mod market_links;
 pub mod price_oracle;
+pub mod rewards_controller;
+pub mod exchange_rate_oracle;
 pub mod a_token;
+pub mod variable_debt_token;
 pub mod cep18_interface;
 pub mod types;
 pub mod math;
+pub mod params;
 pub mod errors;
 pub mod events;
+#[cfg(test)]
+pub mod test_token;
+#[cfg(test)]
+pub mod test_compliance;
+#[cfg(test)]
+pub mod test_market_v1;
+pub mod test_stub_market;
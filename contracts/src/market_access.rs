@@ -0,0 +1,69 @@
+use odra::prelude::*;
+use odra::ContractRef;
+
+use crate::errors::Error;
+use crate::events::{AccessModeUpdated, AllowlistUpdated};
+use crate::types::AccessMode;
+
+/// Interface a compliance contract exposes to `AccessMode::Compliance` markets.
+#[odra::external_contract]
+pub trait ComplianceCheck {
+    fn is_compliant(&self, account: Address) -> bool;
+}
+
+/// Per-market access modes and allowlists, embedded by `MarketRegistry`.
+///
+/// Invariants (MVP):
+/// - Markets without a stored mode are open.
+/// - Allowlist entries are kept when the mode changes, so switching back to
+///   `Allowlist` restores the previous list.
+/// - Role checks are left to the embedding contract.
+#[odra::module(events = [AccessModeUpdated, AllowlistUpdated], errors = Error)]
+pub struct MarketAccess {
+    modes: Mapping<Address, AccessMode>,
+    allowlist: Mapping<(Address, Address), bool>,
+}
+
+#[odra::module]
+impl MarketAccess {
+    pub fn set_mode(&mut self, asset: Address, mode: AccessMode) {
+        let (mode_code, compliance_contract) = match mode {
+            AccessMode::Open => (0, None),
+            AccessMode::Allowlist => (1, None),
+            AccessMode::Compliance(contract) => (2, Some(contract)),
+        };
+        self.modes.set(&asset, mode);
+        self.env().emit_event(AccessModeUpdated {
+            asset,
+            mode: mode_code,
+            compliance_contract,
+        });
+    }
+
+    pub fn get_mode(&self, asset: Address) -> AccessMode {
+        self.modes.get(&asset).unwrap_or(AccessMode::Open)
+    }
+
+    pub fn set_allowlisted(&mut self, asset: Address, account: Address, allowed: bool) {
+        self.allowlist.set(&(asset, account), allowed);
+        self.env().emit_event(AllowlistUpdated {
+            asset,
+            account,
+            allowed,
+        });
+    }
+
+    pub fn is_allowlisted(&self, asset: Address, account: Address) -> bool {
+        self.allowlist.get_or_default(&(asset, account))
+    }
+
+    pub fn is_allowed(&self, asset: Address, account: Address) -> bool {
+        match self.get_mode(asset) {
+            AccessMode::Open => true,
+            AccessMode::Allowlist => self.is_allowlisted(asset, account),
+            AccessMode::Compliance(contract) => {
+                ComplianceCheckContractRef::new(self.env(), contract).is_compliant(account)
+            }
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use odra::prelude::*;
+
+use crate::errors::Error;
+
+/// Addresses a `LendingMarket` talks to, embedded as one storage slot.
+///
+/// Grouped so the market stays under Odra's field limit as it grows.
+#[odra::module(errors = Error)]
+pub struct MarketLinks {
+    asset: Var<Address>,
+    a_token: Var<Address>,
+    oracle: Var<Address>,
+    registry: Var<Address>,
+    debt_token: Var<Address>,
+    rewards_controller: Var<Address>,
+}
+
+#[odra::module]
+impl MarketLinks {
+    pub fn init(&mut self, asset: Address, a_token: Address, oracle: Address, registry: Address) {
+        self.asset.set(asset);
+        self.a_token.set(a_token);
+        self.oracle.set(oracle);
+        self.registry.set(registry);
+    }
+
+    pub fn set_a_token(&mut self, a_token: Address) {
+        self.a_token.set(a_token);
+    }
+
+    pub fn set_registry(&mut self, registry: Address) {
+        self.registry.set(registry);
+    }
+
+    pub fn set_debt_token(&mut self, debt_token: Address) {
+        self.debt_token.set(debt_token);
+    }
+
+    pub fn set_rewards_controller(&mut self, rewards_controller: Address) {
+        self.rewards_controller.set(rewards_controller);
+    }
+
+    pub fn get_asset(&self) -> Address {
+        self.asset
+            .get()
+            .unwrap_or_revert_with(&self.env(), Error::InvalidParam)
+    }
+
+    pub fn get_a_token(&self) -> Address {
+        self.a_token
+            .get()
+            .unwrap_or_revert_with(&self.env(), Error::InvalidParam)
+    }
+
+    pub fn get_oracle(&self) -> Address {
+        self.oracle
+            .get()
+            .unwrap_or_revert_with(&self.env(), Error::InvalidParam)
+    }
+
+    pub fn get_registry(&self) -> Address {
+        self.registry
+            .get()
+            .unwrap_or_revert_with(&self.env(), Error::InvalidParam)
+    }
+
+    /// Unset on markets listed before debt tokens existed.
+    pub fn get_debt_token(&self) -> Option<Address> {
+        self.debt_token.get()
+    }
+
+    pub fn get_rewards_controller(&self) -> Option<Address> {
+        self.rewards_controller.get()
+    }
+}
@@ -1,43 +1,116 @@
 use odra::casper_types::U256;
 use odra::prelude::*;
 use odra::ContractRef;
-use odra_modules::access::{AccessControl, Role, DEFAULT_ADMIN_ROLE};
+use odra_modules::access::{Role, DEFAULT_ADMIN_ROLE};
 
+use crate::a_token::{ATokenContractRef, ATokenFactoryContractRef};
+use crate::admin_access::AdminAccess;
+use crate::debt_ceiling::DebtCeilings;
 use crate::errors::Error;
-use crate::events::{MarketActiveUpdated, MarketRegistered, PauseFlagsUpdated};
-use crate::lending_market::LendingMarketContractRef;
-use crate::types::{MarketAddresses, PauseFlags};
+use crate::events::{
+    AssetEModeCategoryUpdated, DebtTokenLinked, EModeCategoryUpdated, MarketActiveUpdated,
+    MarketClosed, MarketDeprecated, MarketFrozen, MarketRegistered, MarketTemplatesUpdated,
+    MarketUnfrozen,
+};
+use crate::cep18_interface::Cep18TokenContractRef;
+use crate::lending_market::{LendingMarketContractRef, LendingMarketFactoryContractRef};
+use crate::market_access::MarketAccess;
+use crate::math::value_of;
+use crate::params::{validate_emode_category, validate_rate_model, validate_risk_params};
+use crate::pause_control::{all_paused, combine, PauseControl};
+use crate::price_oracle::PriceOracleContractRef;
+use crate::referrals::Referrals;
+use crate::timelock::Timelock;
+use crate::types::{
+    AccessMode, DebtCeiling, EModeCategory, GuardianPause, MarketAction, MarketAddresses,
+    MarketConfig, MarketStatus, MarketSummary, MarketTemplates, PauseFlags, PauseReason,
+    QueuedAction, RateModel, ReferralVolume, RiskParams,
+};
+use crate::variable_debt_token::VariableDebtTokenContractRef;
 
 const PROTOCOL_ADMIN_ROLE: Role = *b"PROTOCOL_ADMIN_ROLE_____________";
 const GUARDIAN_ROLE: Role = *b"GUARDIAN_ROLE___________________";
+const COMPLIANCE_OFFICER_ROLE: Role = *b"COMPLIANCE_OFFICER_ROLE_________";
+const DEFAULT_TIMELOCK_DELAY_MILLIS: u64 = 2 * 24 * 60 * 60 * 1000;
+/// Floor for `queue_timelock_delay`, so the timelock cannot be switched off.
+pub const MIN_TIMELOCK_DELAY_MILLIS: u64 = 24 * 60 * 60 * 1000;
+const DEFAULT_GUARDIAN_PAUSE_MILLIS: u64 = 24 * 60 * 60 * 1000;
+const MAX_MARKET_PAGE: u64 = 20;
 
 /// Registry of isolated markets and their shared configuration.
 ///
 /// Invariants (MVP):
 /// - Each asset can be registered at most once.
-/// - `market_addresses`, `pause_flags`, and `market_active` exist for registered assets.
-#[odra::module(events = [MarketRegistered, MarketActiveUpdated, PauseFlagsUpdated], errors = Error)]
+/// - `market_addresses`, pause flags, and `market_status` exist for registered assets.
+/// - Lifecycle only moves forward (Active <-> Frozen -> Deprecated -> Closed); each
+///   market reads its status from here and enforces it.
+/// - Markets honour the global pause OR-ed with their own flags. Guardians may only
+///   raise flags, and their pauses lapse after `guardian_pause_duration` unless
+///   PROTOCOL_ADMIN_ROLE extends them; only PROTOCOL_ADMIN_ROLE may lower flags.
+/// - Risk parameter, rate model and grace period changes go through the timelock:
+///   queued by PROTOCOL_ADMIN_ROLE, cancellable by guardians, executable after
+///   the delay. Markets accept these setters from the registry only.
+/// - The delay itself changes through the timelock and never drops below
+///   MIN_TIMELOCK_DELAY_MILLIS.
+/// - Queued changes are validated up front so invalid values never wait out the delay.
+/// - E-mode categories and asset assignments go through the timelock too;
+///   category changes are queued under the registry's own address.
+/// - PROTOCOL_ADMIN_ROLE assigns referral codes; code 0 means "no referral".
+///   Only a registered market records referred volume, per code and asset;
+///   unknown codes are ignored rather than reverting the user's action.
+/// - Debt ceilings go through the timelock. A market checks each borrow
+///   against its ceiling with its own total borrows; one past it reverts.
+/// - PROTOCOL_ADMIN_ROLE sets each market's access mode; COMPLIANCE_OFFICER_ROLE
+///   manages allowlists. Markets check `is_account_allowed` on deposit, borrow,
+///   aToken receipt and liquidation; exits stay open to everyone.
+#[odra::module(
+    events = [
+        MarketRegistered,
+        MarketTemplatesUpdated,
+        DebtTokenLinked,
+        MarketActiveUpdated,
+        MarketFrozen,
+        MarketUnfrozen,
+        MarketDeprecated,
+        MarketClosed,
+        EModeCategoryUpdated,
+        AssetEModeCategoryUpdated
+    ],
+    errors = Error
+)]
 pub struct MarketRegistry {
-    access_control: SubModule<AccessControl>,
+    access_control: SubModule<AdminAccess>,
     market_count: Var<u64>,
     market_by_index: Mapping<u64, Address>,
     market_exists: Mapping<Address, bool>,
     market_addresses: Mapping<Address, MarketAddresses>,
-    market_active: Mapping<Address, bool>,
-    pause_flags: Mapping<Address, PauseFlags>,
+    market_status: Mapping<Address, MarketStatus>,
+    status_updated_at: Mapping<Address, u64>,
+    pause: SubModule<PauseControl>,
+    timelock: SubModule<Timelock>,
+    referrals: SubModule<Referrals>,
+    emode_categories: Mapping<u8, EModeCategory>,
+    asset_emode_categories: Mapping<Address, u8>,
+    debt_ceilings: SubModule<DebtCeilings>,
+    access: SubModule<MarketAccess>,
+    templates: Var<MarketTemplates>,
 }
 
 #[odra::module]
 impl MarketRegistry {
     pub fn init(&mut self, admin: Address) {
-        self.access_control
-            .unchecked_grant_role(&DEFAULT_ADMIN_ROLE, &admin);
+        self.access_control.init_admin(admin);
         self.access_control
             .unchecked_grant_role(&PROTOCOL_ADMIN_ROLE, &admin);
         self.access_control
             .set_admin_role(&PROTOCOL_ADMIN_ROLE, &DEFAULT_ADMIN_ROLE);
         self.access_control
             .set_admin_role(&GUARDIAN_ROLE, &PROTOCOL_ADMIN_ROLE);
+        self.access_control
+            .set_admin_role(&COMPLIANCE_OFFICER_ROLE, &PROTOCOL_ADMIN_ROLE);
+        self.timelock.set_delay(DEFAULT_TIMELOCK_DELAY_MILLIS);
+        self.pause
+            .set_guardian_pause_duration(DEFAULT_GUARDIAN_PAUSE_MILLIS);
     }
 
     pub fn register_market(
@@ -47,102 +120,448 @@ impl MarketRegistry {
         a_token: Address,
         oracle: Address,
     ) {
+        self.ensure_protocol_admin();
+        self.store_market(asset, market, a_token, oracle);
+    }
+
+    /// Wires a freshly deployed market/aToken pair and registers it in one call.
+    ///
+    /// The market must have been deployed for `asset` pointing at this registry
+    /// and `oracle`, and the aToken for that market; any mismatch reverts before
+    /// anything is stored.
+    pub fn attach_market(
+        &mut self,
+        asset: Address,
+        market: Address,
+        a_token: Address,
+        oracle: Address,
+    ) {
+        self.ensure_protocol_admin();
+        let mut market_ref = LendingMarketContractRef::new(self.env(), market);
+        let registry = self.env().self_address();
+        if market_ref.get_asset() != asset
+            || market_ref.get_registry() != registry
+            || market_ref.get_oracle() != oracle
+        {
+            self.env().revert(Error::InvalidParam);
+        }
+        if ATokenContractRef::new(self.env(), a_token).get_market() != market {
+            self.env().revert(Error::InvalidParam);
+        }
+        market_ref.set_a_token(a_token);
+        self.store_market(asset, market, a_token, oracle);
+    }
+
+    /// Points `create_market` at installed `LendingMarketFactory` and
+    /// `ATokenFactory` contracts.
+    pub fn set_market_templates(&mut self, market_factory: Address, a_token_factory: Address) {
+        self.ensure_protocol_admin();
+        self.templates.set(MarketTemplates {
+            market_factory,
+            a_token_factory,
+        });
+        self.env().emit_event(MarketTemplatesUpdated {
+            market_factory,
+            a_token_factory,
+        });
+    }
+
+    pub fn get_market_templates(&self) -> Option<MarketTemplates> {
+        self.templates.get()
+    }
+
+    /// Deploys a market and its aToken from the stored templates, wires them
+    /// together and registers the market, all in one call.
+    ///
+    /// The caller becomes the market admin and the aToken takes the asset's
+    /// decimals. A failure at any step reverts the whole call.
+    pub fn create_market(
+        &mut self,
+        asset: Address,
+        oracle: Address,
+        rate_model: RateModel,
+        risk_params: RiskParams,
+        a_token_name: String,
+        a_token_symbol: String,
+    ) -> MarketAddresses {
         self.ensure_protocol_admin();
         if self.market_exists.get_or_default(&asset) {
             self.env().revert(Error::MarketAlreadyRegistered);
         }
-        let addrs = MarketAddresses {
-            market,
-            a_token,
-            oracle,
-        };
-        self.market_addresses.set(&asset, addrs);
-        self.market_active.set(&asset, true);
-        self.pause_flags.set(&asset, default_pause_flags());
-        self.market_exists.set(&asset, true);
-
+        let templates = self
+            .templates
+            .get()
+            .unwrap_or_revert_with(&self.env(), Error::MarketTemplatesNotSet);
+        let registry = self.env().self_address();
         let index = self.market_count.get_or_default();
-        self.market_by_index.set(&index, asset);
-        self.market_count.set(index + 1);
 
-        self.env().emit_event(MarketRegistered {
-            asset,
+        // The registry stands in for the aToken until the aToken exists.
+        let mut market_factory =
+            LendingMarketFactoryContractRef::new(self.env(), templates.market_factory);
+        let (market, _) = market_factory.new_contract(
+            format!("anchor_market_{}", index),
+            MarketConfig {
+                admin: self.env().caller(),
+                asset,
+                a_token: registry,
+                oracle,
+                registry,
+                rate_model,
+                risk_params,
+            },
+        );
+        let decimals = Cep18TokenContractRef::new(self.env(), asset).decimals();
+        let mut a_token_factory =
+            ATokenFactoryContractRef::new(self.env(), templates.a_token_factory);
+        let (a_token, _) = a_token_factory.new_contract(
+            format!("anchor_a_token_{}", index),
+            a_token_name,
+            a_token_symbol,
+            decimals,
             market,
-            a_token,
-            oracle,
-        });
+        );
+        LendingMarketContractRef::new(self.env(), market).set_a_token(a_token);
+        self.store_market(asset, market, a_token, oracle);
+        self.get_market_addresses(asset)
+    }
+
+    /// Links a variable-debt token deployed for `asset`'s market and records it
+    /// in the market's addresses.
+    pub fn link_debt_token(&mut self, asset: Address, debt_token: Address) {
+        self.ensure_protocol_admin();
+        let mut addresses = self.get_market_addresses(asset);
+        let token = VariableDebtTokenContractRef::new(self.env(), debt_token);
+        if token.get_market() != addresses.market {
+            self.env().revert(Error::InvalidParam);
+        }
+        LendingMarketContractRef::new(self.env(), addresses.market).set_debt_token(debt_token);
+        addresses.debt_token = Some(debt_token);
+        self.market_addresses.set(&asset, addresses);
+        self.env()
+            .emit_event(DebtTokenLinked { asset, debt_token });
     }
 
-    pub fn set_market_active(&mut self, asset: Address, is_active: bool) {
+    /// Stops new supply and borrow; guardians may freeze as a defensive action.
+    pub fn freeze_market(&mut self, asset: Address) {
+        self.ensure_guardian_or_admin();
+        self.ensure_market_status(asset, &[MarketStatus::Active]);
+        self.update_market_status(asset, MarketStatus::Frozen);
+        self.env().emit_event(MarketFrozen { asset });
+    }
+
+    pub fn unfreeze_market(&mut self, asset: Address) {
+        self.ensure_protocol_admin();
+        self.ensure_market_status(asset, &[MarketStatus::Frozen]);
+        self.update_market_status(asset, MarketStatus::Active);
+        self.env().emit_event(MarketUnfrozen { asset });
+    }
+
+    /// Starts the wind-down of a market; there is no way back to Active.
+    pub fn deprecate_market(&mut self, asset: Address) {
+        self.ensure_protocol_admin();
+        self.ensure_market_status(asset, &[MarketStatus::Active, MarketStatus::Frozen]);
+        self.update_market_status(asset, MarketStatus::Deprecated);
+        self.env().emit_event(MarketDeprecated { asset });
+    }
+
+    /// Closes a deprecated market once every borrow has been repaid or liquidated.
+    pub fn close_market(&mut self, asset: Address) {
         self.ensure_protocol_admin();
+        self.ensure_market_status(asset, &[MarketStatus::Deprecated]);
+        let market = self.get_market_addresses(asset).market;
+        let total_borrows = LendingMarketContractRef::new(self.env(), market).get_total_borrows();
+        if !total_borrows.is_zero() {
+            self.env().revert(Error::MarketHasOpenBorrows);
+        }
+        self.update_market_status(asset, MarketStatus::Closed);
+        self.env().emit_event(MarketClosed { asset });
+    }
+
+    /// Guardians may only add flags, and their pause lapses after the guardian
+    /// pause duration; protocol admins replace the flags outright.
+    pub fn set_pause_flags(&mut self, asset: Address, flags: PauseFlags, reason_code: u32) {
+        self.ensure_guardian_or_admin();
         self.ensure_market_exists(asset);
-        self.market_active.set(&asset, is_active);
-        self.env().emit_event(MarketActiveUpdated { asset, is_active });
+        let by_admin = self.is_protocol_admin_caller();
+        self.pause
+            .set_market_flags(asset, flags, reason_code, by_admin);
+    }
+
+    /// Sets the pause flags applied to every market on top of its own flags.
+    pub fn set_global_pause(&mut self, flags: PauseFlags, reason_code: u32) {
+        self.ensure_guardian_or_admin();
+        let by_admin = self.is_protocol_admin_caller();
+        self.pause.set_global_flags(flags, reason_code, by_admin);
+    }
+
+    /// Incident shortcut: pauses every action in every market.
+    pub fn pause_all_markets(&mut self, reason_code: u32) {
+        self.set_global_pause(all_paused(), reason_code);
+    }
+
+    /// Keeps a guardian pause on `asset` in force for `duration_millis` from now.
+    pub fn extend_guardian_pause(&mut self, asset: Address, duration_millis: u64) {
+        self.ensure_protocol_admin();
+        self.pause
+            .extend_market_guardian_pause(asset, duration_millis);
+    }
+
+    pub fn extend_global_guardian_pause(&mut self, duration_millis: u64) {
+        self.ensure_protocol_admin();
+        self.pause.extend_global_guardian_pause(duration_millis);
+    }
+
+    pub fn set_guardian_pause_duration(&mut self, duration_millis: u64) {
+        self.access_control.ensure_default_admin();
+        self.pause.set_guardian_pause_duration(duration_millis);
+    }
+
+    pub fn get_guardian_pause_duration(&self) -> u64 {
+        self.pause.get_guardian_pause_duration()
+    }
+
+    /// Queues new risk params for `asset`; returns the timelock action id.
+    pub fn queue_market_risk_params(&mut self, asset: Address, risk_params: RiskParams) -> u64 {
+        self.ensure_protocol_admin();
+        self.ensure_market_exists(asset);
+        if let Err(error) = validate_risk_params(&risk_params) {
+            self.env().revert(error);
+        }
+        self.timelock
+            .queue(asset, MarketAction::RiskParams(risk_params))
     }
 
-    pub fn set_pause_flags(
+    /// Queues a new rate model for `asset`; returns the timelock action id.
+    pub fn queue_market_rate_model(
         &mut self,
         asset: Address,
-        supply_paused: bool,
-        borrow_paused: bool,
-        withdraw_paused: bool,
-        repay_paused: bool,
-        liquidation_paused: bool,
-    ) {
-        self.ensure_guardian_or_admin();
+        base_rate_per_sec: U256,
+        slope_rate_per_sec: U256,
+    ) -> u64 {
+        self.ensure_protocol_admin();
         self.ensure_market_exists(asset);
-        let flags = PauseFlags {
-            supply_paused,
-            borrow_paused,
-            withdraw_paused,
-            repay_paused,
-            liquidation_paused,
+        let rate_model = RateModel {
+            base_rate_per_sec,
+            slope_rate_per_sec,
         };
-        self.pause_flags.set(&asset, flags);
-        self.env().emit_event(PauseFlagsUpdated {
-            asset,
-            supply_paused,
-            borrow_paused,
-            withdraw_paused,
-            repay_paused,
-            liquidation_paused,
-        });
+        if let Err(error) = validate_rate_model(&rate_model) {
+            self.env().revert(error);
+        }
+        self.timelock
+            .queue(asset, MarketAction::RateModel(rate_model))
     }
 
-    pub fn update_market_risk_params(
+    /// Queues a new liquidation grace period for `asset`'s market.
+    pub fn queue_liquidation_grace_period(
         &mut self,
         asset: Address,
+        grace_period_millis: u64,
+    ) -> u64 {
+        self.ensure_protocol_admin();
+        self.ensure_market_exists(asset);
+        self.timelock
+            .queue(asset, MarketAction::LiquidationGracePeriod(grace_period_millis))
+    }
+
+    /// Queues a new timelock delay; it applies to actions queued after it executes.
+    pub fn queue_timelock_delay(&mut self, delay_millis: u64) -> u64 {
+        self.access_control.ensure_default_admin();
+        if delay_millis < MIN_TIMELOCK_DELAY_MILLIS {
+            self.env().revert(Error::TimelockDelayTooShort);
+        }
+        let registry = self.env().self_address();
+        self.timelock
+            .queue(registry, MarketAction::TimelockDelay(delay_millis))
+    }
+
+    /// Queues creating or replacing E-mode category `category_id`.
+    pub fn queue_emode_category(
+        &mut self,
+        category_id: u8,
         collateral_factor: U256,
         liquidation_threshold: U256,
-        close_factor: U256,
         liquidation_bonus: U256,
-        reserve_factor: U256,
-        borrow_cap: U256,
-        supply_cap: U256,
-    ) {
+        label: String,
+    ) -> u64 {
         self.ensure_protocol_admin();
-        let market = self.get_market_addresses(asset).market;
-        LendingMarketContractRef::new(self.env(), market).set_risk_params(
+        let category = EModeCategory {
+            id: category_id,
             collateral_factor,
             liquidation_threshold,
-            close_factor,
             liquidation_bonus,
-            reserve_factor,
-            borrow_cap,
-            supply_cap,
-        );
+            label,
+        };
+        if let Err(error) = validate_emode_category(&category) {
+            self.env().revert(error);
+        }
+        let registry = self.env().self_address();
+        self.timelock
+            .queue(registry, MarketAction::EModeCategory(category))
+    }
+
+    /// Queues moving `asset` into E-mode category `category_id` (0 takes it out).
+    pub fn queue_asset_emode_category(&mut self, asset: Address, category_id: u8) -> u64 {
+        self.ensure_protocol_admin();
+        self.ensure_market_exists(asset);
+        self.ensure_emode_category_exists(category_id);
+        self.timelock
+            .queue(asset, MarketAction::AssetEModeCategory(category_id))
+    }
+
+    /// Queues a ceiling on the debt owed to `asset`'s market, in underlying
+    /// units or, with `in_usd`, in WAD value at the oracle price. Zero removes it.
+    pub fn queue_debt_ceiling(&mut self, asset: Address, amount: U256, in_usd: bool) -> u64 {
+        self.ensure_protocol_admin();
+        self.ensure_market_exists(asset);
+        self.timelock.queue(
+            asset,
+            MarketAction::DebtCeiling(DebtCeiling { amount, in_usd }),
+        )
+    }
+
+    pub fn execute_queued_action(&mut self, id: u64) {
+        self.ensure_protocol_admin();
+        let queued = self.timelock.execute(id);
+        match queued.action {
+            MarketAction::RiskParams(params) => {
+                self.load_market(queued.asset).set_risk_params(params)
+            }
+            MarketAction::RateModel(model) => self
+                .load_market(queued.asset)
+                .set_rate_model(model.base_rate_per_sec, model.slope_rate_per_sec),
+            MarketAction::EModeCategory(category) => self.write_emode_category(category),
+            MarketAction::AssetEModeCategory(category_id) => {
+                self.write_asset_emode_category(queued.asset, category_id)
+            }
+            MarketAction::DebtCeiling(ceiling) => {
+                self.debt_ceilings.set_ceiling(queued.asset, ceiling)
+            }
+            MarketAction::LiquidationGracePeriod(grace_period_millis) => self
+                .load_market(queued.asset)
+                .set_liquidation_grace_period(grace_period_millis),
+            MarketAction::TimelockDelay(delay_millis) => self.timelock.set_delay(delay_millis),
+        }
+    }
+
+    pub fn cancel_queued_action(&mut self, id: u64) {
+        self.ensure_guardian_or_admin();
+        self.timelock.cancel(id);
+    }
+
+    pub fn get_timelock_delay(&self) -> u64 {
+        self.timelock.get_delay()
+    }
+
+    pub fn get_queued_action(&self, id: u64) -> QueuedAction {
+        self.timelock.get_action(id)
     }
 
-    pub fn update_market_rate_model(
+    pub fn get_queued_action_count(&self) -> u64 {
+        self.timelock.get_action_count()
+    }
+
+    /// Assigns an unused referral code to `owner`. Protocol admin only, so
+    /// nobody can squat on codes meant for partners.
+    pub fn register_referral_code(&mut self, code: u32, owner: Address) {
+        self.ensure_protocol_admin();
+        self.referrals.register(code, owner);
+    }
+
+    /// Hands a referral code to `new_owner`; only its current owner may.
+    pub fn transfer_referral_code(&mut self, code: u32, new_owner: Address) {
+        let caller = self.env().caller();
+        self.referrals.transfer(code, caller, new_owner);
+    }
+
+    /// Called by `asset`'s market after a referred deposit or borrow.
+    pub fn record_referral(
         &mut self,
         asset: Address,
-        base_rate_per_sec: U256,
-        slope_rate_per_sec: U256,
+        code: u32,
+        account: Address,
+        amount: U256,
+        is_borrow: bool,
     ) {
+        self.ensure_market_caller(asset);
+        self.referrals
+            .record(asset, code, account, amount, is_borrow);
+    }
+
+    pub fn get_referral_code_owner(&self, code: u32) -> Address {
+        self.referrals.get_owner(code)
+    }
+
+    pub fn get_referral_volume(&self, code: u32, asset: Address) -> ReferralVolume {
+        self.referrals.get_volume(code, asset)
+    }
+
+    /// Reverts with `DebtCeilingExceeded` if `total_borrows`, the debt of
+    /// `asset`'s market after a borrow, interest included, is over the ceiling.
+    pub fn check_debt_ceiling(&self, asset: Address, total_borrows: U256) {
+        let ceiling = self.debt_ceilings.get_ceiling(asset);
+        if ceiling.amount.is_zero() {
+            return;
+        }
+        if self.measure_debt(asset, &ceiling, total_borrows) > ceiling.amount {
+            self.env().revert(Error::DebtCeilingExceeded);
+        }
+    }
+
+    pub fn get_debt_ceiling(&self, asset: Address) -> DebtCeiling {
+        self.debt_ceilings.get_ceiling(asset)
+    }
+
+    /// Debt owed to `asset`'s market, interest included, in underlying units.
+    pub fn get_issued_debt(&self, asset: Address) -> U256 {
+        self.ensure_market_exists(asset);
+        self.load_market(asset).get_total_borrows()
+    }
+
+    /// Debt that can still be issued, in the ceiling's unit; `U256::MAX` when
+    /// no ceiling is set.
+    pub fn get_debt_ceiling_headroom(&self, asset: Address) -> U256 {
+        let ceiling = self.debt_ceilings.get_ceiling(asset);
+        if ceiling.amount.is_zero() {
+            return U256::MAX;
+        }
+        let issued = self.get_issued_debt(asset);
+        ceiling
+            .amount
+            .saturating_sub(self.measure_debt(asset, &ceiling, issued))
+    }
+
+    pub fn set_access_mode(&mut self, asset: Address, mode: AccessMode) {
         self.ensure_protocol_admin();
-        let market = self.get_market_addresses(asset).market;
-        LendingMarketContractRef::new(self.env(), market)
-            .set_rate_model(base_rate_per_sec, slope_rate_per_sec);
+        self.ensure_market_exists(asset);
+        self.access.set_mode(asset, mode);
+    }
+
+    pub fn set_allowlisted(&mut self, asset: Address, account: Address, allowed: bool) {
+        self.ensure_compliance_officer();
+        self.ensure_market_exists(asset);
+        self.access.set_allowlisted(asset, account, allowed);
+    }
+
+    pub fn get_access_mode(&self, asset: Address) -> AccessMode {
+        self.access.get_mode(asset)
+    }
+
+    pub fn is_allowlisted(&self, asset: Address, account: Address) -> bool {
+        self.access.is_allowlisted(asset, account)
+    }
+
+    /// Whether `account` passes `asset`'s access mode.
+    pub fn is_account_allowed(&self, asset: Address, account: Address) -> bool {
+        self.access.is_allowed(asset, account)
+    }
+
+    pub fn get_emode_category(&self, category_id: u8) -> Option<EModeCategory> {
+        self.emode_categories.get(&category_id)
+    }
+
+    /// E-mode category of `asset`; 0 when it is in none.
+    pub fn get_asset_emode_category(&self, asset: Address) -> u8 {
+        self.asset_emode_categories.get_or_default(&asset)
     }
 
     pub fn get_market_addresses(&self, asset: Address) -> MarketAddresses {
@@ -152,13 +571,46 @@ impl MarketRegistry {
     }
 
     pub fn get_pause_flags(&self, asset: Address) -> PauseFlags {
-        self.pause_flags
+        self.pause.get_market_flags(asset)
+    }
+
+    pub fn get_global_pause_flags(&self) -> PauseFlags {
+        self.pause.get_global_flags()
+    }
+
+    pub fn get_guardian_pause(&self, asset: Address) -> Option<GuardianPause> {
+        self.pause.get_market_guardian_pause(asset)
+    }
+
+    pub fn get_global_guardian_pause(&self) -> Option<GuardianPause> {
+        self.pause.get_global_guardian_pause()
+    }
+
+    pub fn get_pause_reason(&self, asset: Address) -> Option<PauseReason> {
+        self.pause.get_market_reason(asset)
+    }
+
+    pub fn get_global_pause_reason(&self) -> Option<PauseReason> {
+        self.pause.get_global_reason()
+    }
+
+    /// Per-asset flags combined with the global pause; this is what markets enforce.
+    pub fn get_effective_pause_flags(&self, asset: Address) -> PauseFlags {
+        combine(&self.get_pause_flags(asset), &self.get_global_pause_flags())
+    }
+
+    pub fn get_market_status(&self, asset: Address) -> MarketStatus {
+        self.market_status
             .get(&asset)
             .unwrap_or_revert_with(&self.env(), Error::MarketNotFound)
     }
 
+    pub fn get_market_status_updated_at(&self, asset: Address) -> u64 {
+        self.status_updated_at.get_or_default(&asset)
+    }
+
     pub fn is_market_active(&self, asset: Address) -> bool {
-        self.market_active.get_or_default(&asset)
+        self.market_status.get(&asset) == Some(MarketStatus::Active)
     }
 
     pub fn get_market_count(&self) -> u64 {
@@ -171,6 +623,29 @@ impl MarketRegistry {
             .unwrap_or_revert_with(&self.env(), Error::MarketNotFound)
     }
 
+    /// Returns up to `limit` market snapshots starting at registration index `offset`.
+    pub fn list_markets(&self, offset: u64, limit: u64) -> Vec<MarketSummary> {
+        if limit == 0 || limit > MAX_MARKET_PAGE {
+            self.env().revert(Error::InvalidParam);
+        }
+        let end = offset
+            .saturating_add(limit)
+            .min(self.market_count.get_or_default());
+        (offset..end)
+            .map(|index| self.market_summary(self.get_market_by_index(index)))
+            .collect()
+    }
+
+    delegate! {
+        to self.access_control {
+            fn propose_admin(&mut self, pending_admin: Address);
+            fn cancel_admin_transfer(&mut self);
+            fn accept_admin(&mut self);
+            fn get_admin(&self) -> Address;
+            fn get_pending_admin(&self) -> Option<Address>;
+        }
+    }
+
     pub fn grant_protocol_admin(&mut self, address: Address) {
         self.access_control
             .grant_role(&PROTOCOL_ADMIN_ROLE, &address);
@@ -189,6 +664,16 @@ impl MarketRegistry {
         self.access_control.revoke_role(&GUARDIAN_ROLE, &address);
     }
 
+    pub fn grant_compliance_officer(&mut self, address: Address) {
+        self.access_control
+            .grant_role(&COMPLIANCE_OFFICER_ROLE, &address);
+    }
+
+    pub fn revoke_compliance_officer(&mut self, address: Address) {
+        self.access_control
+            .revoke_role(&COMPLIANCE_OFFICER_ROLE, &address);
+    }
+
     pub fn has_protocol_admin(&self, address: Address) -> bool {
         self.access_control
             .has_role(&PROTOCOL_ADMIN_ROLE, &address)
@@ -197,6 +682,11 @@ impl MarketRegistry {
     pub fn has_guardian(&self, address: Address) -> bool {
         self.access_control.has_role(&GUARDIAN_ROLE, &address)
     }
+
+    pub fn has_compliance_officer(&self, address: Address) -> bool {
+        self.access_control
+            .has_role(&COMPLIANCE_OFFICER_ROLE, &address)
+    }
 }
 
 impl MarketRegistry {
@@ -205,6 +695,11 @@ impl MarketRegistry {
             .check_role(&PROTOCOL_ADMIN_ROLE, &self.env().caller());
     }
 
+    fn is_protocol_admin_caller(&self) -> bool {
+        self.access_control
+            .has_role(&PROTOCOL_ADMIN_ROLE, &self.env().caller())
+    }
+
     fn ensure_guardian_or_admin(&self) {
         let caller = self.env().caller();
         if self.access_control.has_role(&GUARDIAN_ROLE, &caller)
@@ -215,38 +710,162 @@ impl MarketRegistry {
         self.env().revert(Error::Unauthorized);
     }
 
-    fn ensure_market_exists(&self, asset: Address) {
-        if !self.market_exists.get_or_default(&asset) {
-            self.env().revert(Error::MarketNotFound);
+    fn ensure_compliance_officer(&self) {
+        self.access_control
+            .check_role(&COMPLIANCE_OFFICER_ROLE, &self.env().caller());
+    }
+
+    fn store_market(
+        &mut self,
+        asset: Address,
+        market: Address,
+        a_token: Address,
+        oracle: Address,
+    ) {
+        if self.market_exists.get_or_default(&asset) {
+            self.env().revert(Error::MarketAlreadyRegistered);
         }
+        let addrs = MarketAddresses {
+            market,
+            a_token,
+            oracle,
+            debt_token: None,
+        };
+        self.market_addresses.set(&asset, addrs);
+        self.market_status.set(&asset, MarketStatus::Active);
+        self.status_updated_at
+            .set(&asset, self.env().get_block_time());
+        self.pause.init_market(asset);
+        self.market_exists.set(&asset, true);
+
+        let index = self.market_count.get_or_default();
+        self.market_by_index.set(&index, asset);
+        self.market_count.set(index + 1);
+
+        self.env().emit_event(MarketRegistered {
+            asset,
+            market,
+            a_token,
+            oracle,
+        });
     }
-}
 
-fn default_pause_flags() -> PauseFlags {
-    PauseFlags {
-        supply_paused: false,
-        borrow_paused: false,
-        withdraw_paused: false,
-        repay_paused: false,
-        liquidation_paused: false,
+    fn market_summary(&self, asset: Address) -> MarketSummary {
+        let addresses = self.get_market_addresses(asset);
+        let market = LendingMarketContractRef::new(self.env(), addresses.market);
+        MarketSummary {
+            asset,
+            pause_flags: self.get_pause_flags(asset),
+            status: self.get_market_status(asset),
+            risk_params: market.get_risk_params(),
+            rate_model: market.get_rate_model(),
+            cash: market.get_cash(),
+            total_borrows: market.get_total_borrows(),
+            total_reserves: market.get_total_reserves(),
+            borrow_rate_per_sec: market.get_borrow_rate_per_sec(),
+            supply_rate_per_sec: market.get_supply_rate_per_sec(),
+            addresses,
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::lending_market::{LendingMarket, LendingMarketInitArgs};
-    use crate::math::WAD_U128;
-    use crate::types::{RateModel, RiskParams};
-    use odra::casper_types::U256;
-    use odra::host::Deployer;
+    fn ensure_market_status(&self, asset: Address, allowed: &[MarketStatus]) {
+        if !allowed.contains(&self.get_market_status(asset)) {
+            self.env().revert(Error::InvalidMarketTransition);
+        }
+    }
 
-    #[test]
-    fn admin_can_register_market() {
-        let env = odra_test::env();
-        let admin = env.get_account(0);
-        let asset = env.get_account(1);
-        let market = env.get_account(2);
+    fn update_market_status(&mut self, asset: Address, status: MarketStatus) {
+        // Settle interest under the old status before rates can change.
+        let market = self.get_market_addresses(asset).market;
+        LendingMarketContractRef::new(self.env(), market).accrue_interest();
+        // MarketActiveUpdated is still emitted for existing indexers.
+        let is_active = status == MarketStatus::Active;
+        self.market_status.set(&asset, status);
+        self.status_updated_at
+            .set(&asset, self.env().get_block_time());
+        self.env().emit_event(MarketActiveUpdated { asset, is_active });
+    }
+
+    fn load_market(&self, asset: Address) -> LendingMarketContractRef {
+        let market = self.get_market_addresses(asset).market;
+        LendingMarketContractRef::new(self.env(), market)
+    }
+
+    fn ensure_emode_category_exists(&self, category_id: u8) {
+        if category_id != 0 && self.emode_categories.get(&category_id).is_none() {
+            self.env().revert(Error::InvalidParam);
+        }
+    }
+
+    fn write_emode_category(&mut self, category: EModeCategory) {
+        self.emode_categories.set(&category.id, category.clone());
+        self.env().emit_event(EModeCategoryUpdated {
+            category_id: category.id,
+            collateral_factor: category.collateral_factor,
+            liquidation_threshold: category.liquidation_threshold,
+            liquidation_bonus: category.liquidation_bonus,
+            label: category.label,
+        });
+    }
+
+    fn write_asset_emode_category(&mut self, asset: Address, category_id: u8) {
+        self.ensure_emode_category_exists(category_id);
+        self.asset_emode_categories.set(&asset, category_id);
+        self.env()
+            .emit_event(AssetEModeCategoryUpdated { asset, category_id });
+    }
+
+    fn ensure_market_caller(&self, asset: Address) {
+        if self.env().caller() != self.get_market_addresses(asset).market {
+            self.env().revert(Error::Unauthorized);
+        }
+    }
+
+    fn measure_debt(&self, asset: Address, ceiling: &DebtCeiling, issued: U256) -> U256 {
+        if !ceiling.in_usd {
+            return issued;
+        }
+        let oracle = self.get_market_addresses(asset).oracle;
+        let oracle = PriceOracleContractRef::new(self.env(), oracle);
+        value_of(
+            issued,
+            oracle.get_price_checked(asset),
+            oracle.get_asset_decimals(asset),
+        )
+    }
+
+    fn ensure_market_exists(&self, asset: Address) {
+        if !self.market_exists.get_or_default(&asset) {
+            self.env().revert(Error::MarketNotFound);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::a_token::{AToken, ATokenFactory, ATokenHostRef, ATokenInitArgs};
+    use crate::lending_market::{
+        LendingMarket, LendingMarketFactory, LendingMarketHostRef, LendingMarketInitArgs,
+    };
+    use crate::math::WAD_U128;
+    use crate::events::{
+        AdminTransferCancelled, AdminTransferProposed, AdminTransferred, GlobalPauseUpdated,
+        GuardianPauseExtended, MarketClosed, MarketFrozen, PauseFlagsUpdated, Referral,
+        ReferralCodeRegistered, TimelockActionCancelled, TimelockActionQueued,
+    };
+    use crate::pause_control::unpaused;
+    use crate::timelock::TIMELOCK_GRACE_PERIOD_MILLIS;
+    use crate::types::TimelockStatus;
+    use odra::casper_types::U256;
+    use odra::host::{Deployer, HostRef, NoArgs};
+
+    #[test]
+    fn admin_can_register_market() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let asset = env.get_account(1);
+        let market = env.get_account(2);
         let a_token = env.get_account(3);
         let oracle = env.get_account(4);
         let init_args = MarketRegistryInitArgs { admin };
@@ -297,18 +916,173 @@ mod tests {
         registry.grant_guardian(guardian);
 
         env.set_caller(guardian);
-        let flags = registry.get_pause_flags(asset);
-        registry.set_pause_flags(
-            asset,
-            flags.supply_paused,
-            true,
-            flags.withdraw_paused,
-            flags.repay_paused,
-            flags.liquidation_paused,
-        );
+        let flags = PauseFlags {
+            borrow_paused: true,
+            ..registry.get_pause_flags(asset)
+        };
+        registry.set_pause_flags(asset, flags, 7);
 
         let updated = registry.get_pause_flags(asset);
         assert!(updated.borrow_paused);
+        let reason = registry.get_pause_reason(asset).unwrap();
+        assert_eq!(reason.code, 7);
+        assert_eq!(reason.updated_at, env.block_time());
+    }
+
+    #[test]
+    fn guardian_pause_expires_unless_extended_by_admin() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let guardian = env.get_account(5);
+        let asset = env.get_account(1);
+        let market = env.get_account(2);
+        let a_token = env.get_account(3);
+        let oracle = env.get_account(4);
+        let init_args = MarketRegistryInitArgs { admin };
+        let mut registry = MarketRegistry::deploy(&env, init_args);
+        let duration = registry.get_guardian_pause_duration();
+        assert_eq!(duration, DEFAULT_GUARDIAN_PAUSE_MILLIS);
+
+        env.set_caller(admin);
+        registry.register_market(asset, market, a_token, oracle);
+        registry.grant_guardian(guardian);
+
+        env.set_caller(guardian);
+        let paused_at = env.block_time();
+        let flags = PauseFlags {
+            supply_paused: true,
+            borrow_paused: true,
+            ..unpaused()
+        };
+        registry.set_pause_flags(asset, flags, 42);
+        assert!(env.emitted_event(
+            &registry,
+            PauseFlagsUpdated {
+                asset,
+                supply_paused: true,
+                borrow_paused: true,
+                withdraw_paused: false,
+                repay_paused: false,
+                liquidation_paused: false,
+                reason_code: 42,
+                timestamp: paused_at,
+                expires_at: paused_at + duration,
+            }
+        ));
+        // Guardians cannot lift their own pause early, nor extend it.
+        let flags = PauseFlags {
+            borrow_paused: true,
+            ..unpaused()
+        };
+        let result = registry.try_set_pause_flags(asset, flags, 0);
+        assert!(result.is_err());
+        assert!(registry.try_extend_guardian_pause(asset, duration).is_err());
+
+        env.advance_block_time(duration / 2);
+        env.set_caller(admin);
+        registry.extend_guardian_pause(asset, duration);
+        let expires_at = env.block_time() + duration;
+        assert!(env.emitted_event(
+            &registry,
+            GuardianPauseExtended {
+                asset: Some(asset),
+                expires_at,
+            }
+        ));
+
+        env.advance_block_time(duration - 1);
+        assert!(registry.get_pause_flags(asset).supply_paused);
+        assert_eq!(registry.get_guardian_pause(asset).unwrap().expires_at, expires_at);
+
+        env.advance_block_time(1);
+        let flags = registry.get_pause_flags(asset);
+        assert!(!flags.supply_paused && !flags.borrow_paused);
+        assert!(registry.get_guardian_pause(asset).is_none());
+        assert_eq!(registry.get_pause_reason(asset).unwrap().code, 42);
+        assert_eq!(
+            registry.try_extend_guardian_pause(asset, duration),
+            Err(Error::NoGuardianPause.into())
+        );
+
+        // Admin pauses do not lapse.
+        let flags = PauseFlags {
+            withdraw_paused: true,
+            ..unpaused()
+        };
+        registry.set_pause_flags(asset, flags, 3);
+        env.advance_block_time(duration * 10);
+        assert!(registry.get_pause_flags(asset).withdraw_paused);
+    }
+
+    #[test]
+    fn global_pause_is_raised_by_guardian_and_lifted_by_admin_only() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let guardian = env.get_account(5);
+        let asset = env.get_account(1);
+        let market = env.get_account(2);
+        let a_token = env.get_account(3);
+        let oracle = env.get_account(4);
+        let init_args = MarketRegistryInitArgs { admin };
+        let mut registry = MarketRegistry::deploy(&env, init_args);
+
+        env.set_caller(admin);
+        registry.register_market(asset, market, a_token, oracle);
+        registry.grant_guardian(guardian);
+
+        env.set_caller(guardian);
+        let flags = PauseFlags {
+            borrow_paused: true,
+            ..unpaused()
+        };
+        registry.set_global_pause(flags, 1);
+        let effective = registry.get_effective_pause_flags(asset);
+        assert!(effective.borrow_paused);
+        assert!(!effective.supply_paused);
+        assert!(!registry.get_pause_flags(asset).borrow_paused);
+
+        let paused_at = env.block_time();
+        registry.pause_all_markets(2);
+        assert!(env.emitted_event(
+            &registry,
+            GlobalPauseUpdated {
+                supply_paused: true,
+                borrow_paused: true,
+                withdraw_paused: true,
+                repay_paused: true,
+                liquidation_paused: true,
+                reason_code: 2,
+                timestamp: paused_at,
+                expires_at: paused_at + DEFAULT_GUARDIAN_PAUSE_MILLIS,
+            }
+        ));
+        assert_eq!(registry.get_global_pause_reason().unwrap().code, 2);
+
+        let flags = PauseFlags {
+            supply_paused: true,
+            borrow_paused: true,
+            repay_paused: true,
+            liquidation_paused: true,
+            ..unpaused()
+        };
+        let result = registry.try_set_global_pause(flags, 2);
+        assert!(result.is_err());
+        env.set_caller(env.get_account(6));
+        assert!(registry.try_pause_all_markets(2).is_err());
+
+        env.set_caller(admin);
+        registry.extend_global_guardian_pause(DEFAULT_GUARDIAN_PAUSE_MILLIS);
+        assert!(env.emitted_event(
+            &registry,
+            GuardianPauseExtended {
+                asset: None,
+                expires_at: paused_at + DEFAULT_GUARDIAN_PAUSE_MILLIS,
+            }
+        ));
+        registry.set_global_pause(unpaused(), 0);
+        assert!(registry.get_global_guardian_pause().is_none());
+        let effective = registry.get_effective_pause_flags(asset);
+        assert!(!effective.supply_paused && !effective.borrow_paused);
     }
 
     #[test]
@@ -327,16 +1101,21 @@ mod tests {
             base_rate_per_sec: U256::zero(),
             slope_rate_per_sec: U256::zero(),
         };
-        let risk_params = RiskParams::default();
+        let risk_params = RiskParams {
+            close_factor: U256::from(WAD_U128 / 2),
+            ..Default::default()
+        };
 
         let market_init = LendingMarketInitArgs {
-            admin,
-            asset,
-            a_token,
-            oracle,
-            registry: registry.address(),
-            rate_model,
-            risk_params,
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token,
+                oracle,
+                registry: registry.address(),
+                rate_model,
+                risk_params,
+            },
         };
         let market = LendingMarket::deploy(&env, market_init);
 
@@ -347,18 +1126,15 @@ mod tests {
         let slope_rate_per_sec = U256::from(2u8);
         let mut new_params = RiskParams::default();
         new_params.reserve_factor = U256::from(WAD_U128 / 10);
+        new_params.close_factor = U256::from(WAD_U128 / 2);
 
-        registry.update_market_rate_model(asset, base_rate_per_sec, slope_rate_per_sec);
-        registry.update_market_risk_params(
-            asset,
-            new_params.collateral_factor,
-            new_params.liquidation_threshold,
-            new_params.close_factor,
-            new_params.liquidation_bonus,
-            new_params.reserve_factor,
-            new_params.borrow_cap,
-            new_params.supply_cap,
-        );
+        let rate_id =
+            registry.queue_market_rate_model(asset, base_rate_per_sec, slope_rate_per_sec);
+        let risk_id = registry.queue_market_risk_params(asset, new_params);
+
+        env.advance_block_time(DEFAULT_TIMELOCK_DELAY_MILLIS);
+        registry.execute_queued_action(rate_id);
+        registry.execute_queued_action(risk_id);
 
         let got_model = market.get_rate_model();
         let got_params = market.get_risk_params();
@@ -384,16 +1160,21 @@ mod tests {
             base_rate_per_sec: U256::zero(),
             slope_rate_per_sec: U256::zero(),
         };
-        let risk_params = RiskParams::default();
+        let risk_params = RiskParams {
+            close_factor: U256::from(WAD_U128 / 2),
+            ..Default::default()
+        };
 
         let market_init = LendingMarketInitArgs {
-            admin,
-            asset,
-            a_token,
-            oracle,
-            registry: registry.address(),
-            rate_model,
-            risk_params,
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token,
+                oracle,
+                registry: registry.address(),
+                rate_model,
+                risk_params,
+            },
         };
         let market = LendingMarket::deploy(&env, market_init);
 
@@ -403,11 +1184,673 @@ mod tests {
         env.set_caller(attacker);
         let base_rate_per_sec = U256::from(1u8);
         let slope_rate_per_sec = U256::from(2u8);
-        let result = registry.try_update_market_rate_model(
+        let result = registry.try_queue_market_rate_model(
             asset,
             base_rate_per_sec,
             slope_rate_per_sec,
         );
         assert!(result.is_err());
+
+        env.set_caller(admin);
+        let id = registry.queue_market_rate_model(asset, base_rate_per_sec, slope_rate_per_sec);
+        env.advance_block_time(DEFAULT_TIMELOCK_DELAY_MILLIS);
+        env.set_caller(attacker);
+        let result = registry.try_execute_queued_action(id);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn queued_change_waits_for_delay_and_is_visible() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let asset = env.get_account(1);
+        let a_token = env.get_account(3);
+        let oracle = env.get_account(4);
+
+        let init_args = MarketRegistryInitArgs { admin };
+        let mut registry = MarketRegistry::deploy(&env, init_args);
+
+        let market_init = LendingMarketInitArgs {
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token,
+                oracle,
+                registry: registry.address(),
+                rate_model: RateModel::default(),
+                risk_params: RiskParams {
+                    close_factor: U256::from(WAD_U128 / 2),
+                    ..Default::default()
+                },
+            },
+        };
+        let market = LendingMarket::deploy(&env, market_init);
+
+        env.set_caller(admin);
+        registry.register_market(asset, market.address(), a_token, oracle);
+
+        let id = registry.queue_market_rate_model(asset, U256::from(1u8), U256::from(2u8));
+        let queued = registry.get_queued_action(id);
+        let action = MarketAction::RateModel(RateModel {
+            base_rate_per_sec: U256::from(1u8),
+            slope_rate_per_sec: U256::from(2u8),
+        });
+        assert_eq!(registry.get_queued_action_count(), 1);
+        assert_eq!(queued.asset, asset);
+        assert_eq!(queued.action, action);
+        assert_eq!(queued.status, TimelockStatus::Queued);
+        assert!(env.emitted_event(
+            &registry.address(),
+            TimelockActionQueued {
+                id,
+                asset,
+                eta: queued.eta,
+            }
+        ));
+
+        env.advance_block_time(DEFAULT_TIMELOCK_DELAY_MILLIS - 1);
+        let result = registry.try_execute_queued_action(id);
+        assert_eq!(result, Err(Error::TimelockNotReady.into()));
+        assert_eq!(market.get_rate_model().base_rate_per_sec, U256::zero());
+
+        env.advance_block_time(1);
+        registry.execute_queued_action(id);
+        assert_eq!(market.get_rate_model().base_rate_per_sec, U256::from(1u8));
+        assert_eq!(registry.get_queued_action(id).status, TimelockStatus::Executed);
+
+        let result = registry.try_execute_queued_action(id);
+        assert_eq!(result, Err(Error::TimelockActionNotQueued.into()));
+    }
+
+    #[test]
+    fn attach_market_wires_and_registers_in_one_call() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let asset = env.get_account(1);
+        let oracle = env.get_account(2);
+        let init_args = MarketRegistryInitArgs { admin };
+        let mut registry = MarketRegistry::deploy(&env, init_args);
+
+        let market_init = LendingMarketInitArgs {
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: admin,
+                oracle,
+                registry: registry.address(),
+                rate_model: RateModel::default(),
+                risk_params: RiskParams {
+                    close_factor: U256::from(WAD_U128 / 2),
+                    ..Default::default()
+                },
+            },
+        };
+        let market = LendingMarket::deploy(&env, market_init);
+        let a_token_init = ATokenInitArgs {
+            name: String::from("Anchor Token"),
+            symbol: String::from("aTKN"),
+            decimals: 9,
+            market: market.address(),
+        };
+        let a_token = AToken::deploy(&env, a_token_init);
+
+        env.set_caller(env.get_account(3));
+        let result =
+            registry.try_attach_market(asset, market.address(), a_token.address(), oracle);
+        assert!(result.is_err());
+
+        env.set_caller(admin);
+        registry.attach_market(asset, market.address(), a_token.address(), oracle);
+
+        assert_eq!(market.get_a_token(), a_token.address());
+        let addrs = registry.get_market_addresses(asset);
+        assert_eq!(addrs.market, market.address());
+        assert_eq!(addrs.a_token, a_token.address());
+        assert!(registry.is_market_active(asset));
+    }
+
+    #[test]
+    fn attach_market_reverts_on_mismatched_contracts() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let asset = env.get_account(1);
+        let oracle = env.get_account(2);
+        let init_args = MarketRegistryInitArgs { admin };
+        let mut registry = MarketRegistry::deploy(&env, init_args);
+
+        let market_init = LendingMarketInitArgs {
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: admin,
+                oracle,
+                registry: registry.address(),
+                rate_model: RateModel::default(),
+                risk_params: RiskParams {
+                    close_factor: U256::from(WAD_U128 / 2),
+                    ..Default::default()
+                },
+            },
+        };
+        let market = LendingMarket::deploy(&env, market_init);
+        let foreign_token_init = ATokenInitArgs {
+            name: String::from("Other Token"),
+            symbol: String::from("aOTH"),
+            decimals: 9,
+            market: env.get_account(4),
+        };
+        let foreign_token = AToken::deploy(&env, foreign_token_init);
+
+        env.set_caller(admin);
+        let result =
+            registry.try_attach_market(asset, market.address(), foreign_token.address(), oracle);
+        assert_eq!(result, Err(Error::InvalidParam.into()));
+
+        let other_asset = env.get_account(5);
+        let result = registry.try_attach_market(other_asset, market.address(), admin, oracle);
+        assert_eq!(result, Err(Error::InvalidParam.into()));
+
+        let other_oracle = env.get_account(6);
+        let result = registry.try_attach_market(asset, market.address(), admin, other_oracle);
+        assert_eq!(result, Err(Error::InvalidParam.into()));
+
+        assert_eq!(market.get_a_token(), admin);
+        assert_eq!(registry.get_market_count(), 0);
+    }
+
+    #[test]
+    fn create_market_needs_admin_and_templates() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let asset = env.get_account(1);
+        let oracle = env.get_account(2);
+        let init_args = MarketRegistryInitArgs { admin };
+        let mut registry = MarketRegistry::deploy(&env, init_args);
+        let risk_params = RiskParams {
+            close_factor: U256::from(WAD_U128 / 2),
+            ..Default::default()
+        };
+        let name = String::from("Anchor Token");
+        let symbol = String::from("aTKN");
+
+        env.set_caller(env.get_account(3));
+        assert!(registry.try_set_market_templates(admin, admin).is_err());
+        let result = registry.try_create_market(
+            asset,
+            oracle,
+            RateModel::default(),
+            risk_params.clone(),
+            name.clone(),
+            symbol.clone(),
+        );
+        assert!(result.is_err());
+
+        env.set_caller(admin);
+        let result = registry.try_create_market(
+            asset,
+            oracle,
+            RateModel::default(),
+            risk_params.clone(),
+            name.clone(),
+            symbol.clone(),
+        );
+        assert_eq!(result, Err(Error::MarketTemplatesNotSet.into()));
+
+        registry.register_market(asset, admin, admin, oracle);
+        let result = registry.try_create_market(
+            asset,
+            oracle,
+            RateModel::default(),
+            risk_params,
+            name,
+            symbol,
+        );
+        assert_eq!(result, Err(Error::MarketAlreadyRegistered.into()));
+        assert_eq!(registry.get_market_templates(), None);
+    }
+
+    #[test]
+    #[ignore = "factories only run on CasperVM: build wasm and set ODRA_BACKEND=casper"]
+    fn create_market_deploys_from_templates() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let oracle = env.get_account(2);
+        let init_args = MarketRegistryInitArgs { admin };
+        let mut registry = MarketRegistry::deploy(&env, init_args);
+        let market_factory = LendingMarketFactory::deploy(&env, NoArgs);
+        let a_token_factory = ATokenFactory::deploy(&env, NoArgs);
+        // Any CEP-18 contract works as the asset; the market only reads decimals here.
+        let asset_init = ATokenInitArgs {
+            name: String::from("Underlying"),
+            symbol: String::from("UND"),
+            decimals: 6,
+            market: admin,
+        };
+        let asset = AToken::deploy(&env, asset_init).address();
+
+        env.set_caller(admin);
+        registry.set_market_templates(market_factory.address(), a_token_factory.address());
+        assert!(env.emitted_event(
+            &registry,
+            MarketTemplatesUpdated {
+                market_factory: market_factory.address(),
+                a_token_factory: a_token_factory.address(),
+            }
+        ));
+        let addrs = registry.create_market(
+            asset,
+            oracle,
+            RateModel::default(),
+            RiskParams {
+                close_factor: U256::from(WAD_U128 / 2),
+                ..Default::default()
+            },
+            String::from("Anchor Underlying"),
+            String::from("aUND"),
+        );
+
+        let market = LendingMarketHostRef::new(addrs.market, env.clone());
+        let a_token = ATokenHostRef::new(addrs.a_token, env.clone());
+        assert_eq!(market.get_asset(), asset);
+        assert_eq!(market.get_a_token(), addrs.a_token);
+        assert_eq!(market.get_oracle(), oracle);
+        assert_eq!(market.get_registry(), registry.address());
+        assert_eq!(a_token.get_market(), addrs.market);
+        assert_eq!(a_token.decimals(), 6);
+        assert_eq!(a_token.symbol(), "aUND");
+        assert!(registry.is_market_active(asset));
+        assert_eq!(registry.get_market_count(), 1);
+    }
+
+    #[test]
+    fn list_markets_returns_paginated_snapshots() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let oracle = env.get_account(2);
+        let guardian = env.get_account(3);
+        let init_args = MarketRegistryInitArgs { admin };
+        let mut registry = MarketRegistry::deploy(&env, init_args);
+
+        let risk_params = RiskParams {
+            close_factor: U256::from(WAD_U128 / 2),
+            ..Default::default()
+        };
+        let assets = [env.get_account(4), env.get_account(5), env.get_account(6)];
+        env.set_caller(admin);
+        for (i, asset) in assets.iter().enumerate() {
+            let market_init = LendingMarketInitArgs {
+                config: MarketConfig {
+                    admin,
+                    asset: *asset,
+                    a_token: admin,
+                    oracle,
+                    registry: registry.address(),
+                    rate_model: RateModel {
+                        base_rate_per_sec: U256::from(i + 1),
+                        slope_rate_per_sec: U256::zero(),
+                    },
+                    risk_params: risk_params.clone(),
+                },
+            };
+            let market = LendingMarket::deploy(&env, market_init);
+            registry.register_market(*asset, market.address(), admin, oracle);
+        }
+        registry.grant_guardian(guardian);
+        env.set_caller(guardian);
+        let flags = PauseFlags {
+            borrow_paused: true,
+            ..unpaused()
+        };
+        registry.set_pause_flags(assets[1], flags, 0);
+
+        let first_page = registry.list_markets(0, 2);
+        assert_eq!(first_page.len(), 2);
+        assert_eq!(first_page[0].asset, assets[0]);
+        assert_eq!(first_page[1].asset, assets[1]);
+        assert!(first_page[1].pause_flags.borrow_paused);
+        assert_eq!(first_page[1].status, MarketStatus::Active);
+        assert_eq!(first_page[1].risk_params, risk_params);
+        assert_eq!(first_page[1].rate_model.base_rate_per_sec, U256::from(2u8));
+        assert_eq!(first_page[1].borrow_rate_per_sec, U256::from(2u8));
+        assert_eq!(first_page[1].cash, U256::zero());
+        assert_eq!(
+            first_page[1].addresses,
+            registry.get_market_addresses(assets[1])
+        );
+
+        let second_page = registry.list_markets(2, 2);
+        assert_eq!(second_page.len(), 1);
+        assert_eq!(second_page[0].asset, assets[2]);
+        assert!(registry.list_markets(5, 2).is_empty());
+
+        let result = registry.try_list_markets(0, 0);
+        assert_eq!(result, Err(Error::InvalidParam.into()));
+        let result = registry.try_list_markets(0, MAX_MARKET_PAGE + 1);
+        assert_eq!(result, Err(Error::InvalidParam.into()));
+    }
+
+    #[test]
+    fn lifecycle_transitions_follow_roles_and_order() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let asset = env.get_account(1);
+        let oracle = env.get_account(2);
+        let guardian = env.get_account(3);
+        let attacker = env.get_account(4);
+        let init_args = MarketRegistryInitArgs { admin };
+        let mut registry = MarketRegistry::deploy(&env, init_args);
+
+        let market_init = LendingMarketInitArgs {
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: admin,
+                oracle,
+                registry: registry.address(),
+                rate_model: RateModel::default(),
+                risk_params: RiskParams {
+                    close_factor: U256::from(WAD_U128 / 2),
+                    ..Default::default()
+                },
+            },
+        };
+        let market = LendingMarket::deploy(&env, market_init);
+
+        env.set_caller(admin);
+        registry.register_market(asset, market.address(), admin, oracle);
+        registry.grant_guardian(guardian);
+        assert_eq!(registry.get_market_status(asset), MarketStatus::Active);
+
+        env.set_caller(guardian);
+        registry.freeze_market(asset);
+        assert_eq!(registry.get_market_status(asset), MarketStatus::Frozen);
+        assert!(!registry.is_market_active(asset));
+        assert!(env.emitted_event(&registry, MarketFrozen { asset }));
+
+        // Guardians can only take defensive steps.
+        assert!(registry.try_unfreeze_market(asset).is_err());
+        assert!(registry.try_deprecate_market(asset).is_err());
+        env.set_caller(attacker);
+        assert!(registry.try_unfreeze_market(asset).is_err());
+
+        env.set_caller(admin);
+        registry.unfreeze_market(asset);
+        assert!(registry.is_market_active(asset));
+
+        env.advance_block_time(1_000u64);
+        registry.deprecate_market(asset);
+        assert_eq!(registry.get_market_status(asset), MarketStatus::Deprecated);
+        assert_eq!(
+            registry.get_market_status_updated_at(asset),
+            env.block_time()
+        );
+        let result = registry.try_unfreeze_market(asset);
+        assert_eq!(result, Err(Error::InvalidMarketTransition.into()));
+        let result = registry.try_freeze_market(asset);
+        assert_eq!(result, Err(Error::InvalidMarketTransition.into()));
+
+        registry.close_market(asset);
+        assert_eq!(registry.get_market_status(asset), MarketStatus::Closed);
+        assert!(env.emitted_event(&registry, MarketClosed { asset }));
+        let result = registry.try_deprecate_market(asset);
+        assert_eq!(result, Err(Error::InvalidMarketTransition.into()));
+    }
+
+    #[test]
+    fn invalid_changes_cannot_be_queued() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let asset = env.get_account(1);
+        let market = env.get_account(2);
+        let a_token = env.get_account(3);
+        let oracle = env.get_account(4);
+        let init_args = MarketRegistryInitArgs { admin };
+        let mut registry = MarketRegistry::deploy(&env, init_args);
+
+        env.set_caller(admin);
+        registry.register_market(asset, market, a_token, oracle);
+
+        // 0.9 * (1 + 0.2) > 1: the liquidator could seize more than the collateral.
+        let risky = RiskParams {
+            collateral_factor: U256::from(WAD_U128 * 85 / 100),
+            liquidation_threshold: U256::from(WAD_U128 * 90 / 100),
+            close_factor: U256::from(WAD_U128 / 2),
+            liquidation_bonus: U256::from(WAD_U128 * 20 / 100),
+            ..Default::default()
+        };
+        let result = registry.try_queue_market_risk_params(asset, risky);
+        assert_eq!(result, Err(Error::LiquidationBonusTooHigh.into()));
+
+        let result =
+            registry.try_queue_market_rate_model(asset, U256::from(WAD_U128), U256::zero());
+        assert_eq!(result, Err(Error::BaseRateTooHigh.into()));
+        assert_eq!(registry.get_queued_action_count(), 0);
+    }
+
+    #[test]
+    fn guardian_can_cancel_queued_change() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let guardian = env.get_account(5);
+        let asset = env.get_account(1);
+        let market = env.get_account(2);
+        let a_token = env.get_account(3);
+        let oracle = env.get_account(4);
+        let init_args = MarketRegistryInitArgs { admin };
+        let mut registry = MarketRegistry::deploy(&env, init_args);
+
+        env.set_caller(admin);
+        registry.register_market(asset, market, a_token, oracle);
+        registry.grant_guardian(guardian);
+        let params = RiskParams {
+            close_factor: U256::from(WAD_U128 / 2),
+            ..Default::default()
+        };
+        let id = registry.queue_market_risk_params(asset, params);
+
+        env.set_caller(env.get_account(6));
+        let result = registry.try_cancel_queued_action(id);
+        assert_eq!(result, Err(Error::Unauthorized.into()));
+
+        env.set_caller(guardian);
+        registry.cancel_queued_action(id);
+        assert_eq!(registry.get_queued_action(id).status, TimelockStatus::Cancelled);
+        assert!(env.emitted_event(
+            &registry.address(),
+            TimelockActionCancelled { id, asset }
+        ));
+
+        env.advance_block_time(DEFAULT_TIMELOCK_DELAY_MILLIS);
+        env.set_caller(admin);
+        let result = registry.try_execute_queued_action(id);
+        assert_eq!(result, Err(Error::TimelockActionNotQueued.into()));
+    }
+
+    #[test]
+    fn queued_change_expires_after_grace_period() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let asset = env.get_account(1);
+        let market = env.get_account(2);
+        let a_token = env.get_account(3);
+        let oracle = env.get_account(4);
+        let init_args = MarketRegistryInitArgs { admin };
+        let mut registry = MarketRegistry::deploy(&env, init_args);
+
+        env.set_caller(admin);
+        registry.register_market(asset, market, a_token, oracle);
+        let id = registry.queue_market_rate_model(asset, U256::from(1u8), U256::from(2u8));
+
+        env.advance_block_time(DEFAULT_TIMELOCK_DELAY_MILLIS + TIMELOCK_GRACE_PERIOD_MILLIS + 1);
+        let result = registry.try_execute_queued_action(id);
+        assert_eq!(result, Err(Error::TimelockActionExpired.into()));
+    }
+
+    #[test]
+    fn timelock_delay_changes_are_timelocked_and_floored() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let protocol_admin = env.get_account(1);
+        let init_args = MarketRegistryInitArgs { admin };
+        let mut registry = MarketRegistry::deploy(&env, init_args);
+        assert_eq!(registry.get_timelock_delay(), DEFAULT_TIMELOCK_DELAY_MILLIS);
+
+        env.set_caller(admin);
+        registry.grant_protocol_admin(protocol_admin);
+
+        env.set_caller(protocol_admin);
+        let result = registry.try_queue_timelock_delay(MIN_TIMELOCK_DELAY_MILLIS);
+        assert!(result.is_err());
+
+        env.set_caller(admin);
+        let result = registry.try_queue_timelock_delay(0);
+        assert_eq!(result, Err(Error::TimelockDelayTooShort.into()));
+        let result = registry.try_queue_timelock_delay(MIN_TIMELOCK_DELAY_MILLIS - 1);
+        assert_eq!(result, Err(Error::TimelockDelayTooShort.into()));
+
+        let id = registry.queue_timelock_delay(MIN_TIMELOCK_DELAY_MILLIS);
+        assert_eq!(registry.get_timelock_delay(), DEFAULT_TIMELOCK_DELAY_MILLIS);
+        let result = registry.try_execute_queued_action(id);
+        assert_eq!(result, Err(Error::TimelockNotReady.into()));
+        env.advance_block_time(DEFAULT_TIMELOCK_DELAY_MILLIS);
+        registry.execute_queued_action(id);
+        assert_eq!(registry.get_timelock_delay(), MIN_TIMELOCK_DELAY_MILLIS);
+    }
+
+    #[test]
+    fn default_admin_is_handed_over_in_two_steps() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let multisig = env.get_account(7);
+        let stranger = env.get_account(8);
+        let init_args = MarketRegistryInitArgs { admin };
+        let mut registry = MarketRegistry::deploy(&env, init_args);
+        assert_eq!(registry.get_admin(), admin);
+        assert_eq!(registry.get_pending_admin(), None);
+
+        env.set_caller(stranger);
+        assert!(registry.try_propose_admin(stranger).is_err());
+        assert_eq!(
+            registry.try_accept_admin(),
+            Err(Error::NoPendingAdmin.into())
+        );
+
+        env.set_caller(admin);
+        assert_eq!(
+            registry.try_propose_admin(admin),
+            Err(Error::InvalidParam.into())
+        );
+        registry.propose_admin(stranger);
+        registry.cancel_admin_transfer();
+        assert!(env.emitted_event(
+            &registry,
+            AdminTransferCancelled {
+                pending_admin: stranger,
+            }
+        ));
+
+        registry.propose_admin(multisig);
+        assert!(env.emitted_event(
+            &registry,
+            AdminTransferProposed {
+                admin,
+                pending_admin: multisig,
+            }
+        ));
+        assert_eq!(registry.get_pending_admin(), Some(multisig));
+
+        env.set_caller(stranger);
+        assert_eq!(
+            registry.try_accept_admin(),
+            Err(Error::NotPendingAdmin.into())
+        );
+
+        env.set_caller(multisig);
+        registry.accept_admin();
+        assert!(env.emitted_event(
+            &registry,
+            AdminTransferred {
+                previous_admin: admin,
+                new_admin: multisig,
+            }
+        ));
+        assert_eq!(registry.get_admin(), multisig);
+        assert_eq!(registry.get_pending_admin(), None);
+        registry.queue_timelock_delay(MIN_TIMELOCK_DELAY_MILLIS);
+
+        env.set_caller(admin);
+        assert!(registry
+            .try_queue_timelock_delay(MIN_TIMELOCK_DELAY_MILLIS)
+            .is_err());
+        assert!(registry.try_cancel_admin_transfer().is_err());
+    }
+
+    #[test]
+    fn referral_codes_track_volume_reported_by_markets() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let asset = env.get_account(1);
+        let market = env.get_account(2);
+        let partner = env.get_account(3);
+        let user = env.get_account(4);
+        let successor = env.get_account(5);
+        let mut registry = MarketRegistry::deploy(&env, MarketRegistryInitArgs { admin });
+        env.set_caller(admin);
+        registry.register_market(asset, market, env.get_account(6), env.get_account(7));
+
+        // Only protocol admins assign codes, so a partner's code cannot be squatted.
+        env.set_caller(user);
+        let result = registry.try_register_referral_code(42, user);
+        assert!(result.is_err());
+        env.set_caller(admin);
+        assert_eq!(
+            registry.try_register_referral_code(0, partner),
+            Err(Error::InvalidParam.into())
+        );
+        registry.register_referral_code(42, partner);
+        assert_eq!(registry.get_referral_code_owner(42), partner);
+        assert!(env.emitted_event(
+            &registry,
+            ReferralCodeRegistered {
+                code: 42,
+                owner: partner,
+            }
+        ));
+        assert_eq!(
+            registry.try_register_referral_code(42, user),
+            Err(Error::ReferralCodeTaken.into())
+        );
+
+        // Only the asset's market may report volume; unknown codes are ignored.
+        let amount = U256::from(500u64);
+        let result = registry.try_record_referral(asset, 42, user, amount, false);
+        assert_eq!(result, Err(Error::Unauthorized.into()));
+        env.set_caller(market);
+        registry.record_referral(asset, 7, user, amount, false);
+        assert_eq!(registry.get_referral_volume(7, asset), ReferralVolume::default());
+        registry.record_referral(asset, 42, user, amount, false);
+        registry.record_referral(asset, 42, user, amount, false);
+        registry.record_referral(asset, 42, user, U256::from(200u64), true);
+        let volume = registry.get_referral_volume(42, asset);
+        assert_eq!(volume.supplied, U256::from(1_000u64));
+        assert_eq!(volume.borrowed, U256::from(200u64));
+        assert!(env.emitted_event(
+            &registry,
+            Referral {
+                code: 42,
+                owner: partner,
+                account: user,
+                asset,
+                amount: U256::from(200u64),
+                is_borrow: true,
+            }
+        ));
+
+        env.set_caller(user);
+        let result = registry.try_transfer_referral_code(42, user);
+        assert_eq!(result, Err(Error::Unauthorized.into()));
+        env.set_caller(partner);
+        registry.transfer_referral_code(42, successor);
+        assert_eq!(registry.get_referral_code_owner(42), successor);
     }
 }
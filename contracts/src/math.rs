@@ -1,6 +1,7 @@
 use odra::casper_types::U256;
 
 pub const WAD_U128: u128 = 1_000_000_000_000_000_000;
+pub const WAD_DECIMALS: u8 = 18;
 
 fn wad_u256() -> U256 {
     U256::from(WAD_U128)
@@ -24,6 +25,24 @@ pub fn wad_div(a: U256, b: U256) -> U256 {
         .unwrap_or_else(|| panic!("wad_div overflow"))
 }
 
+/// Scales a raw token amount with `decimals` to the 18-decimal WAD base.
+pub fn normalize_amount(amount: U256, decimals: u8) -> U256 {
+    if decimals <= WAD_DECIMALS {
+        let scale = U256::from(10u8).pow(U256::from(WAD_DECIMALS - decimals));
+        amount
+            .checked_mul(scale)
+            .unwrap_or_else(|| panic!("normalize_amount overflow"))
+    } else {
+        let scale = U256::from(10u8).pow(U256::from(decimals - WAD_DECIMALS));
+        amount / scale
+    }
+}
+
+/// Values a raw token amount at a WAD price, returning a WAD-based value.
+pub fn value_of(amount: U256, price: U256, decimals: u8) -> U256 {
+    wad_mul(normalize_amount(amount, decimals), price)
+}
+
 pub fn utilization_rate(cash: U256, borrows: U256, reserves: U256) -> U256 {
     if borrows.is_zero() {
         return U256::zero();
@@ -56,6 +75,27 @@ mod tests {
         assert_eq!(wad_div(a, b), U256::from(WAD_U128 / 2));
     }
 
+    #[test]
+    fn normalize_amount_scales_to_wad() {
+        let wad = U256::from(WAD_U128);
+        assert_eq!(normalize_amount(U256::from(1_000_000u64), 6), wad);
+        assert_eq!(normalize_amount(U256::from(1_000_000_000u64), 9), wad);
+        assert_eq!(normalize_amount(wad, 18), wad);
+        assert_eq!(normalize_amount(wad * U256::from(1_000_000u64), 24), wad);
+    }
+
+    #[test]
+    fn value_of_agrees_across_decimals() {
+        let price = U256::from(WAD_U128) * U256::from(2u8);
+        let expected = U256::from(WAD_U128) * U256::from(200u8);
+        assert_eq!(value_of(U256::from(100_000_000u64), price, 6), expected);
+        assert_eq!(value_of(U256::from(100_000_000_000u64), price, 9), expected);
+        assert_eq!(
+            value_of(U256::from(WAD_U128) * U256::from(100u8), price, 18),
+            expected
+        );
+    }
+
     #[test]
     fn utilization_zero_borrows() {
         let cash = U256::from(100u64);
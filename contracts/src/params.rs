@@ -0,0 +1,226 @@
+use odra::casper_types::U256;
+
+use crate::errors::Error;
+use crate::math::WAD_U128;
+use crate::types::{EModeCategory, RateModel, RiskParams};
+
+/// Ceiling on the utilization-independent borrow rate (~100% APR per second in WAD).
+pub const MAX_BASE_RATE_PER_SEC: u128 = WAD_U128 / 31_536_000;
+/// Ceiling on the borrow rate at full utilization (~1000% APR per second in WAD).
+pub const MAX_BORROW_RATE_PER_SEC: u128 = 10 * WAD_U128 / 31_536_000;
+
+/// Checks the risk invariants shared by `LendingMarket` and `MarketRegistry`.
+///
+/// Caps are not bounded: zero already means "no cap".
+pub fn validate_risk_params(params: &RiskParams) -> Result<(), Error> {
+    let wad = U256::from(WAD_U128);
+    validate_collateral_params(
+        params.collateral_factor,
+        params.liquidation_threshold,
+        params.liquidation_bonus,
+    )?;
+    if params.reserve_factor > wad {
+        return Err(Error::ReserveFactorTooHigh);
+    }
+    if params.close_factor.is_zero() || params.close_factor > wad {
+        return Err(Error::CloseFactorOutOfRange);
+    }
+    Ok(())
+}
+
+/// Checks an E-mode category with the same collateral rules as a market.
+pub fn validate_emode_category(category: &EModeCategory) -> Result<(), Error> {
+    if category.id == 0 {
+        return Err(Error::InvalidParam);
+    }
+    validate_collateral_params(
+        category.collateral_factor,
+        category.liquidation_threshold,
+        category.liquidation_bonus,
+    )
+}
+
+fn validate_collateral_params(
+    collateral_factor: U256,
+    liquidation_threshold: U256,
+    liquidation_bonus: U256,
+) -> Result<(), Error> {
+    let wad = U256::from(WAD_U128);
+    if liquidation_threshold >= wad {
+        return Err(Error::LiquidationThresholdTooHigh);
+    }
+    if collateral_factor > liquidation_threshold {
+        return Err(Error::CollateralFactorAboveThreshold);
+    }
+    let bonus_multiplier = wad
+        .checked_add(liquidation_bonus)
+        .ok_or(Error::LiquidationBonusTooHigh)?;
+    let max_seized = liquidation_threshold
+        .checked_mul(bonus_multiplier)
+        .ok_or(Error::LiquidationBonusTooHigh)?;
+    if max_seized >= wad * wad {
+        return Err(Error::LiquidationBonusTooHigh);
+    }
+    Ok(())
+}
+
+/// Checks a rate model against the protocol rate ceilings.
+pub fn validate_rate_model(model: &RateModel) -> Result<(), Error> {
+    if model.base_rate_per_sec > U256::from(MAX_BASE_RATE_PER_SEC) {
+        return Err(Error::BaseRateTooHigh);
+    }
+    let max_rate = model
+        .base_rate_per_sec
+        .checked_add(model.slope_rate_per_sec)
+        .ok_or(Error::BorrowRateTooHigh)?;
+    if max_rate > U256::from(MAX_BORROW_RATE_PER_SEC) {
+        return Err(Error::BorrowRateTooHigh);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use odra::prelude::OdraError;
+
+    fn check_risk(params: &RiskParams) -> Result<(), OdraError> {
+        validate_risk_params(params).map_err(Into::into)
+    }
+
+    fn check_rate(model: &RateModel) -> Result<(), OdraError> {
+        validate_rate_model(model).map_err(Into::into)
+    }
+
+    fn pct(value: u128) -> U256 {
+        U256::from(WAD_U128 * value / 100)
+    }
+
+    fn valid_params() -> RiskParams {
+        RiskParams {
+            collateral_factor: pct(75),
+            liquidation_threshold: pct(80),
+            close_factor: pct(50),
+            liquidation_bonus: pct(5),
+            reserve_factor: pct(10),
+            borrow_cap: U256::zero(),
+            supply_cap: U256::zero(),
+        }
+    }
+
+    #[test]
+    fn accepts_typical_risk_params() {
+        assert_eq!(check_risk(&valid_params()), Ok(()));
+    }
+
+    #[test]
+    fn rejects_each_risk_invariant_with_its_own_error() {
+        let mut params = valid_params();
+        params.liquidation_threshold = pct(100);
+        assert_eq!(
+            check_risk(&params),
+            Err(Error::LiquidationThresholdTooHigh.into())
+        );
+
+        let mut params = valid_params();
+        params.collateral_factor = pct(81);
+        assert_eq!(
+            check_risk(&params),
+            Err(Error::CollateralFactorAboveThreshold.into())
+        );
+
+        // 0.8 * 1.25 = 1: seizure would consume the whole position.
+        let mut params = valid_params();
+        params.liquidation_bonus = pct(25);
+        assert_eq!(
+            check_risk(&params),
+            Err(Error::LiquidationBonusTooHigh.into())
+        );
+
+        let mut params = valid_params();
+        params.reserve_factor = pct(101);
+        assert_eq!(check_risk(&params), Err(Error::ReserveFactorTooHigh.into()));
+
+        let mut params = valid_params();
+        params.close_factor = U256::zero();
+        assert_eq!(
+            check_risk(&params),
+            Err(Error::CloseFactorOutOfRange.into())
+        );
+        params.close_factor = pct(101);
+        assert_eq!(
+            check_risk(&params),
+            Err(Error::CloseFactorOutOfRange.into())
+        );
+    }
+
+    #[test]
+    fn boundary_risk_params_are_accepted() {
+        let mut params = valid_params();
+        params.collateral_factor = params.liquidation_threshold;
+        params.reserve_factor = pct(100);
+        params.close_factor = pct(100);
+        assert_eq!(check_risk(&params), Ok(()));
+    }
+
+    #[test]
+    fn rate_model_ceilings() {
+        let ok = RateModel {
+            base_rate_per_sec: U256::from(MAX_BASE_RATE_PER_SEC),
+            slope_rate_per_sec: U256::from(MAX_BORROW_RATE_PER_SEC - MAX_BASE_RATE_PER_SEC),
+        };
+        assert_eq!(check_rate(&ok), Ok(()));
+
+        let base_too_high = RateModel {
+            base_rate_per_sec: U256::from(MAX_BASE_RATE_PER_SEC + 1),
+            slope_rate_per_sec: U256::zero(),
+        };
+        assert_eq!(
+            check_rate(&base_too_high),
+            Err(Error::BaseRateTooHigh.into())
+        );
+
+        let slope_too_high = RateModel {
+            base_rate_per_sec: U256::zero(),
+            slope_rate_per_sec: U256::from(MAX_BORROW_RATE_PER_SEC + 1),
+        };
+        assert_eq!(
+            check_rate(&slope_too_high),
+            Err(Error::BorrowRateTooHigh.into())
+        );
+
+        let overflowing = RateModel {
+            base_rate_per_sec: U256::zero(),
+            slope_rate_per_sec: U256::MAX,
+        };
+        assert_eq!(
+            check_rate(&overflowing),
+            Err(Error::BorrowRateTooHigh.into())
+        );
+    }
+
+    #[test]
+    fn emode_categories_follow_collateral_rules() {
+        let mut category = EModeCategory {
+            id: 1,
+            collateral_factor: pct(90),
+            liquidation_threshold: pct(93),
+            liquidation_bonus: pct(2),
+            label: "Stablecoins".to_string(),
+        };
+        assert_eq!(validate_emode_category(&category).map_err(OdraError::from), Ok(()));
+
+        category.collateral_factor = pct(94);
+        assert_eq!(
+            validate_emode_category(&category).map_err(OdraError::from),
+            Err(Error::CollateralFactorAboveThreshold.into())
+        );
+
+        category.collateral_factor = pct(90);
+        category.id = 0;
+        assert_eq!(
+            validate_emode_category(&category).map_err(OdraError::from),
+            Err(Error::InvalidParam.into())
+        );
+    }
+}
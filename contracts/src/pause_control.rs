@@ -0,0 +1,255 @@
+use odra::prelude::*;
+
+use crate::errors::Error;
+use crate::events::{
+    GlobalPauseUpdated, GuardianPauseDurationUpdated, GuardianPauseExtended, PauseFlagsUpdated,
+};
+use crate::types::{GuardianPause, PauseFlags, PauseReason};
+
+/// Per-market and registry-wide pause state, embedded by `MarketRegistry`.
+///
+/// Invariants (MVP):
+/// - Admin flags never expire; guardian flags lapse at their `expires_at`.
+/// - Guardians can only add flags on top of the flags currently in force.
+/// - An admin update replaces both the admin flags and any guardian pause.
+/// - Role checks are left to the embedding contract.
+#[odra::module(
+    events = [
+        PauseFlagsUpdated,
+        GlobalPauseUpdated,
+        GuardianPauseExtended,
+        GuardianPauseDurationUpdated
+    ],
+    errors = Error
+)]
+pub struct PauseControl {
+    guardian_pause_duration: Var<u64>,
+    market_flags: Mapping<Address, PauseFlags>,
+    market_guardian_pauses: Mapping<Address, GuardianPause>,
+    market_reasons: Mapping<Address, PauseReason>,
+    global_flags: Var<PauseFlags>,
+    global_guardian_pause: Var<GuardianPause>,
+    global_reason: Var<PauseReason>,
+}
+
+#[odra::module]
+impl PauseControl {
+    pub fn set_guardian_pause_duration(&mut self, duration_millis: u64) {
+        self.guardian_pause_duration.set(duration_millis);
+        self.env()
+            .emit_event(GuardianPauseDurationUpdated { duration_millis });
+    }
+
+    pub fn get_guardian_pause_duration(&self) -> u64 {
+        self.guardian_pause_duration.get_or_default()
+    }
+
+    pub fn init_market(&mut self, asset: Address) {
+        self.market_flags.set(&asset, unpaused());
+    }
+
+    pub fn set_market_flags(
+        &mut self,
+        asset: Address,
+        flags: PauseFlags,
+        reason_code: u32,
+        by_admin: bool,
+    ) {
+        let in_force = self.get_market_flags(asset);
+        let guardian_pause = self.next_guardian_pause(&in_force, &flags, by_admin);
+        if by_admin {
+            self.market_flags.set(&asset, flags.clone());
+        }
+        let expires_at = guardian_pause.expires_at;
+        self.market_guardian_pauses.set(&asset, guardian_pause);
+        let timestamp = self.env().get_block_time();
+        self.market_reasons.set(
+            &asset,
+            PauseReason {
+                code: reason_code,
+                updated_at: timestamp,
+            },
+        );
+        self.env().emit_event(PauseFlagsUpdated {
+            asset,
+            supply_paused: flags.supply_paused,
+            borrow_paused: flags.borrow_paused,
+            withdraw_paused: flags.withdraw_paused,
+            repay_paused: flags.repay_paused,
+            liquidation_paused: flags.liquidation_paused,
+            reason_code,
+            timestamp,
+            expires_at,
+        });
+    }
+
+    pub fn set_global_flags(&mut self, flags: PauseFlags, reason_code: u32, by_admin: bool) {
+        let in_force = self.get_global_flags();
+        let guardian_pause = self.next_guardian_pause(&in_force, &flags, by_admin);
+        if by_admin {
+            self.global_flags.set(flags.clone());
+        }
+        let expires_at = guardian_pause.expires_at;
+        self.global_guardian_pause.set(guardian_pause);
+        let timestamp = self.env().get_block_time();
+        self.global_reason.set(PauseReason {
+            code: reason_code,
+            updated_at: timestamp,
+        });
+        self.env().emit_event(GlobalPauseUpdated {
+            supply_paused: flags.supply_paused,
+            borrow_paused: flags.borrow_paused,
+            withdraw_paused: flags.withdraw_paused,
+            repay_paused: flags.repay_paused,
+            liquidation_paused: flags.liquidation_paused,
+            reason_code,
+            timestamp,
+            expires_at,
+        });
+    }
+
+    /// Pushes the expiry of a live guardian pause on `asset` to now + `duration_millis`.
+    pub fn extend_market_guardian_pause(&mut self, asset: Address, duration_millis: u64) {
+        let mut pause = self
+            .market_guardian_pauses
+            .get(&asset)
+            .filter(|pause| self.is_in_force(pause))
+            .unwrap_or_revert_with(&self.env(), Error::NoGuardianPause);
+        pause.expires_at = self.expiry_after(duration_millis);
+        let expires_at = pause.expires_at;
+        self.market_guardian_pauses.set(&asset, pause);
+        self.env().emit_event(GuardianPauseExtended {
+            asset: Some(asset),
+            expires_at,
+        });
+    }
+
+    pub fn extend_global_guardian_pause(&mut self, duration_millis: u64) {
+        let mut pause = self
+            .global_guardian_pause
+            .get()
+            .filter(|pause| self.is_in_force(pause))
+            .unwrap_or_revert_with(&self.env(), Error::NoGuardianPause);
+        pause.expires_at = self.expiry_after(duration_millis);
+        let expires_at = pause.expires_at;
+        self.global_guardian_pause.set(pause);
+        self.env().emit_event(GuardianPauseExtended {
+            asset: None,
+            expires_at,
+        });
+    }
+
+    /// Admin flags for `asset` combined with a guardian pause that has not lapsed.
+    pub fn get_market_flags(&self, asset: Address) -> PauseFlags {
+        let flags = self
+            .market_flags
+            .get(&asset)
+            .unwrap_or_revert_with(&self.env(), Error::MarketNotFound);
+        self.with_guardian_pause(flags, self.market_guardian_pauses.get(&asset))
+    }
+
+    pub fn get_global_flags(&self) -> PauseFlags {
+        let flags = self.global_flags.get().unwrap_or_else(unpaused);
+        self.with_guardian_pause(flags, self.global_guardian_pause.get())
+    }
+
+    pub fn get_market_guardian_pause(&self, asset: Address) -> Option<GuardianPause> {
+        self.market_guardian_pauses
+            .get(&asset)
+            .filter(|pause| self.is_in_force(pause))
+    }
+
+    pub fn get_global_guardian_pause(&self) -> Option<GuardianPause> {
+        self.global_guardian_pause
+            .get()
+            .filter(|pause| self.is_in_force(pause))
+    }
+
+    pub fn get_market_reason(&self, asset: Address) -> Option<PauseReason> {
+        self.market_reasons.get(&asset)
+    }
+
+    pub fn get_global_reason(&self) -> Option<PauseReason> {
+        self.global_reason.get()
+    }
+}
+
+impl PauseControl {
+    /// Guardian pause to store after an update; admin updates clear it.
+    fn next_guardian_pause(
+        &self,
+        in_force: &PauseFlags,
+        flags: &PauseFlags,
+        by_admin: bool,
+    ) -> GuardianPause {
+        if by_admin {
+            return GuardianPause {
+                flags: unpaused(),
+                expires_at: 0,
+            };
+        }
+        if lifts_any(in_force, flags) {
+            self.env().revert(Error::Unauthorized);
+        }
+        GuardianPause {
+            flags: flags.clone(),
+            expires_at: self.expiry_after(self.guardian_pause_duration.get_or_default()),
+        }
+    }
+
+    fn with_guardian_pause(&self, flags: PauseFlags, pause: Option<GuardianPause>) -> PauseFlags {
+        match pause.filter(|pause| self.is_in_force(pause)) {
+            Some(pause) => combine(&flags, &pause.flags),
+            None => flags,
+        }
+    }
+
+    fn is_in_force(&self, pause: &GuardianPause) -> bool {
+        pause.expires_at > self.env().get_block_time()
+    }
+
+    fn expiry_after(&self, duration_millis: u64) -> u64 {
+        self.env()
+            .get_block_time()
+            .checked_add(duration_millis)
+            .unwrap_or_else(|| self.env().revert(Error::MathOverflow))
+    }
+}
+
+pub fn unpaused() -> PauseFlags {
+    PauseFlags {
+        supply_paused: false,
+        borrow_paused: false,
+        withdraw_paused: false,
+        repay_paused: false,
+        liquidation_paused: false,
+    }
+}
+
+pub fn all_paused() -> PauseFlags {
+    PauseFlags {
+        supply_paused: true,
+        borrow_paused: true,
+        withdraw_paused: true,
+        repay_paused: true,
+        liquidation_paused: true,
+    }
+}
+
+pub fn combine(a: &PauseFlags, b: &PauseFlags) -> PauseFlags {
+    PauseFlags {
+        supply_paused: a.supply_paused || b.supply_paused,
+        borrow_paused: a.borrow_paused || b.borrow_paused,
+        withdraw_paused: a.withdraw_paused || b.withdraw_paused,
+        repay_paused: a.repay_paused || b.repay_paused,
+        liquidation_paused: a.liquidation_paused || b.liquidation_paused,
+    }
+}
+
+fn lifts_any(current: &PauseFlags, next: &PauseFlags) -> bool {
+    (current.supply_paused && !next.supply_paused)
+        || (current.borrow_paused && !next.borrow_paused)
+        || (current.withdraw_paused && !next.withdraw_paused)
+        || (current.repay_paused && !next.repay_paused)
+        || (current.liquidation_paused && !next.liquidation_paused)
+}
@@ -1,31 +1,50 @@
 use odra::casper_types::U256;
 use odra::prelude::*;
-use odra_modules::access::{AccessControl, Role, DEFAULT_ADMIN_ROLE};
+use odra::ContractRef;
+use odra_modules::access::{Role, DEFAULT_ADMIN_ROLE};
 
+use crate::admin_access::AdminAccess;
+use crate::cep18_interface::Cep18TokenContractRef;
 use crate::errors::Error;
-use crate::events::PriceUpdated;
+use crate::events::{AssetMaxStaleUpdated, AssetRegistered, PriceRecovered, PriceUpdated};
 use crate::types::OraclePrice;
 
 const ORACLE_ADMIN_ROLE: Role = *b"ORACLE_ADMIN_ROLE_______________";
+const MAX_PRICE_BATCH: usize = 32;
 
 /// Centralized price oracle with staleness protection.
 ///
 /// Invariants (MVP):
 /// - Only ORACLE_ADMIN_ROLE can update prices.
+/// - Batched updates share one timestamp and apply all-or-nothing.
 /// - `get_price_checked` reverts when price is stale or missing.
-#[odra::module(events = [PriceUpdated], errors = Error)]
+/// - Staleness uses the asset's `max_stale_millis` override when set (non-zero),
+///   otherwise the global value.
+/// - Every asset that ever received a price is tracked for `get_stale_assets`.
+/// - Asset decimals are read from the CEP-18 on `register_asset`, so consumers
+///   can normalise raw token amounts against WAD prices.
+/// - An update that replaces a stale price records the recovery time.
+#[odra::module(
+    events = [PriceUpdated, AssetMaxStaleUpdated, AssetRegistered, PriceRecovered],
+    errors = Error
+)]
 pub struct PriceOracle {
-    access_control: SubModule<AccessControl>,
+    access_control: SubModule<AdminAccess>,
     max_stale_millis: Var<u64>,
     prices: Mapping<Address, OraclePrice>,
+    asset_max_stale_millis: Mapping<Address, u64>,
+    asset_count: Var<u64>,
+    asset_by_index: Mapping<u64, Address>,
+    asset_tracked: Mapping<Address, bool>,
+    asset_decimals: Mapping<Address, u8>,
+    recovered_at: Mapping<Address, u64>,
 }
 
 #[odra::module]
 impl PriceOracle {
     pub fn init(&mut self, admin: Address, max_stale_millis: u64) {
         self.max_stale_millis.set(max_stale_millis);
-        self.access_control
-            .unchecked_grant_role(&DEFAULT_ADMIN_ROLE, &admin);
+        self.access_control.init_admin(admin);
         self.access_control
             .unchecked_grant_role(&ORACLE_ADMIN_ROLE, &admin);
         self.access_control
@@ -35,16 +54,32 @@ impl PriceOracle {
     pub fn set_price(&mut self, asset: Address, price: U256) {
         self.ensure_oracle_admin();
         let ts = self.env().get_block_time();
-        let data = OraclePrice {
-            price,
-            last_updated: ts,
-        };
-        self.prices.set(&asset, data);
-        self.env().emit_event(PriceUpdated {
-            asset,
-            price,
-            timestamp: ts,
-        });
+        self.write_price(asset, price, ts);
+    }
+
+    pub fn set_prices(&mut self, prices: Vec<(Address, U256)>) {
+        self.ensure_oracle_admin();
+        if prices.is_empty() || prices.len() > MAX_PRICE_BATCH {
+            self.env().revert(Error::InvalidParam);
+        }
+        let ts = self.env().get_block_time();
+        for (asset, price) in prices {
+            self.write_price(asset, price, ts);
+        }
+    }
+
+    /// Records the asset's token decimals, read from its CEP-18 contract.
+    pub fn register_asset(&mut self, asset: Address) {
+        self.ensure_oracle_admin();
+        let decimals = Cep18TokenContractRef::new(self.env(), asset).decimals();
+        self.asset_decimals.set(&asset, decimals);
+        self.env().emit_event(AssetRegistered { asset, decimals });
+    }
+
+    pub fn get_asset_decimals(&self, asset: Address) -> u8 {
+        self.asset_decimals
+            .get(&asset)
+            .unwrap_or_revert_with(&self.env(), Error::AssetNotRegistered)
     }
 
     pub fn get_price(&self, asset: Address) -> OraclePrice {
@@ -55,7 +90,9 @@ impl PriceOracle {
 
     pub fn get_price_checked(&self, asset: Address) -> U256 {
         let data = self.get_price(asset);
-        self.ensure_fresh(&data);
+        if self.is_stale(asset, &data) {
+            self.env().revert(Error::PriceStale);
+        }
         data.price
     }
 
@@ -64,6 +101,63 @@ impl PriceOracle {
         self.max_stale_millis.set(max_stale_millis);
     }
 
+    /// Sets a per-asset heartbeat. Passing zero clears the override so the
+    /// global `max_stale_millis` applies again.
+    pub fn set_asset_max_stale_millis(&mut self, asset: Address, max_stale_millis: u64) {
+        self.ensure_oracle_admin();
+        self.asset_max_stale_millis.set(&asset, max_stale_millis);
+        self.env().emit_event(AssetMaxStaleUpdated {
+            asset,
+            max_stale_millis,
+        });
+    }
+
+    pub fn get_asset_max_stale_millis(&self, asset: Address) -> u64 {
+        let max_stale = self.asset_max_stale_millis.get_or_default(&asset);
+        if max_stale == 0 {
+            return self.max_stale_millis.get_or_default();
+        }
+        max_stale
+    }
+
+    /// Time at which the asset's feed last came back from staleness, or zero.
+    pub fn get_recovered_at(&self, asset: Address) -> u64 {
+        self.recovered_at.get_or_default(&asset)
+    }
+
+    pub fn get_tracked_asset_count(&self) -> u64 {
+        self.asset_count.get_or_default()
+    }
+
+    pub fn get_tracked_asset_by_index(&self, index: u64) -> Address {
+        self.asset_by_index
+            .get(&index)
+            .unwrap_or_revert_with(&self.env(), Error::PriceMissing)
+    }
+
+    /// Returns every tracked asset whose last update is older than its heartbeat.
+    pub fn get_stale_assets(&self) -> Vec<Address> {
+        let mut stale = Vec::new();
+        for index in 0..self.asset_count.get_or_default() {
+            let asset = self.get_tracked_asset_by_index(index);
+            let data = self.get_price(asset);
+            if self.is_stale(asset, &data) {
+                stale.push(asset);
+            }
+        }
+        stale
+    }
+
+    delegate! {
+        to self.access_control {
+            fn propose_admin(&mut self, pending_admin: Address);
+            fn cancel_admin_transfer(&mut self);
+            fn accept_admin(&mut self);
+            fn get_admin(&self) -> Address;
+            fn get_pending_admin(&self) -> Option<Address>;
+        }
+    }
+
     pub fn grant_oracle_admin(&mut self, address: Address) {
         self.access_control
             .grant_role(&ORACLE_ADMIN_ROLE, &address);
@@ -89,18 +183,45 @@ impl PriceOracle {
             .check_role(&ORACLE_ADMIN_ROLE, &self.env().caller());
     }
 
-    fn ensure_fresh(&self, data: &OraclePrice) {
-        let now = self.env().get_block_time();
-        let max_stale = self.max_stale_millis.get_or_default();
-        if now.saturating_sub(data.last_updated) > max_stale {
-            self.env().revert(Error::PriceStale);
+    fn write_price(&mut self, asset: Address, price: U256, ts: u64) {
+        if !self.asset_tracked.get_or_default(&asset) {
+            let index = self.asset_count.get_or_default();
+            self.asset_by_index.set(&index, asset);
+            self.asset_count.set(index + 1);
+            self.asset_tracked.set(&asset, true);
         }
+        if let Some(previous) = self.prices.get(&asset) {
+            if self.is_stale(asset, &previous) {
+                self.recovered_at.set(&asset, ts);
+                self.env().emit_event(PriceRecovered {
+                    asset,
+                    timestamp: ts,
+                });
+            }
+        }
+        let data = OraclePrice {
+            price,
+            last_updated: ts,
+        };
+        self.prices.set(&asset, data);
+        self.env().emit_event(PriceUpdated {
+            asset,
+            price,
+            timestamp: ts,
+        });
+    }
+
+    fn is_stale(&self, asset: Address, data: &OraclePrice) -> bool {
+        let now = self.env().get_block_time();
+        let max_stale = self.get_asset_max_stale_millis(asset);
+        now.saturating_sub(data.last_updated) > max_stale
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::a_token::{AToken, ATokenInitArgs};
     use crate::errors::Error;
     use odra::host::Deployer;
 
@@ -138,6 +259,219 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn admin_can_set_prices_in_batch() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let asset_a = env.get_account(1);
+        let asset_b = env.get_account(2);
+        let init_args = PriceOracleInitArgs {
+            admin,
+            max_stale_millis: 10_000u64,
+        };
+        let mut oracle = PriceOracle::deploy(&env, init_args);
+
+        env.set_caller(admin);
+        oracle.set_prices(vec![
+            (asset_a, U256::from(5u64)),
+            (asset_b, U256::from(7u64)),
+        ]);
+
+        let data_a = oracle.get_price(asset_a);
+        let data_b = oracle.get_price(asset_b);
+        assert_eq!(data_a.price, U256::from(5u64));
+        assert_eq!(data_b.price, U256::from(7u64));
+        assert_eq!(data_a.last_updated, data_b.last_updated);
+        assert!(env.emitted_event(
+            &oracle.address(),
+            PriceUpdated {
+                asset: asset_b,
+                price: U256::from(7u64),
+                timestamp: data_b.last_updated,
+            }
+        ));
+    }
+
+    #[test]
+    fn set_prices_rejects_empty_and_oversized_batches() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let asset = env.get_account(1);
+        let init_args = PriceOracleInitArgs {
+            admin,
+            max_stale_millis: 10_000u64,
+        };
+        let mut oracle = PriceOracle::deploy(&env, init_args);
+
+        env.set_caller(admin);
+        let empty = oracle.try_set_prices(vec![]);
+        assert_eq!(empty, Err(Error::InvalidParam.into()));
+
+        let batch = vec![(asset, U256::from(1u64)); MAX_PRICE_BATCH + 1];
+        let oversized = oracle.try_set_prices(batch);
+        assert_eq!(oversized, Err(Error::InvalidParam.into()));
+        assert_eq!(oracle.try_get_price(asset), Err(Error::PriceMissing.into()));
+    }
+
+    #[test]
+    fn non_admin_cannot_set_prices() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let asset = env.get_account(1);
+        let init_args = PriceOracleInitArgs {
+            admin,
+            max_stale_millis: 10_000u64,
+        };
+        let mut oracle = PriceOracle::deploy(&env, init_args);
+
+        env.set_caller(env.get_account(2));
+        let result = oracle.try_set_prices(vec![(asset, U256::from(1u64))]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn asset_override_replaces_global_staleness() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let stable = env.get_account(1);
+        let volatile = env.get_account(2);
+        let init_args = PriceOracleInitArgs {
+            admin,
+            max_stale_millis: 1_000u64,
+        };
+        let mut oracle = PriceOracle::deploy(&env, init_args);
+
+        env.set_caller(admin);
+        oracle.set_asset_max_stale_millis(stable, 5_000u64);
+        oracle.set_prices(vec![
+            (stable, U256::from(1u64)),
+            (volatile, U256::from(2u64)),
+        ]);
+        assert_eq!(oracle.get_asset_max_stale_millis(stable), 5_000u64);
+        assert_eq!(oracle.get_asset_max_stale_millis(volatile), 1_000u64);
+
+        env.advance_block_time(2_000u64);
+        assert_eq!(oracle.get_price_checked(stable), U256::from(1u64));
+        let result = oracle.try_get_price_checked(volatile);
+        assert_eq!(result, Err(Error::PriceStale.into()));
+
+        oracle.set_asset_max_stale_millis(stable, 0u64);
+        assert_eq!(oracle.get_asset_max_stale_millis(stable), 1_000u64);
+        let result = oracle.try_get_price_checked(stable);
+        assert_eq!(result, Err(Error::PriceStale.into()));
+    }
+
+    #[test]
+    fn stale_assets_listed_by_heartbeat() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let stable = env.get_account(1);
+        let volatile = env.get_account(2);
+        let init_args = PriceOracleInitArgs {
+            admin,
+            max_stale_millis: 1_000u64,
+        };
+        let mut oracle = PriceOracle::deploy(&env, init_args);
+
+        env.set_caller(admin);
+        oracle.set_asset_max_stale_millis(stable, 5_000u64);
+        oracle.set_price(stable, U256::from(1u64));
+        oracle.set_price(volatile, U256::from(2u64));
+        oracle.set_price(volatile, U256::from(3u64));
+        assert_eq!(oracle.get_tracked_asset_count(), 2);
+        assert!(oracle.get_stale_assets().is_empty());
+
+        env.advance_block_time(2_000u64);
+        assert_eq!(oracle.get_stale_assets(), vec![volatile]);
+
+        env.advance_block_time(4_000u64);
+        assert_eq!(oracle.get_stale_assets(), vec![stable, volatile]);
+    }
+
+    #[test]
+    fn non_admin_cannot_set_asset_max_stale() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let asset = env.get_account(1);
+        let init_args = PriceOracleInitArgs {
+            admin,
+            max_stale_millis: 1_000u64,
+        };
+        let mut oracle = PriceOracle::deploy(&env, init_args);
+
+        env.set_caller(env.get_account(2));
+        let result = oracle.try_set_asset_max_stale_millis(asset, 5_000u64);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn register_asset_reads_token_decimals() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let init_args = PriceOracleInitArgs {
+            admin,
+            max_stale_millis: 1_000u64,
+        };
+        let mut oracle = PriceOracle::deploy(&env, init_args);
+        let token = AToken::deploy(
+            &env,
+            ATokenInitArgs {
+                name: "USD Coin".to_string(),
+                symbol: "USDC".to_string(),
+                decimals: 6,
+                market: admin,
+            },
+        );
+
+        let result = oracle.try_get_asset_decimals(token.address());
+        assert_eq!(result, Err(Error::AssetNotRegistered.into()));
+
+        env.set_caller(admin);
+        oracle.register_asset(token.address());
+        assert_eq!(oracle.get_asset_decimals(token.address()), 6);
+        assert!(env.emitted_event(
+            &oracle.address(),
+            AssetRegistered {
+                asset: token.address(),
+                decimals: 6,
+            }
+        ));
+
+        env.set_caller(env.get_account(1));
+        let result = oracle.try_register_asset(token.address());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn recovery_from_staleness_recorded() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let asset = env.get_account(1);
+        let init_args = PriceOracleInitArgs {
+            admin,
+            max_stale_millis: 1_000u64,
+        };
+        let mut oracle = PriceOracle::deploy(&env, init_args);
+
+        env.set_caller(admin);
+        oracle.set_price(asset, U256::from(10u64));
+        env.advance_block_time(500u64);
+        oracle.set_price(asset, U256::from(11u64));
+        assert_eq!(oracle.get_recovered_at(asset), 0u64);
+
+        env.advance_block_time(1_500u64);
+        oracle.set_price(asset, U256::from(12u64));
+        let recovered_at = oracle.get_price(asset).last_updated;
+        assert_eq!(oracle.get_recovered_at(asset), recovered_at);
+        assert!(env.emitted_event(
+            &oracle.address(),
+            PriceRecovered {
+                asset,
+                timestamp: recovered_at,
+            }
+        ));
+    }
+
     #[test]
     fn stale_price_rejected() {
         let env = odra_test::env();
@@ -155,4 +489,32 @@ mod tests {
         let result = oracle.try_get_price_checked(asset);
         assert_eq!(result, Err(Error::PriceStale.into()));
     }
+
+    #[test]
+    fn admin_handover_can_be_cancelled_before_acceptance() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let typo = env.get_account(8);
+        let multisig = env.get_account(7);
+        let init_args = PriceOracleInitArgs {
+            admin,
+            max_stale_millis: 10_000u64,
+        };
+        let mut oracle = PriceOracle::deploy(&env, init_args);
+
+        env.set_caller(admin);
+        oracle.propose_admin(typo);
+        oracle.cancel_admin_transfer();
+        env.set_caller(typo);
+        assert_eq!(oracle.try_accept_admin(), Err(Error::NoPendingAdmin.into()));
+
+        env.set_caller(admin);
+        oracle.propose_admin(multisig);
+        env.set_caller(multisig);
+        oracle.accept_admin();
+        oracle.grant_oracle_admin(multisig);
+        assert!(oracle.has_oracle_admin(multisig));
+        assert_eq!(oracle.get_admin(), multisig);
+    }
+
 }
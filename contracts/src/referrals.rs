@@ -0,0 +1,95 @@
+use odra::casper_types::U256;
+use odra::prelude::*;
+
+use crate::errors::Error;
+use crate::events::{Referral, ReferralCodeRegistered, ReferralCodeTransferred};
+use crate::types::ReferralVolume;
+
+/// Referral code owners and the volume referred under each code, embedded by
+/// `MarketRegistry`.
+///
+/// Invariants (MVP):
+/// - A code is registered once; code 0 means "no referral".
+/// - Volume is cumulative per code and asset and never decreases.
+/// - Unknown codes are ignored when recording, so a mistyped code never blocks
+///   the deposit or borrow it came with.
+/// - Checking who may register codes, and that the caller recording volume is
+///   a registered market, is left to the embedding contract.
+#[odra::module(
+    events = [ReferralCodeRegistered, ReferralCodeTransferred, Referral],
+    errors = Error
+)]
+pub struct Referrals {
+    owners: Mapping<u32, Address>,
+    volumes: Mapping<(u32, Address), ReferralVolume>,
+}
+
+#[odra::module]
+impl Referrals {
+    pub fn register(&mut self, code: u32, owner: Address) {
+        if code == 0 {
+            self.env().revert(Error::InvalidParam);
+        }
+        if self.owners.get(&code).is_some() {
+            self.env().revert(Error::ReferralCodeTaken);
+        }
+        self.owners.set(&code, owner);
+        self.env()
+            .emit_event(ReferralCodeRegistered { code, owner });
+    }
+
+    /// Hands `code` to `new_owner`; `caller` must be its current owner.
+    pub fn transfer(&mut self, code: u32, caller: Address, new_owner: Address) {
+        let previous_owner = self.get_owner(code);
+        if caller != previous_owner {
+            self.env().revert(Error::Unauthorized);
+        }
+        self.owners.set(&code, new_owner);
+        self.env().emit_event(ReferralCodeTransferred {
+            code,
+            previous_owner,
+            new_owner,
+        });
+    }
+
+    pub fn record(
+        &mut self,
+        asset: Address,
+        code: u32,
+        account: Address,
+        amount: U256,
+        is_borrow: bool,
+    ) {
+        let Some(owner) = self.owners.get(&code) else {
+            return;
+        };
+        let mut volume = self.get_volume(code, asset);
+        let total = if is_borrow {
+            &mut volume.borrowed
+        } else {
+            &mut volume.supplied
+        };
+        *total = total
+            .checked_add(amount)
+            .unwrap_or_else(|| self.env().revert(Error::MathOverflow));
+        self.volumes.set(&(code, asset), volume);
+        self.env().emit_event(Referral {
+            code,
+            owner,
+            account,
+            asset,
+            amount,
+            is_borrow,
+        });
+    }
+
+    pub fn get_owner(&self, code: u32) -> Address {
+        self.owners
+            .get(&code)
+            .unwrap_or_revert_with(&self.env(), Error::UnknownReferralCode)
+    }
+
+    pub fn get_volume(&self, code: u32, asset: Address) -> ReferralVolume {
+        self.volumes.get_or_default(&(code, asset))
+    }
+}
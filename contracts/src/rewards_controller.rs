@@ -0,0 +1,460 @@
+use odra::casper_types::U256;
+use odra::prelude::*;
+use odra::ContractRef;
+use odra_modules::access::{Role, DEFAULT_ADMIN_ROLE};
+
+use crate::a_token::ATokenContractRef;
+use crate::admin_access::AdminAccess;
+use crate::cep18_interface::Cep18TokenContractRef;
+use crate::errors::Error;
+use crate::events::{RewardEmissionsUpdated, RewardsClaimed, RewardsFunded};
+use crate::lending_market::LendingMarketContractRef;
+use crate::math::WAD_U128;
+use crate::types::{RewardEmission, RewardIndex, Timestamp};
+
+const REWARDS_ADMIN_ROLE: Role = *b"REWARDS_ADMIN_ROLE______________";
+const MAX_CLAIM_MARKETS: usize = 20;
+
+#[derive(Clone, Copy)]
+enum Side {
+    Supply,
+    Borrow,
+}
+
+/// Liquidity-mining emissions for suppliers and borrowers of each market.
+///
+/// Invariants (MVP):
+/// - Each market emits `supply_per_sec` to aToken holders and `borrow_per_sec`
+///   to borrowers, pro rata to balance.
+/// - Per side, a reward index grows by `per_sec * elapsed / total`; an account
+///   earns `balance * (index - its index)` before its balance changes.
+/// - The aToken reports supply changes and the market reports borrow changes,
+///   both with the values before the change; other callers are ignored.
+/// - Borrow shares use the account's debt over the market's total debt.
+/// - Time with an empty side emits nothing; those rewards stay unallocated.
+/// - Claims are paid from the reward token balance the admin funded.
+#[odra::module(
+    events = [RewardEmissionsUpdated, RewardsFunded, RewardsClaimed],
+    errors = Error
+)]
+pub struct RewardsController {
+    access_control: SubModule<AdminAccess>,
+    reward_token: Var<Address>,
+    emissions: Mapping<Address, RewardEmission>,
+    market_by_a_token: Mapping<Address, Address>,
+    supply_indexes: Mapping<Address, RewardIndex>,
+    borrow_indexes: Mapping<Address, RewardIndex>,
+    supplier_indexes: Mapping<(Address, Address), U256>,
+    borrower_indexes: Mapping<(Address, Address), U256>,
+    accrued: Mapping<Address, U256>,
+}
+
+#[odra::module]
+impl RewardsController {
+    pub fn init(&mut self, admin: Address, reward_token: Address) {
+        self.access_control.init_admin(admin);
+        self.access_control
+            .unchecked_grant_role(&REWARDS_ADMIN_ROLE, &admin);
+        self.access_control
+            .set_admin_role(&REWARDS_ADMIN_ROLE, &DEFAULT_ADMIN_ROLE);
+        self.reward_token.set(reward_token);
+    }
+
+    /// Sets `market`'s emission rates; accrual up to now uses the old rates.
+    ///
+    /// The market must also be pointed at this controller
+    /// (`LendingMarket::set_rewards_controller`) for balance changes to count.
+    pub fn configure_market(
+        &mut self,
+        market: Address,
+        supply_per_sec: U256,
+        borrow_per_sec: U256,
+    ) {
+        self.ensure_rewards_admin();
+        let market_ref = LendingMarketContractRef::new(self.env(), market);
+        let a_token = market_ref.get_a_token();
+        let supply = ATokenContractRef::new(self.env(), a_token).total_supply();
+        self.update_index(Side::Supply, market, supply);
+        self.update_index(Side::Borrow, market, market_ref.get_total_borrows());
+        self.market_by_a_token.set(&a_token, market);
+        self.emissions.set(
+            &market,
+            RewardEmission {
+                supply_per_sec,
+                borrow_per_sec,
+            },
+        );
+        self.env().emit_event(RewardEmissionsUpdated {
+            market,
+            supply_per_sec,
+            borrow_per_sec,
+        });
+    }
+
+    /// Pulls `amount` of the reward token from the caller into the controller.
+    pub fn fund_rewards(&mut self, amount: U256) {
+        self.ensure_rewards_admin();
+        if amount.is_zero() {
+            self.env().revert(Error::InvalidParam);
+        }
+        let funder = self.env().caller();
+        let controller = self.env().self_address();
+        self.load_reward_token()
+            .transfer_from(&funder, &controller, &amount);
+        self.env().emit_event(RewardsFunded { funder, amount });
+    }
+
+    /// Called by an aToken before `account`'s balance changes.
+    pub fn handle_supply_action(&mut self, account: Address, balance: U256, total_supply: U256) {
+        let Some(market) = self.market_by_a_token.get(&self.env().caller()) else {
+            return;
+        };
+        self.update_account(Side::Supply, market, account, balance, total_supply);
+    }
+
+    /// Called by a market before `account`'s borrow balance changes.
+    pub fn handle_borrow_action(
+        &mut self,
+        account: Address,
+        borrow_balance: U256,
+        total_borrows: U256,
+    ) {
+        let market = self.env().caller();
+        if self.emissions.get(&market).is_none() {
+            return;
+        }
+        self.update_account(Side::Borrow, market, account, borrow_balance, total_borrows);
+    }
+
+    /// Accrues the caller's rewards in `markets` and pays out everything owed.
+    pub fn claim_rewards(&mut self, markets: Vec<Address>) -> U256 {
+        if markets.len() > MAX_CLAIM_MARKETS {
+            self.env().revert(Error::InvalidParam);
+        }
+        let account = self.env().caller();
+        for market in markets {
+            self.accrue_market(account, market);
+        }
+        let amount = self.accrued.get_or_default(&account);
+        if amount.is_zero() {
+            return amount;
+        }
+        let mut reward_token = self.load_reward_token();
+        if reward_token.balance_of(&self.env().self_address()) < amount {
+            self.env().revert(Error::RewardsUnderfunded);
+        }
+        self.accrued.set(&account, U256::zero());
+        reward_token.transfer(&account, &amount);
+        self.env().emit_event(RewardsClaimed { account, amount });
+        amount
+    }
+
+    /// Rewards `account` could claim from `markets` right now.
+    pub fn get_pending_rewards(&self, account: Address, markets: Vec<Address>) -> U256 {
+        let now = self.env().get_block_time();
+        let mut pending = self.accrued.get_or_default(&account);
+        for market in markets {
+            if self.emissions.get(&market).is_none() {
+                continue;
+            }
+            let market_ref = LendingMarketContractRef::new(self.env(), market);
+            let a_token = ATokenContractRef::new(self.env(), market_ref.get_a_token());
+            pending += self.earned(
+                Side::Supply,
+                market,
+                account,
+                a_token.balance_of(&account),
+                a_token.total_supply(),
+                now,
+            );
+            pending += self.earned(
+                Side::Borrow,
+                market,
+                account,
+                market_ref.get_borrow_balance(account),
+                market_ref.get_total_borrows(),
+                now,
+            );
+        }
+        pending
+    }
+
+    pub fn get_accrued_rewards(&self, account: Address) -> U256 {
+        self.accrued.get_or_default(&account)
+    }
+
+    pub fn get_emission(&self, market: Address) -> RewardEmission {
+        self.emissions.get_or_default(&market)
+    }
+
+    pub fn get_supply_index(&self, market: Address) -> RewardIndex {
+        self.supply_indexes.get_or_default(&market)
+    }
+
+    pub fn get_borrow_index(&self, market: Address) -> RewardIndex {
+        self.borrow_indexes.get_or_default(&market)
+    }
+
+    pub fn get_reward_token(&self) -> Address {
+        self.reward_token
+            .get()
+            .unwrap_or_revert_with(&self.env(), Error::InvalidParam)
+    }
+
+    delegate! {
+        to self.access_control {
+            fn propose_admin(&mut self, pending_admin: Address);
+            fn cancel_admin_transfer(&mut self);
+            fn accept_admin(&mut self);
+            fn get_admin(&self) -> Address;
+            fn get_pending_admin(&self) -> Option<Address>;
+        }
+    }
+
+    pub fn grant_rewards_admin(&mut self, address: Address) {
+        self.access_control
+            .grant_role(&REWARDS_ADMIN_ROLE, &address);
+    }
+
+    pub fn revoke_rewards_admin(&mut self, address: Address) {
+        self.access_control
+            .revoke_role(&REWARDS_ADMIN_ROLE, &address);
+    }
+
+    pub fn has_rewards_admin(&self, address: Address) -> bool {
+        self.access_control.has_role(&REWARDS_ADMIN_ROLE, &address)
+    }
+}
+
+impl RewardsController {
+    fn ensure_rewards_admin(&self) {
+        self.access_control
+            .check_role(&REWARDS_ADMIN_ROLE, &self.env().caller());
+    }
+
+    fn load_reward_token(&self) -> Cep18TokenContractRef {
+        Cep18TokenContractRef::new(self.env(), self.get_reward_token())
+    }
+
+    fn accrue_market(&mut self, account: Address, market: Address) {
+        if self.emissions.get(&market).is_none() {
+            self.env().revert(Error::MarketNotFound);
+        }
+        let market_ref = LendingMarketContractRef::new(self.env(), market);
+        let a_token = ATokenContractRef::new(self.env(), market_ref.get_a_token());
+        self.update_account(
+            Side::Supply,
+            market,
+            account,
+            a_token.balance_of(&account),
+            a_token.total_supply(),
+        );
+        self.update_account(
+            Side::Borrow,
+            market,
+            account,
+            market_ref.get_borrow_balance(account),
+            market_ref.get_total_borrows(),
+        );
+    }
+
+    fn update_account(
+        &mut self,
+        side: Side,
+        market: Address,
+        account: Address,
+        balance: U256,
+        total: U256,
+    ) {
+        let now = self.env().get_block_time();
+        let earned = self.earned(side, market, account, balance, total, now);
+        let index = self.update_index(side, market, total);
+        if !earned.is_zero() {
+            self.accrued
+                .set(&account, self.accrued.get_or_default(&account) + earned);
+        }
+        match side {
+            Side::Supply => self.supplier_indexes.set(&(market, account), index),
+            Side::Borrow => self.borrower_indexes.set(&(market, account), index),
+        }
+    }
+
+    fn update_index(&mut self, side: Side, market: Address, total: U256) -> U256 {
+        let now = self.env().get_block_time();
+        let index = self.index_at(side, market, total, now);
+        let state = RewardIndex {
+            index,
+            last_updated: now,
+        };
+        match side {
+            Side::Supply => self.supply_indexes.set(&market, state),
+            Side::Borrow => self.borrow_indexes.set(&market, state),
+        }
+        index
+    }
+
+    fn earned(
+        &self,
+        side: Side,
+        market: Address,
+        account: Address,
+        balance: U256,
+        total: U256,
+        now: Timestamp,
+    ) -> U256 {
+        let account_index = match side {
+            Side::Supply => self.supplier_indexes.get_or_default(&(market, account)),
+            Side::Borrow => self.borrower_indexes.get_or_default(&(market, account)),
+        };
+        let index = self.index_at(side, market, total, now);
+        balance * (index - account_index) / U256::from(WAD_U128)
+    }
+
+    fn index_at(&self, side: Side, market: Address, total: U256, now: Timestamp) -> U256 {
+        let emission = self.emissions.get_or_default(&market);
+        let (state, per_sec) = match side {
+            Side::Supply => (
+                self.supply_indexes.get_or_default(&market),
+                emission.supply_per_sec,
+            ),
+            Side::Borrow => (
+                self.borrow_indexes.get_or_default(&market),
+                emission.borrow_per_sec,
+            ),
+        };
+        if total.is_zero() || per_sec.is_zero() || now <= state.last_updated {
+            return state.index;
+        }
+        let elapsed_millis = U256::from(now - state.last_updated);
+        let growth = per_sec * elapsed_millis * U256::from(WAD_U128)
+            / (total * U256::from(1_000u32));
+        state.index + growth
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::a_token::{AToken, ATokenInitArgs};
+    use crate::lending_market::{LendingMarket, LendingMarketInitArgs};
+    use crate::market_registry::{MarketRegistry, MarketRegistryInitArgs};
+    use crate::price_oracle::{PriceOracle, PriceOracleInitArgs};
+    use crate::test_token::{TestToken, TestTokenHostRef, TestTokenInitArgs};
+    use crate::types::{MarketConfig, RateModel, RiskParams};
+    use odra::host::Deployer;
+
+    fn deploy_test_token(env: &odra::host::HostEnv, symbol: &str) -> TestTokenHostRef {
+        let init_args = TestTokenInitArgs {
+            name: symbol.to_string(),
+            symbol: symbol.to_string(),
+            decimals: 9,
+        };
+        TestToken::deploy(env, init_args)
+    }
+
+    #[test]
+    fn suppliers_and_borrowers_claim_their_share() {
+        let env = odra_test::env();
+        let admin = env.get_account(0);
+        let alice = env.get_account(1);
+        let bob = env.get_account(2);
+        let mut underlying = deploy_test_token(&env, "uTKN");
+        let mut reward_token = deploy_test_token(&env, "RWD");
+        let asset = underlying.address();
+
+        let oracle_init = PriceOracleInitArgs {
+            admin,
+            max_stale_millis: 10_000_000u64,
+        };
+        let mut oracle = PriceOracle::deploy(&env, oracle_init);
+        oracle.register_asset(asset);
+        oracle.set_price(asset, U256::from(WAD_U128));
+        let mut registry = MarketRegistry::deploy(&env, MarketRegistryInitArgs { admin });
+        let market_init = LendingMarketInitArgs {
+            config: MarketConfig {
+                admin,
+                asset,
+                a_token: admin,
+                oracle: oracle.address(),
+                registry: registry.address(),
+                rate_model: RateModel::default(),
+                risk_params: RiskParams {
+                    collateral_factor: U256::from(WAD_U128 * 75 / 100),
+                    liquidation_threshold: U256::from(WAD_U128 * 80 / 100),
+                    close_factor: U256::from(WAD_U128 / 2),
+                    ..Default::default()
+                },
+            },
+        };
+        let mut market = LendingMarket::deploy(&env, market_init);
+        let token_init = ATokenInitArgs {
+            name: "Anchor Token".to_string(),
+            symbol: "aTKN".to_string(),
+            decimals: 9,
+            market: market.address(),
+        };
+        let a_token = AToken::deploy(&env, token_init);
+        market.set_a_token(a_token.address());
+        registry.register_market(asset, market.address(), a_token.address(), oracle.address());
+
+        let controller_init = RewardsControllerInitArgs {
+            admin,
+            reward_token: reward_token.address(),
+        };
+        let mut controller = RewardsController::deploy(&env, controller_init);
+        env.set_caller(alice);
+        let result =
+            controller.try_configure_market(market.address(), U256::from(10u64), U256::zero());
+        assert!(result.is_err());
+        env.set_caller(admin);
+        controller.configure_market(market.address(), U256::from(10u64), U256::from(4u64));
+        market.set_rewards_controller(controller.address());
+        assert_eq!(
+            a_token.get_rewards_controller(),
+            Some(controller.address())
+        );
+        reward_token.mint(admin, U256::from(3_000u64));
+        reward_token.approve(&controller.address(), &U256::from(3_000u64));
+        controller.fund_rewards(U256::from(3_000u64));
+
+        for account in [alice, bob] {
+            env.set_caller(admin);
+            underlying.mint(account, U256::from(1_000u64));
+            env.set_caller(account);
+            underlying.approve(&market.address(), &U256::from(1_000u64));
+        }
+        env.set_caller(alice);
+        market.deposit(U256::from(1_000u64));
+        env.advance_block_time(100_000);
+        env.set_caller(bob);
+        market.deposit(U256::from(1_000u64));
+        env.advance_block_time(100_000);
+        market.borrow(U256::from(500u64));
+        env.advance_block_time(100_000);
+
+        // Alice: 100s alone + 200s at half of 10/s. Bob: 200s at half + 100s of 4/s.
+        let markets = vec![market.address()];
+        assert_eq!(
+            controller.get_pending_rewards(alice, markets.clone()),
+            U256::from(2_000u64)
+        );
+        assert_eq!(
+            controller.get_pending_rewards(bob, markets.clone()),
+            U256::from(1_400u64)
+        );
+
+        env.set_caller(alice);
+        assert_eq!(controller.claim_rewards(markets.clone()), U256::from(2_000u64));
+        assert_eq!(reward_token.balance_of(&alice), U256::from(2_000u64));
+        assert_eq!(controller.claim_rewards(markets.clone()), U256::zero());
+
+        // 1_000 left in the controller does not cover Bob's 1_400.
+        env.set_caller(bob);
+        let result = controller.try_claim_rewards(markets.clone());
+        assert_eq!(result, Err(Error::RewardsUnderfunded.into()));
+        assert_eq!(controller.get_accrued_rewards(bob), U256::zero());
+
+        let result = controller.try_claim_rewards(vec![oracle.address()]);
+        assert_eq!(result, Err(Error::MarketNotFound.into()));
+    }
+}
@@ -0,0 +1,19 @@
+use odra::prelude::*;
+
+/// Compliance contract with an open approval list, standing in for a KYC
+/// provider in tests.
+#[odra::module]
+pub struct TestCompliance {
+    approved: Mapping<Address, bool>,
+}
+
+#[odra::module]
+impl TestCompliance {
+    pub fn set_compliant(&mut self, account: Address, approved: bool) {
+        self.approved.set(&account, approved);
+    }
+
+    pub fn is_compliant(&self, account: Address) -> bool {
+        self.approved.get_or_default(&account)
+    }
+}
@@ -0,0 +1,122 @@
+use odra::casper_types::U256;
+use odra::prelude::*;
+use odra::ContractRef;
+use odra_modules::access::{Role, DEFAULT_ADMIN_ROLE};
+
+use crate::a_token::ATokenContractRef;
+use crate::admin_access::AdminAccess;
+use crate::math::{wad_mul, WAD_U128};
+use crate::types::{RateModel, RiskParams};
+
+const MARKET_ADMIN_ROLE: Role = *b"MARKET_ADMIN_ROLE_______________";
+
+/// `LendingMarket` storage as version 1 laid it out, so tests can upgrade
+/// state written by the old layout.
+#[odra::module]
+pub struct LendingMarketV1 {
+    access_control: SubModule<AdminAccess>,
+    links: SubModule<MarketLinksV1>,
+    rate_model: Var<RateModel>,
+    risk_params: Var<RiskParams>,
+    cash: Var<U256>,
+    total_borrows: Var<U256>,
+    total_reserves: Var<U256>,
+    supply_index: Var<U256>,
+    borrow_index: Var<U256>,
+    last_accrual: Var<u64>,
+    borrow_balances: Mapping<Address, U256>,
+    liquidation_grace_period: Var<u64>,
+    version: Var<u32>,
+}
+
+#[odra::module]
+impl LendingMarketV1 {
+    pub fn init(
+        &mut self,
+        admin: Address,
+        asset: Address,
+        a_token: Address,
+        oracle: Address,
+        registry: Address,
+    ) {
+        self.access_control.init_admin(admin);
+        self.access_control
+            .unchecked_grant_role(&MARKET_ADMIN_ROLE, &admin);
+        self.access_control
+            .set_admin_role(&MARKET_ADMIN_ROLE, &DEFAULT_ADMIN_ROLE);
+        self.links.init(asset, a_token, oracle, registry);
+        let one = U256::from(WAD_U128);
+        self.supply_index.set(one);
+        self.borrow_index.set(one);
+        self.last_accrual.set(self.env().get_block_time());
+        self.version.set(1);
+    }
+
+    pub fn set_params(
+        &mut self,
+        rate_model: RateModel,
+        risk_params: RiskParams,
+        liquidation_grace_period: u64,
+    ) {
+        self.rate_model.set(rate_model);
+        self.risk_params.set(risk_params);
+        self.liquidation_grace_period.set(liquidation_grace_period);
+    }
+
+    pub fn set_a_token(&mut self, a_token: Address) {
+        self.links.a_token.set(a_token);
+    }
+
+    /// Books a deposit of `supplied` and a borrow of `principal` by `account`
+    /// the way v1 stored them: the balance keeps the principal only.
+    pub fn seed_position(&mut self, account: Address, supplied: U256, principal: U256) {
+        let a_token = self.links.a_token.get().unwrap();
+        ATokenContractRef::new(self.env(), a_token).mint(account, supplied);
+        self.borrow_balances.set(&account, principal);
+        self.total_borrows
+            .set(self.total_borrows.get_or_default() + principal);
+        self.cash
+            .set(self.cash.get_or_default() + supplied - principal);
+    }
+
+    /// Books a repayment of `amount` the way v1 did: principal first.
+    pub fn seed_repay(&mut self, account: Address, amount: U256) {
+        let balance = self.borrow_balances.get_or_default(&account);
+        self.borrow_balances.set(&account, balance - amount);
+        self.total_borrows
+            .set(self.total_borrows.get_or_default() - amount);
+        self.cash.set(self.cash.get_or_default() + amount);
+    }
+
+    /// Accrues interest from the initial index up to `borrow_index` the way v1
+    /// did: the total and reserves take it, the balances never do.
+    pub fn seed_interest(&mut self, borrow_index: U256) {
+        let total_borrows = self.total_borrows.get_or_default();
+        let new_total = wad_mul(total_borrows, borrow_index);
+        let interest = new_total - total_borrows;
+        let reserve_factor = self.risk_params.get_or_default().reserve_factor;
+        self.total_reserves
+            .set(self.total_reserves.get_or_default() + wad_mul(interest, reserve_factor));
+        self.total_borrows.set(new_total);
+        self.borrow_index.set(borrow_index);
+    }
+}
+
+/// `MarketLinks` before the debt-token and rewards-controller links.
+#[odra::module]
+pub struct MarketLinksV1 {
+    asset: Var<Address>,
+    a_token: Var<Address>,
+    oracle: Var<Address>,
+    registry: Var<Address>,
+}
+
+#[odra::module]
+impl MarketLinksV1 {
+    pub fn init(&mut self, asset: Address, a_token: Address, oracle: Address, registry: Address) {
+        self.asset.set(asset);
+        self.a_token.set(a_token);
+        self.oracle.set(oracle);
+        self.registry.set(registry);
+    }
+}
@@ -0,0 +1,28 @@
+use odra::casper_types::U256;
+use odra::prelude::*;
+use odra::ContractRef;
+
+use crate::a_token::ATokenContractRef;
+
+/// Market that accepts every aToken transfer and forwards mints, so aToken
+/// calls can be measured on CasperVM without the real market's checks.
+///
+/// Compiled outside tests because CasperVM runs it from wasm.
+#[odra::module]
+pub struct StubMarket {}
+
+#[odra::module]
+impl StubMarket {
+    pub fn before_a_token_transfer(
+        &self,
+        owner: Address,
+        recipient: Address,
+        remaining_balance: U256,
+    ) {
+        let _ = (owner, recipient, remaining_balance);
+    }
+
+    pub fn mint(&mut self, a_token: Address, to: Address, amount: U256) {
+        ATokenContractRef::new(self.env(), a_token).mint(to, amount);
+    }
+}
@@ -0,0 +1,35 @@
+use odra::casper_types::U256;
+use odra::prelude::*;
+use odra_modules::cep18_token::Cep18;
+
+/// Plain CEP-18 with open minting, standing in for a market's underlying asset
+/// in tests.
+#[odra::module]
+pub struct TestToken {
+    token: SubModule<Cep18>,
+}
+
+#[odra::module]
+impl TestToken {
+    pub fn init(&mut self, name: String, symbol: String, decimals: u8) {
+        self.token.init(symbol, name, decimals, U256::zero());
+    }
+
+    delegate! {
+        to self.token {
+            fn name(&self) -> String;
+            fn symbol(&self) -> String;
+            fn decimals(&self) -> u8;
+            fn total_supply(&self) -> U256;
+            fn balance_of(&self, address: &Address) -> U256;
+            fn allowance(&self, owner: &Address, spender: &Address) -> U256;
+            fn approve(&mut self, spender: &Address, amount: &U256);
+            fn transfer(&mut self, recipient: &Address, amount: &U256);
+            fn transfer_from(&mut self, owner: &Address, recipient: &Address, amount: &U256);
+        }
+    }
+
+    pub fn mint(&mut self, to: Address, amount: U256) {
+        self.token.raw_mint(&to, &amount);
+    }
+}
@@ -0,0 +1,113 @@
+use odra::prelude::*;
+
+use crate::errors::Error;
+use crate::events::{
+    TimelockActionCancelled, TimelockActionExecuted, TimelockActionQueued, TimelockDelayUpdated,
+};
+use crate::types::{MarketAction, QueuedAction, TimelockStatus};
+
+/// Window after the ETA during which a queued action can still be executed.
+pub const TIMELOCK_GRACE_PERIOD_MILLIS: u64 = 14 * 24 * 60 * 60 * 1000;
+
+/// Queue of delayed market actions, embedded by `MarketRegistry`.
+///
+/// Invariants (MVP):
+/// - An action executes at most once, only between its ETA and ETA + grace period.
+/// - Cancelled or executed actions stay readable for auditability.
+/// - Role checks are left to the embedding contract.
+#[odra::module(
+    events = [
+        TimelockActionQueued,
+        TimelockActionCancelled,
+        TimelockActionExecuted,
+        TimelockDelayUpdated
+    ],
+    errors = Error
+)]
+pub struct Timelock {
+    delay: Var<u64>,
+    action_count: Var<u64>,
+    actions: Mapping<u64, QueuedAction>,
+}
+
+#[odra::module]
+impl Timelock {
+    pub fn set_delay(&mut self, delay_millis: u64) {
+        self.delay.set(delay_millis);
+        self.env().emit_event(TimelockDelayUpdated { delay_millis });
+    }
+
+    pub fn get_delay(&self) -> u64 {
+        self.delay.get_or_default()
+    }
+
+    pub fn queue(&mut self, asset: Address, action: MarketAction) -> u64 {
+        let eta = self
+            .env()
+            .get_block_time()
+            .checked_add(self.delay.get_or_default())
+            .unwrap_or_else(|| self.env().revert(Error::MathOverflow));
+        let id = self.action_count.get_or_default();
+        let queued = QueuedAction {
+            asset,
+            action: action.clone(),
+            eta,
+            status: TimelockStatus::Queued,
+        };
+        self.actions.set(&id, queued);
+        self.action_count.set(id + 1);
+        self.env().emit_event(TimelockActionQueued {
+            id,
+            asset,
+            eta,
+        });
+        id
+    }
+
+    pub fn cancel(&mut self, id: u64) {
+        let mut queued = self.get_queued(id);
+        queued.status = TimelockStatus::Cancelled;
+        let asset = queued.asset;
+        self.actions.set(&id, queued);
+        self.env().emit_event(TimelockActionCancelled { id, asset });
+    }
+
+    /// Marks a ready action as executed and returns it for the caller to apply.
+    pub fn execute(&mut self, id: u64) -> QueuedAction {
+        let mut queued = self.get_queued(id);
+        let now = self.env().get_block_time();
+        if now < queued.eta {
+            self.env().revert(Error::TimelockNotReady);
+        }
+        if now > queued.eta.saturating_add(TIMELOCK_GRACE_PERIOD_MILLIS) {
+            self.env().revert(Error::TimelockActionExpired);
+        }
+        queued.status = TimelockStatus::Executed;
+        self.actions.set(&id, queued.clone());
+        self.env().emit_event(TimelockActionExecuted {
+            id,
+            asset: queued.asset,
+        });
+        queued
+    }
+
+    pub fn get_action(&self, id: u64) -> QueuedAction {
+        self.actions
+            .get(&id)
+            .unwrap_or_revert_with(&self.env(), Error::TimelockActionNotQueued)
+    }
+
+    pub fn get_action_count(&self) -> u64 {
+        self.action_count.get_or_default()
+    }
+}
+
+impl Timelock {
+    fn get_queued(&self, id: u64) -> QueuedAction {
+        let queued = self.get_action(id);
+        if queued.status != TimelockStatus::Queued {
+            self.env().revert(Error::TimelockActionNotQueued);
+        }
+        queued
+    }
+}
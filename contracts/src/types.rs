@@ -1,3 +1,4 @@
+use odra::casper_types::bytesrepr::Bytes;
 use odra::casper_types::U256;
 use odra::prelude::*;
 
@@ -22,6 +23,19 @@ pub struct RiskParams {
     pub supply_cap: U256,
 }
 
+/// Everything `LendingMarket::init` needs, in one argument so the entry points
+/// the market factory generates from `init` stay short.
+#[odra::odra_type]
+pub struct MarketConfig {
+    pub admin: Address,
+    pub asset: Address,
+    pub a_token: Address,
+    pub oracle: Address,
+    pub registry: Address,
+    pub rate_model: RateModel,
+    pub risk_params: RiskParams,
+}
+
 #[odra::odra_type]
 pub struct PauseFlags {
     pub supply_paused: bool,
@@ -31,15 +45,192 @@ pub struct PauseFlags {
     pub liquidation_paused: bool,
 }
 
+/// Lifecycle of a listed market; transitions are driven by `MarketRegistry`.
+#[odra::odra_type]
+pub enum MarketStatus {
+    /// All actions allowed.
+    Active,
+    /// No new supply or borrow; exits and liquidations continue.
+    Frozen,
+    /// Wind-down: positions liquidatable at any health factor, rates ramp up.
+    Deprecated,
+    /// Terminal: no open borrows remain, only withdrawals are meaningful.
+    Closed,
+}
+
+/// Flags raised by a guardian; they lapse at `expires_at` unless an admin extends them.
+#[odra::odra_type]
+pub struct GuardianPause {
+    pub flags: PauseFlags,
+    pub expires_at: Timestamp,
+}
+
+/// Why and when pause flags last changed.
+#[odra::odra_type]
+pub struct PauseReason {
+    pub code: u32,
+    pub updated_at: Timestamp,
+}
+
 #[odra::odra_type]
 pub struct OraclePrice {
     pub price: U256,
     pub last_updated: Timestamp,
 }
 
+#[odra::odra_type]
+pub struct ExchangeRateFeed {
+    pub base_asset: Address,
+    pub rate_source: Address,
+    pub rate_entrypoint: String,
+    pub max_growth_per_sec: U256,
+    pub max_drop_per_update: U256,
+    pub decimals: u8,
+    pub last_rate: U256,
+    pub last_updated: Timestamp,
+}
+
+#[odra::odra_type]
+pub enum MarketAction {
+    RiskParams(RiskParams),
+    RateModel(RateModel),
+    /// Protocol-wide; queued under the registry's own address.
+    EModeCategory(EModeCategory),
+    /// Moves the queued asset into a category (0 takes it out).
+    AssetEModeCategory(u8),
+    DebtCeiling(DebtCeiling),
+    LiquidationGracePeriod(u64),
+    /// Registry-wide; queued under the registry's own address.
+    TimelockDelay(u64),
+}
+
+#[odra::odra_type]
+pub enum TimelockStatus {
+    Queued,
+    Cancelled,
+    Executed,
+}
+
+#[odra::odra_type]
+pub struct QueuedAction {
+    pub asset: Address,
+    pub action: MarketAction,
+    pub eta: Timestamp,
+    pub status: TimelockStatus,
+}
+
+#[odra::odra_type]
+pub enum ProposalStatus {
+    Pending,
+    Cancelled,
+    Executed,
+}
+
+/// A call the governance executor makes once enough signers approve it.
+///
+/// `args` holds the serialized `RuntimeArgs` for `entry_point` on `target`.
+#[odra::odra_type]
+pub struct GovernanceProposal {
+    pub proposer: Address,
+    pub target: Address,
+    pub entry_point: String,
+    pub args: Bytes,
+    pub expires_at: Timestamp,
+    pub status: ProposalStatus,
+}
+
 #[odra::odra_type]
 pub struct MarketAddresses {
     pub market: Address,
     pub a_token: Address,
     pub oracle: Address,
+    /// Set by `MarketRegistry::link_debt_token`.
+    pub debt_token: Option<Address>,
+}
+
+/// Installed `LendingMarketFactory` and `ATokenFactory` contracts that
+/// `MarketRegistry::create_market` deploys new markets from.
+#[odra::odra_type]
+pub struct MarketTemplates {
+    pub market_factory: Address,
+    pub a_token_factory: Address,
+}
+
+/// Snapshot of one market as returned by `MarketRegistry::list_markets`.
+#[odra::odra_type]
+pub struct MarketSummary {
+    pub asset: Address,
+    pub addresses: MarketAddresses,
+    pub pause_flags: PauseFlags,
+    pub status: MarketStatus,
+    pub risk_params: RiskParams,
+    pub rate_model: RateModel,
+    pub cash: U256,
+    pub total_borrows: U256,
+    pub total_reserves: U256,
+    pub borrow_rate_per_sec: U256,
+    pub supply_rate_per_sec: U256,
+}
+
+/// Value recorded at a block time; later writes in the same block overwrite it.
+#[odra::odra_type]
+pub struct Checkpoint {
+    pub timestamp: Timestamp,
+    pub value: U256,
+}
+
+/// Reward tokens emitted per second to one market's suppliers and borrowers.
+#[derive(Default)]
+#[odra::odra_type]
+pub struct RewardEmission {
+    pub supply_per_sec: U256,
+    pub borrow_per_sec: U256,
+}
+
+/// Rewards per unit of balance (WAD) accrued since emissions started.
+#[derive(Default)]
+#[odra::odra_type]
+pub struct RewardIndex {
+    pub index: U256,
+    pub last_updated: Timestamp,
+}
+
+/// Cumulative volume one referral code brought to one market.
+#[derive(Default)]
+#[odra::odra_type]
+pub struct ReferralVolume {
+    pub supplied: U256,
+    pub borrowed: U256,
+}
+
+/// Risk parameters for positions in correlated assets; id 0 means "no category".
+#[odra::odra_type]
+pub struct EModeCategory {
+    pub id: u8,
+    pub collateral_factor: U256,
+    pub liquidation_threshold: U256,
+    pub liquidation_bonus: U256,
+    pub label: String,
+}
+
+/// Cap on a market's total debt; an `amount` of zero means no ceiling.
+///
+/// `in_usd` measures the debt at the oracle price (WAD) instead of in
+/// underlying units.
+#[derive(Default)]
+#[odra::odra_type]
+pub struct DebtCeiling {
+    pub amount: U256,
+    pub in_usd: bool,
+}
+
+/// Who may supply to, borrow from, receive aTokens of, or liquidate in a market.
+#[odra::odra_type]
+pub enum AccessMode {
+    /// Anyone.
+    Open,
+    /// Accounts on the market's allowlist in `MarketRegistry`.
+    Allowlist,
+    /// Accounts the compliance contract at this address approves.
+    Compliance(Address),
 }
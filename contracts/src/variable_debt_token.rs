@@ -0,0 +1,142 @@
+use odra::casper_types::U256;
+use odra::prelude::*;
+use odra::ContractRef;
+use odra_modules::cep18::events::{Burn, Mint};
+
+use crate::errors::Error;
+use crate::lending_market::LendingMarketContractRef;
+
+/// Market-minted borrow receipt that shows debt in wallets and explorers.
+///
+/// Invariants (MVP):
+/// - `balance_of` and `total_supply` read the market's debt, so the token shows
+///   exactly what the market will collect, including positions opened before
+///   it was linked.
+/// - Only the market mints and burns, on borrow, repay and liquidate; both only
+///   emit `Mint`/`Burn` so indexers see the change, and store nothing.
+/// - Transfers and approvals always revert; debt cannot be handed to others.
+#[odra::module(events = [Mint, Burn], errors = Error)]
+pub struct VariableDebtToken {
+    name: Var<String>,
+    symbol: Var<String>,
+    decimals: Var<u8>,
+    market: Var<Address>,
+}
+
+#[odra::module]
+impl VariableDebtToken {
+    pub fn init(&mut self, name: String, symbol: String, decimals: u8, market: Address) {
+        self.name.set(name);
+        self.symbol.set(symbol);
+        self.decimals.set(decimals);
+        self.market.set(market);
+    }
+
+    pub fn name(&self) -> String {
+        self.name.get_or_default()
+    }
+
+    pub fn symbol(&self) -> String {
+        self.symbol.get_or_default()
+    }
+
+    pub fn decimals(&self) -> u8 {
+        self.decimals.get_or_default()
+    }
+
+    /// Debt of `address` in underlying units at the market's last accrual.
+    pub fn balance_of(&self, address: &Address) -> U256 {
+        self.load_market().get_borrow_balance(*address)
+    }
+
+    pub fn total_supply(&self) -> U256 {
+        self.load_market().get_total_borrows()
+    }
+
+    pub fn allowance(&self, _owner: &Address, _spender: &Address) -> U256 {
+        U256::zero()
+    }
+
+    pub fn approve(&mut self, _spender: &Address, _amount: &U256) {
+        self.env().revert(Error::DebtTokenNotTransferable);
+    }
+
+    pub fn transfer(&mut self, _recipient: &Address, _amount: &U256) {
+        self.env().revert(Error::DebtTokenNotTransferable);
+    }
+
+    pub fn transfer_from(&mut self, _owner: &Address, _recipient: &Address, _amount: &U256) {
+        self.env().revert(Error::DebtTokenNotTransferable);
+    }
+
+    /// Records that `to` borrowed `amount`; the balance itself is the market's.
+    pub fn mint(&mut self, to: Address, amount: U256) {
+        self.ensure_market();
+        self.env().emit_event(Mint {
+            recipient: to,
+            amount,
+        });
+    }
+
+    /// Records that `amount` of `from`'s debt was repaid or liquidated.
+    pub fn burn(&mut self, from: Address, amount: U256) {
+        self.ensure_market();
+        self.env().emit_event(Burn {
+            owner: from,
+            amount,
+        });
+    }
+
+    pub fn get_market(&self) -> Address {
+        self.market
+            .get()
+            .unwrap_or_revert_with(&self.env(), Error::InvalidParam)
+    }
+}
+
+impl VariableDebtToken {
+    fn load_market(&self) -> LendingMarketContractRef {
+        LendingMarketContractRef::new(self.env(), self.get_market())
+    }
+
+    fn ensure_market(&self) {
+        if self.env().caller() != self.get_market() {
+            self.env().revert(Error::Unauthorized);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use odra::host::Deployer;
+
+    #[test]
+    fn debt_cannot_be_transferred_or_minted_by_others() {
+        let env = odra_test::env();
+        let market = env.get_account(0);
+        let user = env.get_account(1);
+        let init_args = VariableDebtTokenInitArgs {
+            name: "Anchor Variable Debt".to_string(),
+            symbol: "vdTKN".to_string(),
+            decimals: 9,
+            market,
+        };
+        let mut token = VariableDebtToken::deploy(&env, init_args);
+
+        env.set_caller(user);
+        let other = env.get_account(2);
+        let result = token.try_transfer(&other, &U256::one());
+        assert_eq!(result, Err(Error::DebtTokenNotTransferable.into()));
+        let result = token.try_transfer_from(&other, &user, &U256::one());
+        assert_eq!(result, Err(Error::DebtTokenNotTransferable.into()));
+        let result = token.try_approve(&other, &U256::one());
+        assert_eq!(result, Err(Error::DebtTokenNotTransferable.into()));
+        assert_eq!(token.allowance(&user, &other), U256::zero());
+
+        let result = token.try_mint(user, U256::one());
+        assert_eq!(result, Err(Error::Unauthorized.into()));
+        let result = token.try_burn(user, U256::one());
+        assert_eq!(result, Err(Error::Unauthorized.into()));
+    }
+}